@@ -0,0 +1,612 @@
+use {
+    binary_option::state::BinaryOption,
+    binary_option_client::{accounts::Pool, rpc::BinaryOptionClient},
+    clap::{
+        crate_description, crate_name, crate_version, App, AppSettings, Arg, ArgMatches, SubCommand,
+    },
+    solana_client::{rpc_config::RpcProgramAccountsConfig, rpc_filter::RpcFilterType},
+    solana_sdk::{
+        instruction::Instruction,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::{read_keypair_file, Keypair, Signer},
+    },
+    spl_token::state::Account,
+    std::{error::Error, str::FromStr},
+};
+
+type CliResult = Result<(), Box<dyn Error>>;
+
+fn is_pubkey(value: String) -> Result<(), String> {
+    Pubkey::from_str(&value)
+        .map(|_| ())
+        .map_err(|e| format!("{}: {}", value, e))
+}
+
+fn is_amount(value: String) -> Result<(), String> {
+    value
+        .parse::<u64>()
+        .map(|_| ())
+        .map_err(|e| format!("{}: {}", value, e))
+}
+
+fn pubkey_of(matches: &ArgMatches, name: &str) -> Option<Pubkey> {
+    matches
+        .value_of(name)
+        .map(|value| Pubkey::from_str(value).unwrap())
+}
+
+fn amount_of(matches: &ArgMatches, name: &str) -> u64 {
+    matches.value_of(name).unwrap().parse().unwrap()
+}
+
+fn keypair_of(matches: &ArgMatches, name: &str) -> Result<Keypair, Box<dyn Error>> {
+    let path = matches.value_of(name).unwrap();
+    read_keypair_file(path).map_err(|e| format!("Failed to read keypair {}: {}", path, e).into())
+}
+
+fn pool_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("pool")
+        .value_name("POOL")
+        .validator(is_pubkey)
+        .required(true)
+        .index(1)
+        .help("Address of the pool")
+}
+
+fn amount_arg<'a, 'b>(name: &'a str, long: &'b str, help: &'b str) -> Arg<'a, 'b> {
+    Arg::with_name(name)
+        .long(long)
+        .value_name("AMOUNT")
+        .validator(is_amount)
+        .takes_value(true)
+        .required(true)
+        .help(help)
+}
+
+fn keypair_arg<'a, 'b>(name: &'a str, help: &'b str) -> Arg<'a, 'b> {
+    Arg::with_name(name)
+        .long(name)
+        .value_name("KEYPAIR")
+        .takes_value(true)
+        .help(help)
+}
+
+fn command_create(client: &BinaryOptionClient, matches: &ArgMatches) -> CliResult {
+    let escrow_mint = pubkey_of(matches, "escrow_mint").unwrap();
+    let decimals = matches.value_of("decimals").unwrap().parse()?;
+    let expiry = matches.value_of("expiry").unwrap_or("0").parse()?;
+    let pool = client.initialize(&escrow_mint, decimals, expiry)?;
+    println!("Created pool {}", pool);
+    Ok(())
+}
+
+/// Creates the position accounts of both traders if needed, then trades
+fn trade_instructions(
+    pool: &Pool,
+    matches: &ArgMatches,
+    payer: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+) -> Vec<Instruction> {
+    let mut instructions = pool.create_position_accounts(payer, buyer);
+    instructions.extend(pool.create_position_accounts(payer, seller));
+    instructions.push(pool.trade(
+        buyer,
+        seller,
+        amount_of(matches, "size"),
+        amount_of(matches, "buy_price"),
+        amount_of(matches, "sell_price"),
+    ));
+    instructions
+}
+
+fn settle_instruction(pool: &Pool, matches: &ArgMatches, owner: &Pubkey) -> Instruction {
+    let winning_mint = match matches.value_of("winner").unwrap() {
+        "long" => pool.state.long_mint_account_pubkey,
+        _ => pool.state.short_mint_account_pubkey,
+    };
+    pool.settle(&winning_mint, owner)
+}
+
+/// Pays out to `--destination` with the payer signing as the collector, or else to the
+/// associated token account of `--collector`
+fn collect_instruction(pool: &Pool, matches: &ArgMatches, payer: &Pubkey) -> Instruction {
+    match pubkey_of(matches, "destination") {
+        Some(destination) => pool.collect_to(payer, &destination),
+        None => pool.collect(&pubkey_of(matches, "collector").unwrap_or(*payer)),
+    }
+}
+
+fn command_trade(client: &BinaryOptionClient, matches: &ArgMatches) -> CliResult {
+    let pool = client.get_pool(&pubkey_of(matches, "pool").unwrap())?;
+    let buyer = keypair_of(matches, "buyer")?;
+    let seller = keypair_of(matches, "seller")?;
+    let instructions = trade_instructions(
+        &pool,
+        matches,
+        &client.payer.pubkey(),
+        &buyer.pubkey(),
+        &seller.pubkey(),
+    );
+    let signature = client.send(&instructions, &[&buyer, &seller])?;
+    println!("Signature: {}", signature);
+    Ok(())
+}
+
+fn command_settle(client: &BinaryOptionClient, matches: &ArgMatches) -> CliResult {
+    let pool = client.get_pool(&pubkey_of(matches, "pool").unwrap())?;
+    let signature = match matches.value_of("owner") {
+        Some(_) => {
+            let owner = keypair_of(matches, "owner")?;
+            client.send(
+                &[settle_instruction(&pool, matches, &owner.pubkey())],
+                &[&owner],
+            )?
+        }
+        None => client.send(
+            &[settle_instruction(&pool, matches, &client.payer.pubkey())],
+            &[],
+        )?,
+    };
+    println!("Signature: {}", signature);
+    Ok(())
+}
+
+fn command_collect(client: &BinaryOptionClient, matches: &ArgMatches) -> CliResult {
+    let pool = client.get_pool(&pubkey_of(matches, "pool").unwrap())?;
+    let signature = client.send(
+        &[collect_instruction(&pool, matches, &client.payer.pubkey())],
+        &[],
+    )?;
+    println!("Signature: {}", signature);
+    Ok(())
+}
+
+fn command_show(client: &BinaryOptionClient, matches: &ArgMatches) -> CliResult {
+    let pool = client.get_pool(&pubkey_of(matches, "pool").unwrap())?;
+    let state = &pool.state;
+    println!("Pool: {}", pool.address);
+    println!("{:#?}", state);
+    let long_supply = client
+        .rpc
+        .get_token_supply(&state.long_mint_account_pubkey)?;
+    let short_supply = client
+        .rpc
+        .get_token_supply(&state.short_mint_account_pubkey)?;
+    let escrow = client
+        .rpc
+        .get_token_account_balance(&state.escrow_account_pubkey)?;
+    println!("Long supply: {}", long_supply.amount);
+    println!("Short supply: {}", short_supply.amount);
+    println!("Escrow balance: {}", escrow.amount);
+    Ok(())
+}
+
+/// Token balance of an account that may not exist
+fn balance_of(client: &BinaryOptionClient, address: &Pubkey) -> Result<u64, Box<dyn Error>> {
+    match client
+        .rpc
+        .get_account_with_commitment(address, client.rpc.commitment())?
+        .value
+    {
+        Some(account) => Ok(Account::unpack(&account.data)?.amount),
+        None => Ok(0),
+    }
+}
+
+fn command_positions(client: &BinaryOptionClient, matches: &ArgMatches) -> CliResult {
+    let wallet = pubkey_of(matches, "wallet").unwrap();
+    let pools = client.rpc.get_program_accounts_with_config(
+        &client.program_id,
+        RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::DataSize(BinaryOption::LEN as u64)]),
+            ..RpcProgramAccountsConfig::default()
+        },
+    )?;
+    for (address, account) in pools {
+        let state = match binary_option_client::accounts::decode_binary_option(&account.data) {
+            Ok(state) => state,
+            Err(_) => continue,
+        };
+        let pool = Pool::new(client.program_id, address, state);
+        let accounts = pool.position_accounts(&wallet);
+        let long = balance_of(client, &accounts.long)?;
+        let short = balance_of(client, &accounts.short)?;
+        if long > 0 || short > 0 {
+            println!(
+                "{}: {} long, {} short ({:?})",
+                address, long, short, pool.state.status
+            );
+        }
+    }
+    Ok(())
+}
+
+fn app<'a, 'b>() -> App<'a, 'b> {
+    App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("url")
+                .short("u")
+                .long("url")
+                .value_name("URL")
+                .takes_value(true)
+                .global(true)
+                .default_value("http://localhost:8899")
+                .help("JSON RPC URL of the cluster"),
+        )
+        .arg(
+            Arg::with_name("keypair")
+                .long("keypair")
+                .value_name("KEYPAIR")
+                .takes_value(true)
+                .global(true)
+                .help("Fee payer, and owner of created pools [default: ~/.config/solana/id.json]"),
+        )
+        .arg(
+            Arg::with_name("program_id")
+                .long("program-id")
+                .value_name("PUBKEY")
+                .validator(is_pubkey)
+                .takes_value(true)
+                .global(true)
+                .help("Binary option program [default: the deployed program]"),
+        )
+        .subcommand(
+            SubCommand::with_name("create")
+                .about("Create a pool owned by the fee payer")
+                .arg(
+                    Arg::with_name("escrow_mint")
+                        .value_name("ESCROW_MINT")
+                        .validator(is_pubkey)
+                        .required(true)
+                        .index(1)
+                        .help("Mint of the collateral"),
+                )
+                .arg(
+                    Arg::with_name("decimals")
+                        .long("decimals")
+                        .value_name("N")
+                        .takes_value(true)
+                        .required(true)
+                        .help("A complete set is worth 10^N of the collateral"),
+                )
+                .arg(
+                    Arg::with_name("expiry")
+                        .long("expiry")
+                        .value_name("UNIX_TIMESTAMP")
+                        .takes_value(true)
+                        .help("Trading stops at this time [default: never]"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("trade")
+                .about("Trade contracts between a buyer and a seller")
+                .arg(pool_arg())
+                .arg(keypair_arg("buyer", "Keypair of the buyer").required(true))
+                .arg(keypair_arg("seller", "Keypair of the seller").required(true))
+                .arg(amount_arg("size", "size", "Number of contracts"))
+                .arg(amount_arg(
+                    "buy_price",
+                    "buy-price",
+                    "Price paid by the buyer",
+                ))
+                .arg(amount_arg(
+                    "sell_price",
+                    "sell-price",
+                    "Price paid by the seller",
+                )),
+        )
+        .subcommand(
+            SubCommand::with_name("settle")
+                .about("Settle a pool")
+                .arg(pool_arg())
+                .arg(
+                    Arg::with_name("winner")
+                        .value_name("SIDE")
+                        .possible_values(&["long", "short"])
+                        .required(true)
+                        .index(2)
+                        .help("Winning side"),
+                )
+                .arg(keypair_arg(
+                    "owner",
+                    "Keypair of the pool owner [default: the fee payer]",
+                )),
+        )
+        .subcommand(
+            SubCommand::with_name("collect")
+                .about("Collect the payout of a settled pool")
+                .arg(pool_arg())
+                .arg(
+                    Arg::with_name("collector")
+                        .long("collector")
+                        .value_name("PUBKEY")
+                        .validator(is_pubkey)
+                        .takes_value(true)
+                        .help("Wallet to collect for [default: the fee payer]"),
+                )
+                .arg(
+                    Arg::with_name("destination")
+                        .long("destination")
+                        .value_name("ACCOUNT")
+                        .validator(is_pubkey)
+                        .takes_value(true)
+                        .conflicts_with("collector")
+                        .help(
+                            "Collateral account to pay out to, signed by the fee payer as the \
+                             collector [default: the collector's associated token account]",
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("Show a pool along with its mint supplies and escrow balance")
+                .arg(pool_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("positions")
+                .about("List the positions of a wallet in every pool")
+                .arg(
+                    Arg::with_name("wallet")
+                        .value_name("WALLET")
+                        .validator(is_pubkey)
+                        .required(true)
+                        .index(1)
+                        .help("Owner of the associated token accounts"),
+                ),
+        )
+}
+
+fn main() -> CliResult {
+    let matches = app().get_matches();
+
+    let keypair_path = match matches.value_of("keypair") {
+        Some(path) => path.to_string(),
+        None => format!(
+            "{}/.config/solana/id.json",
+            std::env::var("HOME").unwrap_or_else(|_| ".".to_string())
+        ),
+    };
+    let payer = read_keypair_file(&keypair_path)
+        .map_err(|e| format!("Failed to read keypair {}: {}", keypair_path, e))?;
+    let program_id = pubkey_of(&matches, "program_id").unwrap_or_else(binary_option::id);
+    let client = BinaryOptionClient::new(matches.value_of("url").unwrap(), program_id, payer);
+
+    match matches.subcommand() {
+        ("create", Some(matches)) => command_create(&client, matches),
+        ("trade", Some(matches)) => command_trade(&client, matches),
+        ("settle", Some(matches)) => command_settle(&client, matches),
+        ("collect", Some(matches)) => command_collect(&client, matches),
+        ("show", Some(matches)) => command_show(&client, matches),
+        ("positions", Some(matches)) => command_positions(&client, matches),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, binary_option::instruction, binary_option_client::accounts::decode_binary_option,
+    };
+
+    fn test_pool() -> Pool {
+        let mut state = decode_binary_option(&[0; BinaryOption::LEN]).unwrap();
+        state.escrow_mint_account_pubkey = Pubkey::new_unique();
+        state.escrow_account_pubkey = Pubkey::new_unique();
+        state.long_mint_account_pubkey = Pubkey::new_unique();
+        state.short_mint_account_pubkey = Pubkey::new_unique();
+        Pool::new(binary_option::id(), Pubkey::new_unique(), state)
+    }
+
+    fn parse(args: &[&str]) -> Result<ArgMatches<'static>, clap::Error> {
+        app()
+            .get_matches_from_safe(std::iter::once("binary-option-cli").chain(args.iter().copied()))
+    }
+
+    fn subcommand<'a>(matches: &'a ArgMatches<'a>, name: &str) -> &'a ArgMatches<'a> {
+        matches.subcommand_matches(name).unwrap()
+    }
+
+    #[test]
+    fn test_parse_global_args() {
+        let program_id = Pubkey::new_unique().to_string();
+        let pool = Pubkey::new_unique().to_string();
+        let matches = parse(&["show", &pool]).unwrap();
+        assert_eq!(matches.value_of("url"), Some("http://localhost:8899"));
+        assert_eq!(matches.value_of("keypair"), None);
+        assert_eq!(pubkey_of(&matches, "program_id"), None);
+
+        let matches = parse(&[
+            "show",
+            &pool,
+            "--url",
+            "http://api.devnet.solana.com",
+            "--program-id",
+            &program_id,
+            "--keypair",
+            "payer.json",
+        ])
+        .unwrap();
+        assert_eq!(
+            matches.value_of("url"),
+            Some("http://api.devnet.solana.com")
+        );
+        assert_eq!(matches.value_of("keypair"), Some("payer.json"));
+        assert_eq!(
+            pubkey_of(&matches, "program_id").unwrap().to_string(),
+            program_id
+        );
+
+        assert!(parse(&["show", &pool, "--program-id", "not-a-pubkey"]).is_err());
+        assert!(parse(&[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_trade() {
+        let pool = Pubkey::new_unique().to_string();
+        let args = [
+            "trade",
+            &pool,
+            "--buyer",
+            "buyer.json",
+            "--seller",
+            "seller.json",
+            "--size",
+            "10",
+            "--buy-price",
+            "60",
+            "--sell-price",
+            "40",
+        ];
+        let matches = parse(&args).unwrap();
+        let trade = subcommand(&matches, "trade");
+        assert_eq!(pubkey_of(trade, "pool").unwrap().to_string(), pool);
+        assert_eq!(trade.value_of("buyer"), Some("buyer.json"));
+        assert_eq!(trade.value_of("seller"), Some("seller.json"));
+        assert_eq!(amount_of(trade, "size"), 10);
+        assert_eq!(amount_of(trade, "buy_price"), 60);
+        assert_eq!(amount_of(trade, "sell_price"), 40);
+
+        // Every argument is required
+        for missing in (2..args.len()).step_by(2) {
+            let mut partial = args.to_vec();
+            partial.drain(missing..missing + 2);
+            assert!(parse(&partial).is_err(), "{} is optional", args[missing]);
+        }
+
+        let mut invalid = args;
+        invalid[8] = "-1";
+        assert!(parse(&invalid).is_err());
+        let mut invalid = args;
+        invalid[1] = "not-a-pubkey";
+        assert!(parse(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_settle_and_collect() {
+        let pool = Pubkey::new_unique().to_string();
+        let wallet = Pubkey::new_unique().to_string();
+        assert!(parse(&["settle", &pool, "long"]).is_ok());
+        assert!(parse(&["settle", &pool, "short", "--owner", "owner.json"]).is_ok());
+        assert!(parse(&["settle", &pool, "both"]).is_err());
+        assert!(parse(&["settle", &pool]).is_err());
+
+        assert!(parse(&["collect", &pool]).is_ok());
+        assert!(parse(&["collect", &pool, "--collector", &wallet]).is_ok());
+        assert!(parse(&["collect", &pool, "--destination", &wallet]).is_ok());
+        assert!(parse(&["collect", &pool, "--collector", "not-a-pubkey"]).is_err());
+        assert!(parse(&[
+            "collect",
+            &pool,
+            "--collector",
+            &wallet,
+            "--destination",
+            &wallet
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_trade_instructions() {
+        let pool = test_pool();
+        let payer = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let seller = Pubkey::new_unique();
+        let pool_address = pool.address.to_string();
+        let matches = parse(&[
+            "trade",
+            &pool_address,
+            "--buyer",
+            "buyer.json",
+            "--seller",
+            "seller.json",
+            "--size",
+            "10",
+            "--buy-price",
+            "60",
+            "--sell-price",
+            "40",
+        ])
+        .unwrap();
+
+        let instructions = trade_instructions(
+            &pool,
+            subcommand(&matches, "trade"),
+            &payer,
+            &buyer,
+            &seller,
+        );
+        let mut expected = pool.create_position_accounts(&payer, &buyer);
+        expected.extend(pool.create_position_accounts(&payer, &seller));
+        expected.push(pool.trade(&buyer, &seller, 10, 60, 40));
+        assert_eq!(instructions, expected);
+    }
+
+    #[test]
+    fn test_settle_instruction() {
+        let pool = test_pool();
+        let owner = Pubkey::new_unique();
+        let pool_address = pool.address.to_string();
+        for (side, winning_mint) in [
+            ("long", pool.state.long_mint_account_pubkey),
+            ("short", pool.state.short_mint_account_pubkey),
+        ] {
+            let matches = parse(&["settle", &pool_address, side]).unwrap();
+            assert_eq!(
+                settle_instruction(&pool, subcommand(&matches, "settle"), &owner),
+                instruction::settle(binary_option::id(), pool.address, winning_mint, owner),
+            );
+        }
+    }
+
+    #[test]
+    fn test_collect_instruction() {
+        let pool = test_pool();
+        let payer = Pubkey::new_unique();
+        let collector = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let pool_address = pool.address.to_string();
+
+        let matches = parse(&["collect", &pool_address]).unwrap();
+        let collect = collect_instruction(&pool, subcommand(&matches, "collect"), &payer);
+        assert_eq!(collect, pool.collect(&payer));
+
+        let matches = parse(&[
+            "collect",
+            &pool_address,
+            "--collector",
+            &collector.to_string(),
+        ])
+        .unwrap();
+        let collect = collect_instruction(&pool, subcommand(&matches, "collect"), &payer);
+        assert_eq!(collect, pool.collect(&collector));
+        let collateral = pool.position_accounts(&collector).collateral;
+        assert!(collect
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == collateral));
+        assert!(collect.accounts.iter().all(|meta| !meta.is_signer));
+
+        let matches = parse(&[
+            "collect",
+            &pool_address,
+            "--destination",
+            &destination.to_string(),
+        ])
+        .unwrap();
+        let collect = collect_instruction(&pool, subcommand(&matches, "collect"), &payer);
+        assert_eq!(collect, pool.collect_to(&payer, &destination));
+        assert!(collect
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == payer && meta.is_signer));
+        assert!(collect
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == destination));
+    }
+}
@@ -35,21 +35,22 @@ def initialize_binary_option_instruction(
     token_account,
     system_account,
     rent_account,
-    decimals
+    decimals,
+    expiry_unix_timestamp=0,
 ):
     keys = [
         AccountMeta(pubkey=pool_account, is_signer=True, is_writable=True),
         AccountMeta(pubkey=escrow_mint_account, is_signer=False, is_writable=False),
         AccountMeta(pubkey=escrow_account, is_signer=True, is_writable=True),
-        AccountMeta(pubkey=long_token_mint_account, is_signer=True, is_writable=False),
-        AccountMeta(pubkey=short_token_mint_account, is_signer=True, is_writable=False),
-        AccountMeta(pubkey=mint_authority_account, is_signer=True, is_writable=False),
-        AccountMeta(pubkey=update_authority_account, is_signer=True, is_writable=False),
+        AccountMeta(pubkey=long_token_mint_account, is_signer=True, is_writable=True),
+        AccountMeta(pubkey=short_token_mint_account, is_signer=True, is_writable=True),
+        AccountMeta(pubkey=mint_authority_account, is_signer=True, is_writable=True),
+        AccountMeta(pubkey=update_authority_account, is_signer=True, is_writable=True),
         AccountMeta(pubkey=token_account, is_signer=False, is_writable=False),
         AccountMeta(pubkey=system_account, is_signer=False, is_writable=False),
         AccountMeta(pubkey=rent_account, is_signer=False, is_writable=False),
     ]
-    data = struct.pack("<BB", 0, decimals)
+    data = struct.pack("<BBq", 0, decimals, expiry_unix_timestamp)
     return TransactionInstruction(keys=keys, program_id=PublicKey(BINARY_OPTION_PROGRAM_ID), data=data)
 
 def trade_instruction(
@@ -70,6 +71,7 @@ def trade_instruction(
     size,
     buyer_price,
     seller_price,
+    fee_vault_account=None,
 ):
     keys = [
         AccountMeta(pubkey=pool_account, is_signer=False, is_writable=True),
@@ -87,6 +89,8 @@ def trade_instruction(
         AccountMeta(pubkey=escrow_authority_account, is_signer=False, is_writable=False),
         AccountMeta(pubkey=token_account, is_signer=False, is_writable=False),
     ]
+    if fee_vault_account is not None:
+        keys.append(AccountMeta(pubkey=fee_vault_account, is_signer=False, is_writable=True))
     data = struct.pack("<BQQQ", 1, size, buyer_price, seller_price)
     return TransactionInstruction(keys=keys, program_id=PublicKey(BINARY_OPTION_PROGRAM_ID), data=data)
 
@@ -114,19 +118,24 @@ def collect_instruction(
     escrow_account,
     escrow_authority_account,
     token_account,
+    fee_vault_account=None,
+    collector_is_signer=False,
 ):
+    # Without the collector's signature the payout has to go to the collector's associated token account
     keys = [
         AccountMeta(pubkey=pool_account, is_signer=False, is_writable=True),
-        AccountMeta(pubkey=collector_account, is_signer=False, is_writable=False),
+        AccountMeta(pubkey=collector_account, is_signer=collector_is_signer, is_writable=False),
         AccountMeta(pubkey=collector_long_token_account, is_signer=False, is_writable=True),
         AccountMeta(pubkey=collector_short_token_account, is_signer=False, is_writable=True),
         AccountMeta(pubkey=collector_collateral_account, is_signer=False, is_writable=True),
         AccountMeta(pubkey=long_token_mint_account, is_signer=False, is_writable=True),
         AccountMeta(pubkey=short_token_mint_account, is_signer=False, is_writable=True),
         AccountMeta(pubkey=escrow_account, is_signer=False, is_writable=True),
-        AccountMeta(pubkey=escrow_authority_account, is_signer=False, is_writable=True),
+        AccountMeta(pubkey=escrow_authority_account, is_signer=False, is_writable=False),
         AccountMeta(pubkey=token_account, is_signer=False, is_writable=False),
     ]
+    if fee_vault_account is not None:
+        keys.append(AccountMeta(pubkey=fee_vault_account, is_signer=False, is_writable=True))
     data = struct.pack("<B", 3)
     return TransactionInstruction(keys=keys, program_id=PublicKey(BINARY_OPTION_PROGRAM_ID), data=data)
 
@@ -138,7 +147,7 @@ class BinaryOption():
         self.cipher = Fernet(cfg["DECRYPTION_KEY"])
 
 
-    def initialize(self, api_endpoint, escrow_mint, decimals=2, skip_confirmation=True):
+    def initialize(self, api_endpoint, escrow_mint, decimals=2, expiry_unix_timestamp=0, skip_confirmation=True):
         msg = ""
         # Initalize Clinet
         client = Client(api_endpoint)
@@ -179,6 +188,7 @@ class BinaryOption():
             system_account,
             rent_account,
             decimals,
+            expiry_unix_timestamp,
         )
         tx = tx.add(init_binary_option_ix)
         msg += f" | Creating binary option"
@@ -391,14 +401,14 @@ class BinaryOption():
                 }
             )
         pubkey = 'B' * 32
-        raw_bytes = struct.unpack(f"<BQ?{pubkey}{pubkey}{pubkey}{pubkey}{pubkey}{pubkey}", pool_data)
+        raw_bytes = struct.unpack(f"<BQB{pubkey}{pubkey}{pubkey}{pubkey}{pubkey}{pubkey}QQQ{pubkey}{pubkey}{pubkey}qQB{pubkey}qBQH{pubkey}qBqqq{pubkey}{pubkey}HQ{pubkey}HQ{pubkey}{pubkey}{pubkey}{pubkey}{pubkey}{pubkey}{pubkey}", pool_data)
         i = 0
         pool = {}
         pool["decimals"] = raw_bytes[i] 
         i += 1
         pool["circulation"] = raw_bytes[i] 
         i += 1
-        pool["settled"] = raw_bytes[i] 
+        pool["status"] = raw_bytes[i]
         i += 1
         pool["escrow_mint"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
         i += 32
@@ -412,6 +422,86 @@ class BinaryOption():
         i += 32
         pool["winning_side"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
         i += 32
+        pool["amm_liquidity"] = raw_bytes[i]
+        i += 1
+        pool["amm_long_outstanding"] = raw_bytes[i]
+        i += 1
+        pool["amm_short_outstanding"] = raw_bytes[i]
+        i += 1
+        pool["amm_vault"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["amm_long_account"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["amm_short_account"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["expiry_unix_timestamp"] = raw_bytes[i]
+        i += 1
+        pool["void_price"] = raw_bytes[i]
+        i += 1
+        pool["resolution_source"] = raw_bytes[i]
+        i += 1
+        pool["oracle_feed"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["strike_price"] = raw_bytes[i]
+        i += 1
+        pool["comparison"] = raw_bytes[i]
+        i += 1
+        pool["max_staleness_slots"] = raw_bytes[i]
+        i += 1
+        pool["max_confidence_bps"] = raw_bytes[i]
+        i += 1
+        pool["oracle_program"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["oracle_max_publish_delay"] = raw_bytes[i]
+        i += 1
+        pool["market_type"] = raw_bytes[i]
+        i += 1
+        pool["scalar_lower_bound"] = raw_bytes[i]
+        i += 1
+        pool["scalar_upper_bound"] = raw_bytes[i]
+        i += 1
+        pool["resolved_value"] = raw_bytes[i]
+        i += 1
+        pool["fee_vault"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["protocol_fee_recipient"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["protocol_fee_bps"] = raw_bytes[i]
+        i += 1
+        pool["protocol_fees_accrued"] = raw_bytes[i]
+        i += 1
+        pool["creator_fee_recipient"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["creator_fee_bps"] = raw_bytes[i]
+        i += 1
+        pool["creator_fees_accrued"] = raw_bytes[i]
+        i += 1
+        pool["pending_owner"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["payer"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["fee_vault_payer"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["order_book_vault"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["cpmm_vault"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["cpmm_lp_mint"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["bond_vault"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["committee"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["order_book"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["cpmm"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["cpmm_long_token_account"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["cpmm_short_token_account"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
+        pool["resolver"] = base58.b58encode(bytes(raw_bytes[i:i+32])).decode('ascii')
+        i += 32
         return pool
 
     def topup(self, api_endpoint, to, amount=None, skip_confirmation=True):
@@ -0,0 +1,1931 @@
+//! Throws instruction sequences built by attackers at live pools and the markets next to them, and
+//! checks after every transaction that they stay solvent and honest users keep their funds.
+//!
+//! `cargo hfuzz run binary-option-instructions` from this directory runs it.
+
+use {
+    arbitrary::Arbitrary,
+    binary_option::{
+        id, instruction,
+        processor::Processor,
+        state::{
+            BinaryOption, CategoricalMarket, MarketType, OrderSide, Outcome, PoolStatus,
+            PositionSide,
+        },
+    },
+    borsh::BorshDeserialize,
+    honggfuzz::fuzz,
+    solana_program::{
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction},
+        program_error::ProgramError,
+        program_pack::Pack,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        pubkey::Pubkey,
+        system_instruction, system_program, sysvar,
+    },
+    solana_program_test::{processor, BanksClient, ProgramTest, ProgramTestContext},
+    solana_sdk::{
+        account::{Account as SolanaAccount, AccountSharedData},
+        hash::Hash,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    },
+    spl_token::state::{Account, Mint},
+    std::{collections::HashMap, sync::OnceLock},
+};
+
+const DECIMALS: u8 = 2;
+const UNIT: u64 = 100;
+const COLLATERAL: u64 = 1_000_000;
+
+#[derive(Debug, Arbitrary, Clone, Copy, PartialEq, Eq, Hash)]
+enum Actor {
+    /// Owner of the honest pool
+    Owner,
+    Alice,
+    Bob,
+    /// Owner of the attacker pool
+    Mallory,
+    Trudy,
+}
+
+const ACTORS: [Actor; 5] = [
+    Actor::Owner,
+    Actor::Alice,
+    Actor::Bob,
+    Actor::Mallory,
+    Actor::Trudy,
+];
+
+impl Actor {
+    /// Only attackers sign the fuzzed transactions
+    fn is_attacker(self) -> bool {
+        matches!(self, Actor::Mallory | Actor::Trudy)
+    }
+}
+
+/// Every market is a pool with an AMM, a CPMM, an order book and a categorical market of the
+/// same owner next to it
+#[derive(Debug, Arbitrary, Clone, Copy, PartialEq, Eq, Hash)]
+enum Market {
+    /// Owned by `Actor::Owner`, only honest users and attackers trade in it
+    Honest,
+    /// Owned by `Actor::Mallory`, only attackers hold positions in it
+    Attacker,
+    /// Owned by `Actor::Mallory` and resolved by proposals `Actor::Trudy` arbitrates, without
+    /// fees so that orders can be placed
+    Optimistic,
+    /// Owned by `Actor::Mallory` and resolved by the votes of `Actor::Mallory`, `Actor::Trudy`
+    /// and `Actor::Alice`, without fees so that orders can be placed
+    Committee,
+}
+
+const MARKETS: [Market; 4] = [
+    Market::Honest,
+    Market::Attacker,
+    Market::Optimistic,
+    Market::Committee,
+];
+
+impl Market {
+    fn has_fees(self) -> bool {
+        matches!(self, Market::Honest | Market::Attacker)
+    }
+}
+
+/// Number of outcomes of every categorical market
+const OUTCOMES: usize = 3;
+
+/// Mirrors `Outcome`, which doesn't implement `Arbitrary`
+#[derive(Debug, Arbitrary, Clone, Copy)]
+enum FuzzOutcome {
+    Long,
+    Short,
+    Void,
+}
+
+impl From<FuzzOutcome> for Outcome {
+    fn from(outcome: FuzzOutcome) -> Self {
+        match outcome {
+            FuzzOutcome::Long => Outcome::Long,
+            FuzzOutcome::Short => Outcome::Short,
+            FuzzOutcome::Void => Outcome::Void,
+        }
+    }
+}
+
+#[derive(Debug, Arbitrary, Clone, Copy)]
+enum FuzzAccount {
+    Pool(Market),
+    Escrow(Market),
+    LongMint(Market),
+    ShortMint(Market),
+    EscrowAuthority(Market),
+    FeeVault(Market),
+    /// Copy of a pool owned by the system program
+    FakePool(Market),
+    /// Copy of an escrow owned by the system program
+    FakeEscrow(Market),
+    /// Copy of a long mint owned by the system program
+    FakeMint(Market),
+    AmmVault(Market),
+    Cpmm(Market),
+    LpMint(Market),
+    CpmmVault(Market),
+    OrderBook(Market),
+    OrderVault(Market),
+    Resolver(Market),
+    BondVault(Market),
+    Committee(Market),
+    Categorical(Market),
+    CategoricalEscrow(Market),
+    OutcomeMint(Market, u8),
+    EscrowMint,
+    Wallet(Actor),
+    Collateral(Actor),
+    LongTokens(Actor, Market),
+    ShortTokens(Actor, Market),
+    LpTokens(Actor, Market),
+    OutcomeTokens(Actor, Market, u8),
+    /// A token account that was never initialized
+    Uninitialized,
+    /// A system account without data
+    Empty,
+    /// An address without an account
+    Missing,
+    TokenProgram,
+    SystemProgram,
+    BinaryOptionProgram,
+    Rent,
+    Clock,
+}
+
+#[derive(Debug, Arbitrary)]
+enum FuzzKind {
+    Trade {
+        market: Market,
+        buyer: Actor,
+        seller: Actor,
+        size: u64,
+        buy_price: u64,
+        sell_price: u64,
+    },
+    MintSet {
+        market: Market,
+        user: Actor,
+        size: u64,
+    },
+    RedeemSet {
+        market: Market,
+        user: Actor,
+        size: u64,
+    },
+    Settle {
+        market: Market,
+        long_wins: bool,
+    },
+    Void {
+        market: Market,
+        void_price: u64,
+    },
+    Halt {
+        market: Market,
+    },
+    Resume {
+        market: Market,
+    },
+    Collect {
+        market: Market,
+        collector: Actor,
+    },
+    WithdrawFees {
+        market: Market,
+        recipient: Actor,
+    },
+    SweepDust {
+        market: Market,
+    },
+    ClosePool {
+        market: Market,
+    },
+    PlaceOrder {
+        market: Market,
+        trader: Actor,
+        bid: bool,
+        price: u64,
+        size: u64,
+    },
+    CancelOrder {
+        market: Market,
+        trader: Actor,
+        order_id: u64,
+    },
+    MatchOrders {
+        market: Market,
+        buyer: Actor,
+        seller: Actor,
+        bid_order_id: u64,
+        ask_order_id: u64,
+    },
+    AmmBuy {
+        market: Market,
+        user: Actor,
+        long: bool,
+        size: u64,
+        max_cost: u64,
+    },
+    AmmSell {
+        market: Market,
+        user: Actor,
+        long: bool,
+        size: u64,
+        min_proceeds: u64,
+    },
+    AmmWithdraw {
+        market: Market,
+    },
+    CpmmAddLiquidity {
+        market: Market,
+        provider: Actor,
+        amount: u64,
+    },
+    CpmmRemoveLiquidity {
+        market: Market,
+        provider: Actor,
+        shares: u64,
+    },
+    CpmmBuy {
+        market: Market,
+        user: Actor,
+        long: bool,
+        size: u64,
+        max_cost: u64,
+    },
+    CpmmSell {
+        market: Market,
+        user: Actor,
+        long: bool,
+        size: u64,
+        min_proceeds: u64,
+    },
+    ProposeOutcome {
+        market: Market,
+        proposer: Actor,
+        outcome: FuzzOutcome,
+    },
+    DisputeOutcome {
+        market: Market,
+        disputer: Actor,
+    },
+    FinalizeOutcome {
+        market: Market,
+        proposer: Actor,
+    },
+    ResolveDispute {
+        market: Market,
+        proposer: Actor,
+        disputer: Actor,
+        arbiter: Actor,
+        outcome: FuzzOutcome,
+    },
+    RefundBonds {
+        market: Market,
+        proposer: Actor,
+        disputer: Option<Actor>,
+    },
+    Vote {
+        market: Market,
+        voter: Actor,
+        outcome: FuzzOutcome,
+    },
+    CategoricalMintSet {
+        market: Market,
+        user: Actor,
+        size: u64,
+    },
+    CategoricalRedeemSet {
+        market: Market,
+        user: Actor,
+        size: u64,
+    },
+    CategoricalTrade {
+        market: Market,
+        buyer: Actor,
+        seller: Actor,
+        outcome: u8,
+        size: u64,
+        price: u64,
+    },
+    CategoricalSettle {
+        market: Market,
+        winning_outcome: u8,
+    },
+    CategoricalCollect {
+        market: Market,
+        collector: Actor,
+        outcome: u8,
+    },
+    /// Any instruction data with any accounts
+    Raw {
+        data: Vec<u8>,
+        accounts: Vec<FuzzAccount>,
+    },
+}
+
+/// Changes to the account list of an instruction, indices wrap around the list
+#[derive(Debug, Arbitrary)]
+enum Mutation {
+    Replace { index: u8, account: FuzzAccount },
+    Swap { first: u8, second: u8 },
+    Duplicate { from: u8, to: u8 },
+    Truncate { len: u8 },
+}
+
+#[derive(Debug, Arbitrary)]
+struct FuzzInstruction {
+    kind: FuzzKind,
+    mutations: Vec<Mutation>,
+}
+
+#[derive(Debug, Arbitrary)]
+struct FuzzData {
+    instructions: Vec<FuzzInstruction>,
+}
+
+/// Stubs that fail a cross-program invocation which escalates privileges, as the runtime does
+/// on-chain, instead of panicking like the program-test stubs they wrap
+struct CheckedSyscallStubs;
+
+/// The program-test stubs, replaced by `CheckedSyscallStubs` once the first program test has started
+static PROGRAM_TEST_STUBS: OnceLock<Box<dyn SyscallStubs>> = OnceLock::new();
+
+fn program_test_stubs() -> &'static dyn SyscallStubs {
+    PROGRAM_TEST_STUBS
+        .get()
+        .expect("program-test stubs are replaced")
+        .as_ref()
+}
+
+impl SyscallStubs for CheckedSyscallStubs {
+    fn sol_log(&self, message: &str) {
+        program_test_stubs().sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        program_test_stubs().sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        program_test_stubs().sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let signers = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &id()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        for meta in &instruction.accounts {
+            let info = account_infos
+                .iter()
+                .find(|info| *info.key == meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            if meta.is_writable && !info.is_writable {
+                return Err(ProgramError::InvalidArgument);
+            }
+            if meta.is_signer && !info.is_signer && !signers.contains(&meta.pubkey) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+        }
+        program_test_stubs().sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        program_test_stubs().sol_get_last_restart_slot(var_addr)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        program_test_stubs().sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        program_test_stubs().sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        program_test_stubs().sol_log_data(fields)
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        program_test_stubs().sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        program_test_stubs().sol_get_stack_height()
+    }
+}
+
+/// Accounts of a pool, the copies owned by the system program included
+struct PoolKeys {
+    address: Pubkey,
+    escrow: Pubkey,
+    long_mint: Pubkey,
+    short_mint: Pubkey,
+    escrow_authority: Pubkey,
+    /// Only initialized in markets with fees
+    fee_vault: Pubkey,
+    fake_pool: Pubkey,
+    fake_escrow: Pubkey,
+    fake_mint: Pubkey,
+    amm: AmmKeys,
+    cpmm: CpmmKeys,
+    order_book: Pubkey,
+    order_vault: Pubkey,
+    /// Only initialized in `Market::Optimistic`
+    resolver: Pubkey,
+    bond_vault: Pubkey,
+    /// Only initialized in `Market::Committee`
+    committee: Pubkey,
+    categorical: CategoricalKeys,
+}
+
+struct AmmKeys {
+    vault: Pubkey,
+    long: Pubkey,
+    short: Pubkey,
+}
+
+struct CpmmKeys {
+    address: Pubkey,
+    lp_mint: Pubkey,
+    vault: Pubkey,
+    long: Pubkey,
+    short: Pubkey,
+}
+
+struct CategoricalKeys {
+    address: Pubkey,
+    escrow: Pubkey,
+    escrow_authority: Pubkey,
+    outcome_mints: Vec<Pubkey>,
+}
+
+struct World {
+    wallets: HashMap<Actor, Keypair>,
+    collateral: HashMap<Actor, Pubkey>,
+    positions: HashMap<(Actor, Market), (Pubkey, Pubkey)>,
+    lp_tokens: HashMap<(Actor, Market), Pubkey>,
+    outcome_tokens: HashMap<(Actor, Market), Vec<Pubkey>>,
+    pools: HashMap<Market, PoolKeys>,
+    escrow_mint: Pubkey,
+    uninitialized: Pubkey,
+    empty: Pubkey,
+    missing: Pubkey,
+    /// Token balances of the honest users, no attacker transaction may change them
+    honest_balances: Vec<(Pubkey, u64)>,
+}
+
+impl World {
+    fn wallet(&self, actor: Actor) -> Pubkey {
+        self.wallets[&actor].pubkey()
+    }
+
+    fn pool(&self, market: Market) -> &PoolKeys {
+        &self.pools[&market]
+    }
+
+    fn owner(market: Market) -> Actor {
+        match market {
+            Market::Honest => Actor::Owner,
+            Market::Attacker | Market::Optimistic | Market::Committee => Actor::Mallory,
+        }
+    }
+
+    /// Position account of `actor` holding the tokens of the long or the short side
+    fn side_tokens(&self, actor: Actor, market: Market, long: bool) -> Pubkey {
+        let (long_tokens, short_tokens) = self.positions[&(actor, market)];
+        if long {
+            long_tokens
+        } else {
+            short_tokens
+        }
+    }
+
+    fn outcome_mint(&self, market: Market, outcome: u8) -> Pubkey {
+        self.pool(market).categorical.outcome_mints[outcome as usize % OUTCOMES]
+    }
+
+    fn outcome_tokens(&self, actor: Actor, market: Market, outcome: u8) -> Pubkey {
+        self.outcome_tokens[&(actor, market)][outcome as usize % OUTCOMES]
+    }
+
+    fn key(&self, account: FuzzAccount) -> Pubkey {
+        match account {
+            FuzzAccount::Pool(market) => self.pool(market).address,
+            FuzzAccount::Escrow(market) => self.pool(market).escrow,
+            FuzzAccount::LongMint(market) => self.pool(market).long_mint,
+            FuzzAccount::ShortMint(market) => self.pool(market).short_mint,
+            FuzzAccount::EscrowAuthority(market) => self.pool(market).escrow_authority,
+            FuzzAccount::FeeVault(market) => self.pool(market).fee_vault,
+            FuzzAccount::FakePool(market) => self.pool(market).fake_pool,
+            FuzzAccount::FakeEscrow(market) => self.pool(market).fake_escrow,
+            FuzzAccount::FakeMint(market) => self.pool(market).fake_mint,
+            FuzzAccount::AmmVault(market) => self.pool(market).amm.vault,
+            FuzzAccount::Cpmm(market) => self.pool(market).cpmm.address,
+            FuzzAccount::LpMint(market) => self.pool(market).cpmm.lp_mint,
+            FuzzAccount::CpmmVault(market) => self.pool(market).cpmm.vault,
+            FuzzAccount::OrderBook(market) => self.pool(market).order_book,
+            FuzzAccount::OrderVault(market) => self.pool(market).order_vault,
+            FuzzAccount::Resolver(market) => self.pool(market).resolver,
+            FuzzAccount::BondVault(market) => self.pool(market).bond_vault,
+            FuzzAccount::Committee(market) => self.pool(market).committee,
+            FuzzAccount::Categorical(market) => self.pool(market).categorical.address,
+            FuzzAccount::CategoricalEscrow(market) => self.pool(market).categorical.escrow,
+            FuzzAccount::OutcomeMint(market, outcome) => self.outcome_mint(market, outcome),
+            FuzzAccount::EscrowMint => self.escrow_mint,
+            FuzzAccount::Wallet(actor) => self.wallet(actor),
+            FuzzAccount::Collateral(actor) => self.collateral[&actor],
+            FuzzAccount::LongTokens(actor, market) => self.positions[&(actor, market)].0,
+            FuzzAccount::ShortTokens(actor, market) => self.positions[&(actor, market)].1,
+            FuzzAccount::LpTokens(actor, market) => self.lp_tokens[&(actor, market)],
+            FuzzAccount::OutcomeTokens(actor, market, outcome) => {
+                self.outcome_tokens(actor, market, outcome)
+            }
+            FuzzAccount::Uninitialized => self.uninitialized,
+            FuzzAccount::Empty => self.empty,
+            FuzzAccount::Missing => self.missing,
+            FuzzAccount::TokenProgram => spl_token::id(),
+            FuzzAccount::SystemProgram => system_program::id(),
+            FuzzAccount::BinaryOptionProgram => id(),
+            FuzzAccount::Rent => sysvar::rent::id(),
+            FuzzAccount::Clock => sysvar::clock::id(),
+        }
+    }
+
+    /// Keypair of `key` if it belongs to an attacker
+    fn attacker_keypair(&self, key: &Pubkey) -> Option<&Keypair> {
+        ACTORS
+            .iter()
+            .filter(|actor| actor.is_attacker())
+            .map(|actor| &self.wallets[actor])
+            .find(|wallet| wallet.pubkey() == *key)
+    }
+
+    fn trade(
+        &self,
+        market: Market,
+        buyer: Actor,
+        seller: Actor,
+        size: u64,
+        buy_price: u64,
+        sell_price: u64,
+    ) -> Instruction {
+        let pool = self.pool(market);
+        let (buyer_long, buyer_short) = self.positions[&(buyer, market)];
+        let (seller_long, seller_short) = self.positions[&(seller, market)];
+        instruction::trade(
+            id(),
+            pool.address,
+            pool.escrow,
+            pool.long_mint,
+            pool.short_mint,
+            self.wallet(buyer),
+            self.wallet(seller),
+            self.collateral[&buyer],
+            self.collateral[&seller],
+            buyer_long,
+            buyer_short,
+            seller_long,
+            seller_short,
+            pool.escrow_authority,
+            Some(pool.fee_vault),
+            size,
+            buy_price,
+            sell_price,
+        )
+    }
+
+    fn cpmm_add_liquidity(&self, market: Market, provider: Actor, amount: u64) -> Instruction {
+        let pool = self.pool(market);
+        let (long, short) = self.positions[&(provider, market)];
+        instruction::cpmm_add_liquidity(
+            id(),
+            pool.address,
+            pool.cpmm.address,
+            pool.cpmm.lp_mint,
+            pool.cpmm.vault,
+            pool.cpmm.long,
+            pool.cpmm.short,
+            pool.escrow,
+            pool.long_mint,
+            pool.short_mint,
+            self.wallet(provider),
+            self.collateral[&provider],
+            long,
+            short,
+            self.lp_tokens[&(provider, market)],
+            pool.escrow_authority,
+            amount,
+        )
+    }
+
+    fn instruction(&self, kind: &FuzzKind) -> Instruction {
+        match *kind {
+            FuzzKind::Trade {
+                market,
+                buyer,
+                seller,
+                size,
+                buy_price,
+                sell_price,
+            } => self.trade(market, buyer, seller, size, buy_price, sell_price),
+            FuzzKind::MintSet { market, user, size } => {
+                let pool = self.pool(market);
+                let (long, short) = self.positions[&(user, market)];
+                instruction::mint_set(
+                    id(),
+                    pool.address,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    self.wallet(user),
+                    self.collateral[&user],
+                    long,
+                    short,
+                    pool.escrow_authority,
+                    size,
+                )
+            }
+            FuzzKind::RedeemSet { market, user, size } => {
+                let pool = self.pool(market);
+                let (long, short) = self.positions[&(user, market)];
+                instruction::redeem_set(
+                    id(),
+                    pool.address,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    self.wallet(user),
+                    self.collateral[&user],
+                    long,
+                    short,
+                    pool.escrow_authority,
+                    size,
+                )
+            }
+            FuzzKind::Settle { market, long_wins } => {
+                let pool = self.pool(market);
+                let winning_mint = if long_wins {
+                    pool.long_mint
+                } else {
+                    pool.short_mint
+                };
+                instruction::settle(
+                    id(),
+                    pool.address,
+                    winning_mint,
+                    self.wallet(Self::owner(market)),
+                )
+            }
+            FuzzKind::Void { market, void_price } => instruction::void(
+                id(),
+                self.pool(market).address,
+                self.wallet(Self::owner(market)),
+                void_price,
+            ),
+            FuzzKind::Halt { market } => instruction::halt(
+                id(),
+                self.pool(market).address,
+                self.wallet(Self::owner(market)),
+            ),
+            FuzzKind::Resume { market } => instruction::resume(
+                id(),
+                self.pool(market).address,
+                self.wallet(Self::owner(market)),
+                (market == Market::Optimistic).then(|| self.pool(market).resolver),
+            ),
+            FuzzKind::Collect { market, collector } => {
+                let pool = self.pool(market);
+                let (long, short) = self.positions[&(collector, market)];
+                instruction::collect(
+                    id(),
+                    pool.address,
+                    self.wallet(collector),
+                    long,
+                    short,
+                    self.collateral[&collector],
+                    pool.long_mint,
+                    pool.short_mint,
+                    pool.escrow,
+                    pool.escrow_authority,
+                    Some(pool.fee_vault),
+                )
+            }
+            FuzzKind::WithdrawFees { market, recipient } => {
+                let pool = self.pool(market);
+                instruction::withdraw_fees(
+                    id(),
+                    pool.address,
+                    pool.fee_vault,
+                    pool.long_mint,
+                    pool.short_mint,
+                    self.wallet(recipient),
+                    self.collateral[&recipient],
+                    pool.escrow_authority,
+                )
+            }
+            FuzzKind::SweepDust { market } => {
+                let pool = self.pool(market);
+                let owner = Self::owner(market);
+                instruction::sweep_dust(
+                    id(),
+                    pool.address,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    self.wallet(owner),
+                    self.collateral[&owner],
+                    pool.escrow_authority,
+                )
+            }
+            FuzzKind::ClosePool { market } => {
+                let pool = self.pool(market);
+                let owner = Self::owner(market);
+                instruction::close_pool(
+                    id(),
+                    pool.address,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    self.wallet(owner),
+                    self.collateral[&owner],
+                    self.wallet(owner),
+                    pool.escrow_authority,
+                    Some((pool.fee_vault, self.wallet(owner))),
+                    // Venues a market doesn't have are never looked up
+                    &[
+                        pool.amm.vault,
+                        pool.amm.long,
+                        pool.amm.short,
+                        pool.order_book,
+                        pool.order_vault,
+                        pool.cpmm.address,
+                        pool.cpmm.vault,
+                        pool.cpmm.long,
+                        pool.cpmm.short,
+                        pool.cpmm.lp_mint,
+                        pool.resolver,
+                        pool.bond_vault,
+                        pool.committee,
+                    ],
+                )
+            }
+            FuzzKind::PlaceOrder {
+                market,
+                trader,
+                bid,
+                price,
+                size,
+            } => {
+                let pool = self.pool(market);
+                let (long, short) = self.positions[&(trader, market)];
+                instruction::place_order(
+                    id(),
+                    pool.address,
+                    pool.order_book,
+                    pool.order_vault,
+                    pool.long_mint,
+                    pool.short_mint,
+                    self.wallet(trader),
+                    self.collateral[&trader],
+                    long,
+                    short,
+                    pool.escrow_authority,
+                    if bid { OrderSide::Bid } else { OrderSide::Ask },
+                    price,
+                    size,
+                )
+            }
+            FuzzKind::CancelOrder {
+                market,
+                trader,
+                order_id,
+            } => {
+                let pool = self.pool(market);
+                let (long, short) = self.positions[&(trader, market)];
+                instruction::cancel_order(
+                    id(),
+                    pool.address,
+                    pool.order_book,
+                    pool.order_vault,
+                    pool.long_mint,
+                    pool.short_mint,
+                    self.wallet(trader),
+                    self.collateral[&trader],
+                    long,
+                    short,
+                    pool.escrow_authority,
+                    order_id,
+                )
+            }
+            FuzzKind::MatchOrders {
+                market,
+                buyer,
+                seller,
+                bid_order_id,
+                ask_order_id,
+            } => {
+                let pool = self.pool(market);
+                let (buyer_long, buyer_short) = self.positions[&(buyer, market)];
+                let (seller_long, seller_short) = self.positions[&(seller, market)];
+                instruction::match_orders(
+                    id(),
+                    pool.address,
+                    pool.order_book,
+                    pool.order_vault,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    self.collateral[&buyer],
+                    buyer_long,
+                    buyer_short,
+                    self.collateral[&seller],
+                    seller_long,
+                    seller_short,
+                    pool.escrow_authority,
+                    bid_order_id,
+                    ask_order_id,
+                    Some(pool.fee_vault),
+                )
+            }
+            FuzzKind::AmmBuy {
+                market,
+                user,
+                long,
+                size,
+                max_cost,
+            } => {
+                let pool = self.pool(market);
+                instruction::amm_buy(
+                    id(),
+                    pool.address,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    pool.amm.vault,
+                    pool.amm.long,
+                    pool.amm.short,
+                    self.wallet(user),
+                    self.collateral[&user],
+                    self.side_tokens(user, market, long),
+                    pool.escrow_authority,
+                    Some(pool.fee_vault),
+                    side(long),
+                    size,
+                    max_cost,
+                )
+            }
+            FuzzKind::AmmSell {
+                market,
+                user,
+                long,
+                size,
+                min_proceeds,
+            } => {
+                let pool = self.pool(market);
+                instruction::amm_sell(
+                    id(),
+                    pool.address,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    pool.amm.vault,
+                    pool.amm.long,
+                    pool.amm.short,
+                    self.wallet(user),
+                    self.collateral[&user],
+                    self.side_tokens(user, market, long),
+                    pool.escrow_authority,
+                    Some(pool.fee_vault),
+                    side(long),
+                    size,
+                    min_proceeds,
+                )
+            }
+            FuzzKind::AmmWithdraw { market } => {
+                let pool = self.pool(market);
+                let owner = Self::owner(market);
+                instruction::amm_withdraw(
+                    id(),
+                    pool.address,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    pool.amm.vault,
+                    pool.amm.long,
+                    pool.amm.short,
+                    self.wallet(owner),
+                    self.collateral[&owner],
+                    pool.escrow_authority,
+                )
+            }
+            FuzzKind::CpmmAddLiquidity {
+                market,
+                provider,
+                amount,
+            } => self.cpmm_add_liquidity(market, provider, amount),
+            FuzzKind::CpmmRemoveLiquidity {
+                market,
+                provider,
+                shares,
+            } => {
+                let pool = self.pool(market);
+                let (long, short) = self.positions[&(provider, market)];
+                instruction::cpmm_remove_liquidity(
+                    id(),
+                    pool.address,
+                    pool.cpmm.address,
+                    pool.cpmm.lp_mint,
+                    pool.cpmm.vault,
+                    pool.cpmm.long,
+                    pool.cpmm.short,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    self.wallet(provider),
+                    self.collateral[&provider],
+                    long,
+                    short,
+                    self.lp_tokens[&(provider, market)],
+                    pool.escrow_authority,
+                    shares,
+                )
+            }
+            FuzzKind::CpmmBuy {
+                market,
+                user,
+                long,
+                size,
+                max_cost,
+            } => {
+                let pool = self.pool(market);
+                instruction::cpmm_buy(
+                    id(),
+                    pool.address,
+                    pool.cpmm.address,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    pool.cpmm.vault,
+                    pool.cpmm.long,
+                    pool.cpmm.short,
+                    self.wallet(user),
+                    self.collateral[&user],
+                    self.side_tokens(user, market, long),
+                    pool.escrow_authority,
+                    Some(pool.fee_vault),
+                    side(long),
+                    size,
+                    max_cost,
+                )
+            }
+            FuzzKind::CpmmSell {
+                market,
+                user,
+                long,
+                size,
+                min_proceeds,
+            } => {
+                let pool = self.pool(market);
+                instruction::cpmm_sell(
+                    id(),
+                    pool.address,
+                    pool.cpmm.address,
+                    pool.escrow,
+                    pool.long_mint,
+                    pool.short_mint,
+                    pool.cpmm.vault,
+                    pool.cpmm.long,
+                    pool.cpmm.short,
+                    self.wallet(user),
+                    self.collateral[&user],
+                    self.side_tokens(user, market, long),
+                    pool.escrow_authority,
+                    Some(pool.fee_vault),
+                    side(long),
+                    size,
+                    min_proceeds,
+                )
+            }
+            FuzzKind::ProposeOutcome {
+                market,
+                proposer,
+                outcome,
+            } => {
+                let pool = self.pool(market);
+                instruction::propose_outcome(
+                    id(),
+                    pool.address,
+                    pool.resolver,
+                    pool.bond_vault,
+                    self.wallet(proposer),
+                    self.collateral[&proposer],
+                    outcome.into(),
+                )
+            }
+            FuzzKind::DisputeOutcome { market, disputer } => {
+                let pool = self.pool(market);
+                instruction::dispute_outcome(
+                    id(),
+                    pool.address,
+                    pool.resolver,
+                    pool.bond_vault,
+                    self.wallet(disputer),
+                    self.collateral[&disputer],
+                )
+            }
+            FuzzKind::FinalizeOutcome { market, proposer } => {
+                let pool = self.pool(market);
+                instruction::finalize_outcome(
+                    id(),
+                    pool.address,
+                    pool.resolver,
+                    pool.bond_vault,
+                    self.collateral[&proposer],
+                    pool.escrow_authority,
+                )
+            }
+            FuzzKind::ResolveDispute {
+                market,
+                proposer,
+                disputer,
+                arbiter,
+                outcome,
+            } => {
+                let pool = self.pool(market);
+                instruction::resolve_dispute(
+                    id(),
+                    pool.address,
+                    pool.resolver,
+                    pool.bond_vault,
+                    self.collateral[&proposer],
+                    self.collateral[&disputer],
+                    self.wallet(arbiter),
+                    pool.escrow_authority,
+                    outcome.into(),
+                )
+            }
+            FuzzKind::RefundBonds {
+                market,
+                proposer,
+                disputer,
+            } => {
+                let pool = self.pool(market);
+                instruction::refund_bonds(
+                    id(),
+                    pool.address,
+                    pool.resolver,
+                    pool.bond_vault,
+                    self.collateral[&proposer],
+                    disputer.map(|disputer| self.collateral[&disputer]),
+                    pool.escrow_authority,
+                )
+            }
+            FuzzKind::Vote {
+                market,
+                voter,
+                outcome,
+            } => {
+                let pool = self.pool(market);
+                instruction::vote(
+                    id(),
+                    pool.address,
+                    pool.committee,
+                    self.wallet(voter),
+                    outcome.into(),
+                )
+            }
+            FuzzKind::CategoricalMintSet { market, user, size } => {
+                let categorical = &self.pool(market).categorical;
+                instruction::categorical_mint_set(
+                    id(),
+                    categorical.address,
+                    categorical.escrow,
+                    self.wallet(user),
+                    self.collateral[&user],
+                    categorical.escrow_authority,
+                    &categorical.outcome_mints,
+                    &self.outcome_tokens[&(user, market)],
+                    size,
+                )
+            }
+            FuzzKind::CategoricalRedeemSet { market, user, size } => {
+                let categorical = &self.pool(market).categorical;
+                instruction::categorical_redeem_set(
+                    id(),
+                    categorical.address,
+                    categorical.escrow,
+                    self.wallet(user),
+                    self.collateral[&user],
+                    categorical.escrow_authority,
+                    &categorical.outcome_mints,
+                    &self.outcome_tokens[&(user, market)],
+                    size,
+                )
+            }
+            FuzzKind::CategoricalTrade {
+                market,
+                buyer,
+                seller,
+                outcome,
+                size,
+                price,
+            } => instruction::categorical_trade(
+                id(),
+                self.pool(market).categorical.address,
+                self.outcome_mint(market, outcome),
+                self.wallet(buyer),
+                self.wallet(seller),
+                self.collateral[&buyer],
+                self.collateral[&seller],
+                self.outcome_tokens(buyer, market, outcome),
+                self.outcome_tokens(seller, market, outcome),
+                outcome,
+                size,
+                price,
+            ),
+            FuzzKind::CategoricalSettle {
+                market,
+                winning_outcome,
+            } => instruction::categorical_settle(
+                id(),
+                self.pool(market).categorical.address,
+                self.wallet(Self::owner(market)),
+                winning_outcome,
+            ),
+            FuzzKind::CategoricalCollect {
+                market,
+                collector,
+                outcome,
+            } => {
+                let categorical = &self.pool(market).categorical;
+                instruction::categorical_collect(
+                    id(),
+                    categorical.address,
+                    categorical.escrow,
+                    self.outcome_mint(market, outcome),
+                    self.wallet(collector),
+                    self.collateral[&collector],
+                    self.outcome_tokens(collector, market, outcome),
+                    categorical.escrow_authority,
+                )
+            }
+            FuzzKind::Raw {
+                ref data,
+                ref accounts,
+            } => Instruction {
+                program_id: id(),
+                accounts: accounts
+                    .iter()
+                    .map(|account| AccountMeta::new(self.key(*account), false))
+                    .collect(),
+                data: data.clone(),
+            },
+        }
+    }
+
+    /// Builds the instruction with its mutations applied, signed by the attackers it names
+    fn transaction(
+        &self,
+        fuzz_instruction: &FuzzInstruction,
+        payer: &Keypair,
+        blockhash: Hash,
+    ) -> Transaction {
+        let mut instruction = self.instruction(&fuzz_instruction.kind);
+        let accounts = &mut instruction.accounts;
+        for mutation in &fuzz_instruction.mutations {
+            let len = accounts.len();
+            if len == 0 {
+                break;
+            }
+            match *mutation {
+                Mutation::Replace { index, account } => {
+                    accounts[index as usize % len].pubkey = self.key(account)
+                }
+                Mutation::Swap { first, second } => {
+                    accounts.swap(first as usize % len, second as usize % len)
+                }
+                Mutation::Duplicate { from, to } => {
+                    accounts[to as usize % len].pubkey = accounts[from as usize % len].pubkey
+                }
+                Mutation::Truncate { len: new_len } => accounts.truncate(new_len as usize),
+            }
+        }
+        for meta in accounts.iter_mut() {
+            meta.is_signer = self.attacker_keypair(&meta.pubkey).is_some();
+        }
+
+        let mut signers = vec![payer];
+        for meta in accounts.iter() {
+            if let Some(keypair) = self.attacker_keypair(&meta.pubkey) {
+                if !signers
+                    .iter()
+                    .any(|signer| signer.pubkey() == keypair.pubkey())
+                {
+                    signers.push(keypair);
+                }
+            }
+        }
+        Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &signers,
+            blockhash,
+        )
+    }
+}
+
+fn side(long: bool) -> PositionSide {
+    if long {
+        PositionSide::Long
+    } else {
+        PositionSide::Short
+    }
+}
+
+async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    let account = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    process(
+        context,
+        &[
+            system_instruction::create_account(
+                &context.payer.pubkey(),
+                &account.pubkey(),
+                rent.minimum_balance(Account::LEN),
+                Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &account.pubkey(),
+                mint,
+                owner,
+            )
+            .unwrap(),
+        ],
+        &[&account],
+    )
+    .await;
+    account.pubkey()
+}
+
+/// Stores a copy of `address` owned by the system program at a new address
+async fn copy_to_system_account(context: &mut ProgramTestContext, address: &Pubkey) -> Pubkey {
+    let mut account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .unwrap();
+    account.owner = system_program::id();
+    let copy = Pubkey::new_unique();
+    context.set_account(&copy, &AccountSharedData::from(account));
+    copy
+}
+
+async fn initialize_pool(
+    context: &mut ProgramTestContext,
+    escrow_mint: &Pubkey,
+    market: Market,
+    wallets: &HashMap<Actor, Keypair>,
+    owner_collateral: &Pubkey,
+) -> PoolKeys {
+    let owner = &wallets[&World::owner(market)];
+    let pool = Keypair::new();
+    let escrow = Keypair::new();
+    let long_mint = Keypair::new();
+    let short_mint = Keypair::new();
+    let fee_vault = Keypair::new();
+    let (escrow_authority, _) = Pubkey::find_program_address(
+        &[
+            long_mint.pubkey().as_ref(),
+            short_mint.pubkey().as_ref(),
+            spl_token::id().as_ref(),
+            id().as_ref(),
+        ],
+        &id(),
+    );
+    let mut instructions = vec![instruction::initialize_binary_option(
+        id(),
+        pool.pubkey(),
+        *escrow_mint,
+        escrow.pubkey(),
+        long_mint.pubkey(),
+        short_mint.pubkey(),
+        owner.pubkey(),
+        owner.pubkey(),
+        DECIMALS,
+        0,
+    )];
+    let mut signers = vec![&pool, &escrow, &long_mint, &short_mint, owner];
+    if market.has_fees() {
+        signers.push(&fee_vault);
+        instructions.push(instruction::configure_fees(
+            id(),
+            pool.pubkey(),
+            *escrow_mint,
+            fee_vault.pubkey(),
+            long_mint.pubkey(),
+            short_mint.pubkey(),
+            owner.pubkey(),
+            owner.pubkey(),
+            owner.pubkey(),
+            escrow_authority,
+            30,
+            20,
+        ));
+    }
+    process(context, &instructions, &signers).await;
+
+    // The resolution source can only change while no positions exist
+    let resolver = Keypair::new();
+    let bond_vault = Keypair::new();
+    let committee = Keypair::new();
+    match market {
+        Market::Optimistic => {
+            process(
+                context,
+                &[instruction::initialize_resolver(
+                    id(),
+                    pool.pubkey(),
+                    resolver.pubkey(),
+                    bond_vault.pubkey(),
+                    *escrow_mint,
+                    wallets[&Actor::Trudy].pubkey(),
+                    escrow_authority,
+                    owner.pubkey(),
+                    UNIT,
+                    1,
+                    UNIT / 2,
+                )],
+                &[&resolver, &bond_vault, owner],
+            )
+            .await
+        }
+        Market::Committee => {
+            let voters =
+                [Actor::Mallory, Actor::Trudy, Actor::Alice].map(|actor| wallets[&actor].pubkey());
+            process(
+                context,
+                &[instruction::initialize_committee(
+                    id(),
+                    pool.pubkey(),
+                    committee.pubkey(),
+                    owner.pubkey(),
+                    &voters,
+                    2,
+                    UNIT / 2,
+                )],
+                &[&committee, owner],
+            )
+            .await
+        }
+        Market::Honest | Market::Attacker => {}
+    }
+
+    let amm_vault = Keypair::new();
+    let amm_long = Keypair::new();
+    let amm_short = Keypair::new();
+    process(
+        context,
+        &[instruction::initialize_amm(
+            id(),
+            pool.pubkey(),
+            *escrow_mint,
+            amm_vault.pubkey(),
+            amm_long.pubkey(),
+            amm_short.pubkey(),
+            long_mint.pubkey(),
+            short_mint.pubkey(),
+            owner.pubkey(),
+            *owner_collateral,
+            escrow_authority,
+            10,
+            10 * UNIT,
+        )],
+        &[&amm_vault, &amm_long, &amm_short, owner],
+    )
+    .await;
+
+    let cpmm = Keypair::new();
+    let lp_mint = Keypair::new();
+    let cpmm_vault = Keypair::new();
+    let cpmm_long = Keypair::new();
+    let cpmm_short = Keypair::new();
+    process(
+        context,
+        &[instruction::initialize_cpmm(
+            id(),
+            pool.pubkey(),
+            cpmm.pubkey(),
+            lp_mint.pubkey(),
+            cpmm_vault.pubkey(),
+            cpmm_long.pubkey(),
+            cpmm_short.pubkey(),
+            *escrow_mint,
+            long_mint.pubkey(),
+            short_mint.pubkey(),
+            escrow_authority,
+            owner.pubkey(),
+            30,
+        )],
+        &[&cpmm, &lp_mint, &cpmm_vault, &cpmm_long, &cpmm_short, owner],
+    )
+    .await;
+
+    let order_book = Keypair::new();
+    let order_vault = Keypair::new();
+    process(
+        context,
+        &[instruction::initialize_order_book(
+            id(),
+            pool.pubkey(),
+            order_book.pubkey(),
+            order_vault.pubkey(),
+            *escrow_mint,
+            escrow_authority,
+            owner.pubkey(),
+        )],
+        &[&order_book, &order_vault, owner],
+    )
+    .await;
+
+    let categorical = Keypair::new();
+    let categorical_escrow = Keypair::new();
+    let outcome_mints: Vec<Keypair> = (0..OUTCOMES).map(|_| Keypair::new()).collect();
+    let (categorical_authority, _) = Pubkey::find_program_address(
+        &[
+            categorical.pubkey().as_ref(),
+            spl_token::id().as_ref(),
+            id().as_ref(),
+        ],
+        &id(),
+    );
+    let outcome_mint_keys: Vec<Pubkey> = outcome_mints.iter().map(|mint| mint.pubkey()).collect();
+    let mut signers = vec![&categorical, &categorical_escrow, owner];
+    signers.extend(outcome_mints.iter());
+    process(
+        context,
+        &[instruction::initialize_categorical(
+            id(),
+            categorical.pubkey(),
+            *escrow_mint,
+            categorical_escrow.pubkey(),
+            categorical_authority,
+            owner.pubkey(),
+            &outcome_mint_keys,
+            DECIMALS,
+            0,
+        )],
+        &signers,
+    )
+    .await;
+
+    PoolKeys {
+        address: pool.pubkey(),
+        escrow: escrow.pubkey(),
+        long_mint: long_mint.pubkey(),
+        short_mint: short_mint.pubkey(),
+        escrow_authority,
+        fee_vault: fee_vault.pubkey(),
+        fake_pool: copy_to_system_account(context, &pool.pubkey()).await,
+        fake_escrow: copy_to_system_account(context, &escrow.pubkey()).await,
+        fake_mint: copy_to_system_account(context, &long_mint.pubkey()).await,
+        amm: AmmKeys {
+            vault: amm_vault.pubkey(),
+            long: amm_long.pubkey(),
+            short: amm_short.pubkey(),
+        },
+        cpmm: CpmmKeys {
+            address: cpmm.pubkey(),
+            lp_mint: lp_mint.pubkey(),
+            vault: cpmm_vault.pubkey(),
+            long: cpmm_long.pubkey(),
+            short: cpmm_short.pubkey(),
+        },
+        order_book: order_book.pubkey(),
+        order_vault: order_vault.pubkey(),
+        resolver: resolver.pubkey(),
+        bond_vault: bond_vault.pubkey(),
+        committee: committee.pubkey(),
+        categorical: CategoricalKeys {
+            address: categorical.pubkey(),
+            escrow: categorical_escrow.pubkey(),
+            escrow_authority: categorical_authority,
+            outcome_mints: outcome_mint_keys,
+        },
+    }
+}
+
+/// Funded markets with open positions in each
+async fn setup(context: &mut ProgramTestContext) -> World {
+    let escrow_mint = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    process(
+        context,
+        &[
+            system_instruction::create_account(
+                &context.payer.pubkey(),
+                &escrow_mint.pubkey(),
+                rent.minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &escrow_mint.pubkey(),
+                &context.payer.pubkey(),
+                None,
+                0,
+            )
+            .unwrap(),
+        ],
+        &[&escrow_mint],
+    )
+    .await;
+
+    let wallets: HashMap<Actor, Keypair> = ACTORS
+        .iter()
+        .map(|actor| (*actor, Keypair::new()))
+        .collect();
+    let mut collateral = HashMap::new();
+    for actor in ACTORS {
+        let wallet = wallets[&actor].pubkey();
+        let account = create_token_account(context, &escrow_mint.pubkey(), &wallet).await;
+        process(
+            context,
+            &[
+                system_instruction::transfer(&context.payer.pubkey(), &wallet, 1_000_000_000),
+                spl_token::instruction::mint_to(
+                    &spl_token::id(),
+                    &escrow_mint.pubkey(),
+                    &account,
+                    &context.payer.pubkey(),
+                    &[],
+                    COLLATERAL,
+                )
+                .unwrap(),
+            ],
+            &[],
+        )
+        .await;
+        collateral.insert(actor, account);
+    }
+
+    let mut pools = HashMap::new();
+    let mut positions = HashMap::new();
+    let mut lp_tokens = HashMap::new();
+    let mut outcome_tokens = HashMap::new();
+    for market in MARKETS {
+        let owner_collateral = collateral[&World::owner(market)];
+        let pool = initialize_pool(
+            context,
+            &escrow_mint.pubkey(),
+            market,
+            &wallets,
+            &owner_collateral,
+        )
+        .await;
+        for actor in ACTORS {
+            let wallet = wallets[&actor].pubkey();
+            let long = create_token_account(context, &pool.long_mint, &wallet).await;
+            let short = create_token_account(context, &pool.short_mint, &wallet).await;
+            positions.insert((actor, market), (long, short));
+            let lp = create_token_account(context, &pool.cpmm.lp_mint, &wallet).await;
+            lp_tokens.insert((actor, market), lp);
+            let mut outcomes = vec![];
+            for mint in &pool.categorical.outcome_mints {
+                outcomes.push(create_token_account(context, mint, &wallet).await);
+            }
+            outcome_tokens.insert((actor, market), outcomes);
+        }
+        pools.insert(market, pool);
+    }
+
+    let uninitialized = Pubkey::new_unique();
+    context.set_account(
+        &uninitialized,
+        &AccountSharedData::from(SolanaAccount {
+            lamports: rent.minimum_balance(Account::LEN),
+            data: vec![0; Account::LEN],
+            owner: spl_token::id(),
+            ..SolanaAccount::default()
+        }),
+    );
+    let empty = Pubkey::new_unique();
+    context.set_account(
+        &empty,
+        &AccountSharedData::from(SolanaAccount {
+            lamports: 1_000_000,
+            owner: system_program::id(),
+            ..SolanaAccount::default()
+        }),
+    );
+
+    let mut world = World {
+        wallets,
+        collateral,
+        positions,
+        lp_tokens,
+        outcome_tokens,
+        pools,
+        escrow_mint: escrow_mint.pubkey(),
+        uninitialized,
+        empty,
+        missing: Pubkey::new_unique(),
+        honest_balances: vec![],
+    };
+
+    // Honest users only hold positions in the honest pool, which attackers can't settle
+    let trades = [
+        (Market::Honest, Actor::Alice, Actor::Bob, 10, 30),
+        (Market::Honest, Actor::Trudy, Actor::Mallory, 5, 50),
+        (Market::Attacker, Actor::Mallory, Actor::Trudy, 10, 60),
+        (Market::Optimistic, Actor::Trudy, Actor::Mallory, 10, 40),
+        (Market::Committee, Actor::Mallory, Actor::Trudy, 10, 70),
+    ];
+    for (market, buyer, seller, size, price) in trades {
+        let trade = world.trade(market, buyer, seller, size, price, UNIT - price);
+        let buyer = &world.wallets[&buyer];
+        let seller = &world.wallets[&seller];
+        process(context, &[trade], &[buyer, seller]).await;
+    }
+    for market in MARKETS {
+        let owner = World::owner(market);
+        let add_liquidity = world.cpmm_add_liquidity(market, owner, 10 * UNIT);
+        process(context, &[add_liquidity], &[&world.wallets[&owner]]).await;
+    }
+    for (market, user) in [
+        (Market::Honest, Actor::Bob),
+        (Market::Attacker, Actor::Trudy),
+    ] {
+        let mint_set = world.instruction(&FuzzKind::CategoricalMintSet {
+            market,
+            user,
+            size: 10,
+        });
+        process(context, &[mint_set], &[&world.wallets[&user]]).await;
+    }
+
+    let mut honest_balances = vec![];
+    for actor in ACTORS.iter().filter(|actor| !actor.is_attacker()) {
+        let mut accounts = vec![world.collateral[actor]];
+        for market in MARKETS {
+            let (long, short) = world.positions[&(*actor, market)];
+            accounts.extend([long, short, world.lp_tokens[&(*actor, market)]]);
+            accounts.extend(world.outcome_tokens[&(*actor, market)].iter());
+        }
+        for account in accounts {
+            let balance = token_balance(&mut context.banks_client, &account).await;
+            honest_balances.push((account, balance.unwrap()));
+        }
+    }
+    world.honest_balances = honest_balances;
+    world
+}
+
+async fn token_balance(banks_client: &mut BanksClient, address: &Pubkey) -> Option<u64> {
+    let account = banks_client.get_account(*address).await.unwrap()?;
+    Account::unpack(&account.data)
+        .ok()
+        .map(|account| account.amount)
+}
+
+async fn mint_supply(banks_client: &mut BanksClient, address: &Pubkey) -> u64 {
+    let account = banks_client.get_account(*address).await.unwrap().unwrap();
+    Mint::unpack(&account.data).unwrap().supply
+}
+
+/// Checks that a pool that still exists can pay out every outstanding contract
+async fn check_pool(banks_client: &mut BanksClient, pool: &PoolKeys) {
+    let account = match banks_client.get_account(pool.address).await.unwrap() {
+        Some(account) if account.owner == id() => account,
+        _ => return,
+    };
+    let state = BinaryOption::try_from_slice(&account.data).unwrap();
+    let escrow = token_balance(banks_client, &pool.escrow).await.unwrap();
+    let long = mint_supply(banks_client, &pool.long_mint).await;
+    let short = mint_supply(banks_client, &pool.short_mint).await;
+    let circulation = state.circulation as u128;
+    let escrow = escrow as u128;
+    let unit = UNIT as u128;
+    match state.status {
+        PoolStatus::Open | PoolStatus::Halted => {
+            assert_eq!(long as u128, circulation, "{:?}", state);
+            assert_eq!(short as u128, circulation, "{:?}", state);
+            assert!(escrow >= circulation * unit, "{:?}", state);
+        }
+        PoolStatus::Settled if state.market_type == MarketType::Binary => {
+            let winning_supply = if state.winning_side_pubkey == pool.long_mint {
+                long
+            } else {
+                short
+            };
+            assert_eq!(winning_supply as u128, circulation, "{:?}", state);
+            assert!(escrow >= circulation * unit, "{:?}", state);
+        }
+        _ => {
+            let long_price = if state.status == PoolStatus::Voided {
+                state.void_price
+            } else {
+                state.scalar_long_price()
+            } as u128;
+            assert!(circulation >= long.max(short) as u128, "{:?}", state);
+            assert!(
+                escrow >= long as u128 * long_price + short as u128 * (unit - long_price),
+                "{:?}",
+                state
+            );
+        }
+    }
+    if state.has_fees() {
+        let vault = token_balance(banks_client, &pool.fee_vault).await.unwrap();
+        assert!(
+            vault >= state.protocol_fees_accrued + state.creator_fees_accrued,
+            "{:?}",
+            state
+        );
+    }
+}
+
+/// Checks that a categorical market can pay out every outstanding winning token
+async fn check_categorical(banks_client: &mut BanksClient, categorical: &CategoricalKeys) {
+    let account = banks_client
+        .get_account(categorical.address)
+        .await
+        .unwrap()
+        .unwrap();
+    let state = CategoricalMarket::try_from_slice(&account.data).unwrap();
+    let escrow = token_balance(banks_client, &categorical.escrow)
+        .await
+        .unwrap();
+    let outstanding_mints = if state.status == PoolStatus::Settled {
+        std::slice::from_ref(&categorical.outcome_mints[state.winning_outcome as usize])
+    } else {
+        &categorical.outcome_mints[..]
+    };
+    for mint in outstanding_mints {
+        let supply = mint_supply(banks_client, mint).await;
+        assert_eq!(supply, state.circulation, "{:?}", state);
+    }
+    assert!(
+        escrow as u128 >= state.circulation as u128 * UNIT as u128,
+        "{:?}",
+        state
+    );
+}
+
+async fn check_invariants(banks_client: &mut BanksClient, world: &World) {
+    for market in MARKETS {
+        check_pool(banks_client, world.pool(market)).await;
+        check_categorical(banks_client, &world.pool(market).categorical).await;
+    }
+    for (address, balance) in &world.honest_balances {
+        assert_eq!(
+            token_balance(banks_client, address).await,
+            Some(*balance),
+            "honest account {}",
+            address
+        );
+    }
+}
+
+/// One program test shared by every input. The accounts that inputs write are restored to their
+/// state after `setup` before the next input runs.
+struct Harness {
+    context: ProgramTestContext,
+    world: World,
+    /// State after `setup` of every account an input has written so far, `None` if it didn't exist
+    snapshot: HashMap<Pubkey, Option<SolanaAccount>>,
+    /// Accounts written by the current input
+    dirty: Vec<Pubkey>,
+}
+
+impl Harness {
+    async fn new() -> Self {
+        let program_test = ProgramTest::new("binary_option", id(), processor!(Processor::process));
+        let mut context = program_test.start_with_context().await;
+        PROGRAM_TEST_STUBS.get_or_init(|| set_syscall_stubs(Box::new(CheckedSyscallStubs)));
+        let world = setup(&mut context).await;
+        Self {
+            context,
+            world,
+            snapshot: HashMap::new(),
+            dirty: vec![],
+        }
+    }
+
+    /// Records the state of the accounts `transaction` may write before it first touches them
+    async fn track(&mut self, transaction: &Transaction) {
+        let message = &transaction.message;
+        for (index, address) in message.account_keys.iter().enumerate() {
+            if !message.is_writable(index) {
+                continue;
+            }
+            if !self.snapshot.contains_key(address) {
+                let account = self
+                    .context
+                    .banks_client
+                    .get_account(*address)
+                    .await
+                    .unwrap();
+                self.snapshot.insert(*address, account);
+            }
+            self.dirty.push(*address);
+        }
+    }
+
+    /// Puts back the accounts written by the last input, and moves to a new slot so that the same
+    /// transaction is not rejected as a duplicate
+    async fn reset(&mut self) {
+        for address in self.dirty.drain(..) {
+            let account = self.snapshot[&address]
+                .clone()
+                .map(AccountSharedData::from)
+                .unwrap_or_default();
+            self.context.set_account(&address, &account);
+        }
+        let slot = self.context.banks_client.get_root_slot().await.unwrap();
+        self.context.warp_to_slot(slot + 1).unwrap();
+    }
+
+    async fn run(&mut self, fuzz_data: FuzzData) {
+        for fuzz_instruction in &fuzz_data.instructions {
+            let transaction = self.world.transaction(
+                fuzz_instruction,
+                &self.context.payer,
+                self.context.last_blockhash,
+            );
+            self.track(&transaction).await;
+            // Failing is fine, breaking an invariant is not
+            let _ = self
+                .context
+                .banks_client
+                .process_transaction(transaction)
+                .await;
+            check_invariants(&mut self.context.banks_client, &self.world).await;
+        }
+        self.reset().await;
+    }
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let mut harness = runtime.block_on(Harness::new());
+    loop {
+        fuzz!(|fuzz_data: FuzzData| {
+            runtime.block_on(harness.run(fuzz_data));
+        });
+    }
+}
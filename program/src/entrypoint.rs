@@ -1,4 +1,7 @@
-#![cfg(all(target_arch = "bpf", not(feature = "no-entrypoint")))]
+#![cfg(all(
+    any(target_arch = "bpf", target_os = "solana"),
+    not(feature = "no-entrypoint")
+))]
 
 use solana_program::{
     account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, pubkey::Pubkey,
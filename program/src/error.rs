@@ -28,6 +28,76 @@ pub enum BinaryOptionError {
     PublicKeysShouldBeUnique,
     #[error("TradePricesIncorrect")]
     TradePricesIncorrect,
+    #[error("OrderBookFull")]
+    OrderBookFull,
+    #[error("OrderNotFound")]
+    OrderNotFound,
+    #[error("InvalidOrder")]
+    InvalidOrder,
+    #[error("OrdersDoNotCross")]
+    OrdersDoNotCross,
+    #[error("AmmNotInitialized")]
+    AmmNotInitialized,
+    #[error("AmmAlreadyInitialized")]
+    AmmAlreadyInitialized,
+    #[error("InsufficientSubsidy")]
+    InsufficientSubsidy,
+    #[error("SlippageExceeded")]
+    SlippageExceeded,
+    #[error("MathOverflow")]
+    MathOverflow,
+    #[error("InsufficientLiquidity")]
+    InsufficientLiquidity,
+    #[error("TradingClosed")]
+    TradingClosed,
+    #[error("InvalidPoolStatus")]
+    InvalidPoolStatus,
+    #[error("InvalidResolutionSource")]
+    InvalidResolutionSource,
+    #[error("InvalidOracleAccount")]
+    InvalidOracleAccount,
+    #[error("StaleOraclePrice")]
+    StaleOraclePrice,
+    #[error("OracleConfidenceTooWide")]
+    OracleConfidenceTooWide,
+    #[error("MarketNotExpired")]
+    MarketNotExpired,
+    #[error("InvalidProposalState")]
+    InvalidProposalState,
+    #[error("DisputeWindowOpen")]
+    DisputeWindowOpen,
+    #[error("DisputeWindowClosed")]
+    DisputeWindowClosed,
+    #[error("NotAVoter")]
+    NotAVoter,
+    #[error("InvalidOutcome")]
+    InvalidOutcome,
+    #[error("InvalidMarketType")]
+    InvalidMarketType,
+    #[error("FeesAlreadyConfigured")]
+    FeesAlreadyConfigured,
+    #[error("FeeTooHigh")]
+    FeeTooHigh,
+    #[error("NotAFeeRecipient")]
+    NotAFeeRecipient,
+    #[error("PoolNotEmpty")]
+    PoolNotEmpty,
+    #[error("InvalidPayoutDestination")]
+    InvalidPayoutDestination,
+    #[error("OraclePriceNotAtExpiry")]
+    OraclePriceNotAtExpiry,
+    #[error("FeesNotSupported")]
+    FeesNotSupported,
+    #[error("OrderBookAlreadyInitialized")]
+    OrderBookAlreadyInitialized,
+    #[error("CpmmAlreadyInitialized")]
+    CpmmAlreadyInitialized,
+    #[error("ResolverAlreadyInitialized")]
+    ResolverAlreadyInitialized,
+    #[error("UnsupportedOracleProgram")]
+    UnsupportedOracleProgram,
+    #[error("CommitteeAlreadyInitialized")]
+    CommitteeAlreadyInitialized,
 }
 
 impl From<BinaryOptionError> for ProgramError {
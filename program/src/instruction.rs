@@ -1,15 +1,20 @@
 use solana_program::{
+    account_info::{next_account_info, AccountInfo},
     instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
     pubkey::Pubkey,
     sysvar,
 };
 
+use crate::state::{Comparison, OrderSide, Outcome, PositionSide};
 use borsh::{BorshDeserialize, BorshSerialize};
 
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
 pub struct InitializeBinaryOptionArgs {
     pub decimals: u8,
+    /// Unix timestamp after which trading is rejected, 0 for no expiry
+    pub expiry_unix_timestamp: i64,
 }
 
 #[repr(C)]
@@ -20,6 +25,223 @@ pub struct TradeArgs {
     pub sell_price: u64,
 }
 
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct MintSetArgs {
+    pub size: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct RedeemSetArgs {
+    pub size: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct PlaceOrderArgs {
+    pub side: OrderSide,
+    pub price: u64,
+    pub size: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct CancelOrderArgs {
+    pub order_id: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct MatchOrdersArgs {
+    pub bid_order_id: u64,
+    pub ask_order_id: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct InitializeAmmArgs {
+    pub liquidity: u64,
+    pub subsidy: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct AmmTradeArgs {
+    pub side: PositionSide,
+    pub size: u64,
+    /// Maximum cost when buying, minimum proceeds when selling
+    pub collateral_limit: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct InitializeCpmmArgs {
+    pub fee_bps: u16,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct CpmmAddLiquidityArgs {
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct CpmmRemoveLiquidityArgs {
+    pub shares: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct VoidArgs {
+    /// Payout of a long token, a short token pays out 10^decimals - void_price
+    pub void_price: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ConfigureOracleArgs {
+    pub strike_price: i64,
+    pub comparison: Comparison,
+    pub max_staleness_slots: u64,
+    pub max_confidence_bps: u16,
+    /// Seconds after the expiry during which a published price is accepted
+    pub max_publish_delay: i64,
+    /// Program that owns the price feed, `PYTH_PROGRAM_ID` unless built with `mock-oracle`
+    pub oracle_program_id: Pubkey,
+    /// Payout of a long token if the owner voids the pool because no price settled it
+    pub void_price: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct InitializeResolverArgs {
+    pub bond: u64,
+    /// Seconds during which a proposal can be disputed
+    pub dispute_window: i64,
+    /// Payout of a long token if the pool is resolved as void
+    pub void_price: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct OutcomeArgs {
+    pub outcome: Outcome,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct InitializeCommitteeArgs {
+    pub threshold: u8,
+    /// Payout of a long token if the committee votes void
+    pub void_price: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct InitializeCategoricalArgs {
+    pub decimals: u8,
+    /// Unix timestamp after which trading is rejected, 0 for no expiry
+    pub expiry_unix_timestamp: i64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct CategoricalTradeArgs {
+    pub outcome: u8,
+    pub size: u64,
+    /// Collateral paid per token
+    pub price: u64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct CategoricalSettleArgs {
+    pub winning_outcome: u8,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ConfigureScalarArgs {
+    pub lower_bound: i64,
+    pub upper_bound: i64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct SettleScalarArgs {
+    pub value: i64,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ConfigureFeesArgs {
+    pub protocol_fee_bps: u16,
+    pub creator_fee_bps: u16,
+}
+
+/// Declares the accounts of an instruction once, in order, as a struct generic over the account
+/// type. Builders turn the struct of keys into account metas and the processor reads the struct of
+/// account infos, so the two always agree on the layout. A `readonly(flag)` account only signs when
+/// the builder passes `flag`.
+macro_rules! instruction_accounts {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident {
+            $($(#[$field_attr:meta])* $field:ident: $access:ident $(($is_signer:ident))?,)*
+        }
+    ) => {
+        $(#[$attr])*
+        pub struct $name<T> {
+            $($(#[$field_attr])* pub $field: T,)*
+        }
+
+        impl $name<Pubkey> {
+            /// Account metas in instruction order
+            pub fn to_account_metas(&self, $($($is_signer: bool,)?)*) -> Vec<AccountMeta> {
+                vec![$(instruction_accounts!(@meta $access $(($is_signer))?, self.$field)),*]
+            }
+        }
+
+        impl<'a, 'b> $name<&'b AccountInfo<'a>> {
+            /// Reads the accounts in instruction order
+            pub fn from_account_infos(
+                account_info_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+            ) -> Result<Self, ProgramError> {
+                Ok(Self {
+                    $($field: next_account_info(account_info_iter)?,)*
+                })
+            }
+        }
+    };
+    (@meta writable, $key:expr) => {
+        AccountMeta::new($key, false)
+    };
+    (@meta readonly, $key:expr) => {
+        AccountMeta::new_readonly($key, false)
+    };
+    (@meta readonly($is_signer:ident), $key:expr) => {
+        AccountMeta::new_readonly($key, $is_signer)
+    };
+}
+
+instruction_accounts! {
+    /// Accounts of a Collect instruction, followed by the fee vault if the pool charges fees
+    pub struct CollectAccounts {
+        pool: writable,
+        collector: readonly(collector_is_signer),
+        collector_long_token_account: writable,
+        collector_short_token_account: writable,
+        collector_collateral_account: writable,
+        long_token_mint: writable,
+        short_token_mint: writable,
+        escrow_account: writable,
+        escrow_authority: readonly,
+        token_program: readonly,
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Clone)]
 pub enum BinaryOptionInstruction {
     // TODO: Add comments here
@@ -30,6 +252,90 @@ pub enum BinaryOptionInstruction {
     Settle,
 
     Collect,
+
+    MintSet(MintSetArgs),
+
+    RedeemSet(RedeemSetArgs),
+
+    InitializeOrderBook,
+
+    PlaceOrder(PlaceOrderArgs),
+
+    CancelOrder(CancelOrderArgs),
+
+    MatchOrders(MatchOrdersArgs),
+
+    InitializeAmm(InitializeAmmArgs),
+
+    AmmBuy(AmmTradeArgs),
+
+    AmmSell(AmmTradeArgs),
+
+    AmmWithdraw,
+
+    InitializeCpmm(InitializeCpmmArgs),
+
+    CpmmAddLiquidity(CpmmAddLiquidityArgs),
+
+    CpmmRemoveLiquidity(CpmmRemoveLiquidityArgs),
+
+    CpmmBuy(AmmTradeArgs),
+
+    CpmmSell(AmmTradeArgs),
+
+    Halt,
+
+    Resume,
+
+    Void(VoidArgs),
+
+    ConfigureOracle(ConfigureOracleArgs),
+
+    SettleFromOracle,
+
+    InitializeResolver(InitializeResolverArgs),
+
+    ProposeOutcome(OutcomeArgs),
+
+    DisputeOutcome,
+
+    FinalizeOutcome,
+
+    ResolveDispute(OutcomeArgs),
+
+    InitializeCommittee(InitializeCommitteeArgs),
+
+    Vote(OutcomeArgs),
+
+    InitializeCategorical(InitializeCategoricalArgs),
+
+    CategoricalMintSet(MintSetArgs),
+
+    CategoricalRedeemSet(RedeemSetArgs),
+
+    CategoricalTrade(CategoricalTradeArgs),
+
+    CategoricalSettle(CategoricalSettleArgs),
+
+    CategoricalCollect,
+
+    ConfigureScalar(ConfigureScalarArgs),
+
+    SettleScalar(SettleScalarArgs),
+
+    ConfigureFees(ConfigureFeesArgs),
+
+    WithdrawFees,
+
+    ProposeOwner,
+
+    AcceptOwner,
+
+    ClosePool,
+
+    SweepDust,
+
+    RefundBonds,
 }
 
 /// Creates an InitializeBinaryOption instruction
@@ -44,6 +350,7 @@ pub fn initialize_binary_option(
     mint_authority: Pubkey,
     update_authority: Pubkey,
     decimals: u8,
+    expiry_unix_timestamp: i64,
 ) -> Instruction {
     Instruction {
         program_id,
@@ -51,16 +358,17 @@ pub fn initialize_binary_option(
             AccountMeta::new(pool_account, true),
             AccountMeta::new_readonly(escrow_mint, false),
             AccountMeta::new(escrow_account, true),
-            AccountMeta::new_readonly(long_token_mint, true),
-            AccountMeta::new_readonly(short_token_mint, true),
-            AccountMeta::new_readonly(mint_authority, true),
-            AccountMeta::new_readonly(update_authority, true),
+            AccountMeta::new(long_token_mint, true),
+            AccountMeta::new(short_token_mint, true),
+            AccountMeta::new(mint_authority, true),
+            AccountMeta::new(update_authority, true),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(solana_program::system_program::id(), false),
             AccountMeta::new_readonly(sysvar::rent::id(), false),
         ],
         data: BinaryOptionInstruction::InitializeBinaryOption(InitializeBinaryOptionArgs {
             decimals,
+            expiry_unix_timestamp,
         })
         .try_to_vec()
         .unwrap(),
@@ -84,28 +392,34 @@ pub fn trade(
     seller_long_token_account: Pubkey,
     seller_short_token_account: Pubkey,
     escrow_authority: Pubkey,
+    fee_vault: Option<Pubkey>,
     size: u64,
     buy_price: u64,
     sell_price: u64,
 ) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(pool_account, false),
+        AccountMeta::new(escrow_account, false),
+        AccountMeta::new(long_token_mint, false),
+        AccountMeta::new(short_token_mint, false),
+        AccountMeta::new_readonly(buyer, true),
+        AccountMeta::new_readonly(seller, true),
+        AccountMeta::new(buyer_account, false),
+        AccountMeta::new(seller_account, false),
+        AccountMeta::new(buyer_long_token_account, false),
+        AccountMeta::new(buyer_short_token_account, false),
+        AccountMeta::new(seller_long_token_account, false),
+        AccountMeta::new(seller_short_token_account, false),
+        AccountMeta::new_readonly(escrow_authority, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    // Only pools with fees configured take the fee vault
+    if let Some(fee_vault) = fee_vault {
+        accounts.push(AccountMeta::new(fee_vault, false));
+    }
     Instruction {
         program_id,
-        accounts: vec![
-            AccountMeta::new(pool_account, false),
-            AccountMeta::new(escrow_account, false),
-            AccountMeta::new(long_token_mint, false),
-            AccountMeta::new(short_token_mint, false),
-            AccountMeta::new_readonly(buyer, true),
-            AccountMeta::new_readonly(seller, true),
-            AccountMeta::new(buyer_account, false),
-            AccountMeta::new(seller_account, false),
-            AccountMeta::new(buyer_long_token_account, false),
-            AccountMeta::new(buyer_short_token_account, false),
-            AccountMeta::new(seller_long_token_account, false),
-            AccountMeta::new(seller_short_token_account, false),
-            AccountMeta::new_readonly(escrow_authority, false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
+        accounts,
         data: BinaryOptionInstruction::Trade(TradeArgs {
             size,
             buy_price,
@@ -134,7 +448,46 @@ pub fn settle(
     }
 }
 
-/// Create a Collect instruction
+#[allow(clippy::too_many_arguments)]
+fn collect_instruction(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    collector_account: Pubkey,
+    collector_long_token_account: Pubkey,
+    collector_short_token_account: Pubkey,
+    collector_collateral_account: Pubkey,
+    long_token_mint_account: Pubkey,
+    short_token_mint_account: Pubkey,
+    escrow_account: Pubkey,
+    escrow_authority_account: Pubkey,
+    fee_vault: Option<Pubkey>,
+    collector_is_signer: bool,
+) -> Instruction {
+    let mut accounts = CollectAccounts {
+        pool: pool_account,
+        collector: collector_account,
+        collector_long_token_account,
+        collector_short_token_account,
+        collector_collateral_account,
+        long_token_mint: long_token_mint_account,
+        short_token_mint: short_token_mint_account,
+        escrow_account,
+        escrow_authority: escrow_authority_account,
+        token_program: spl_token::id(),
+    }
+    .to_account_metas(collector_is_signer);
+    if let Some(fee_vault) = fee_vault {
+        accounts.push(AccountMeta::new(fee_vault, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: BinaryOptionInstruction::Collect.try_to_vec().unwrap(),
+    }
+}
+
+/// Create a Collect instruction. The collector doesn't sign, so anyone can send it, and the
+/// collateral account has to be the collector's associated token account.
 #[allow(clippy::too_many_arguments)]
 pub fn collect(
     program_id: Pubkey,
@@ -147,25 +500,1564 @@ pub fn collect(
     short_token_mint_account: Pubkey,
     escrow_account: Pubkey,
     escrow_authority_account: Pubkey,
-    fee_payer_account: Pubkey,
+    fee_vault: Option<Pubkey>,
+) -> Instruction {
+    collect_instruction(
+        program_id,
+        pool_account,
+        collector_account,
+        collector_long_token_account,
+        collector_short_token_account,
+        collector_collateral_account,
+        long_token_mint_account,
+        short_token_mint_account,
+        escrow_account,
+        escrow_authority_account,
+        fee_vault,
+        false,
+    )
+}
+
+/// Create a Collect instruction signed by the collector, which can pay out to any collateral
+/// account
+#[allow(clippy::too_many_arguments)]
+pub fn collect_signed(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    collector_account: Pubkey,
+    collector_long_token_account: Pubkey,
+    collector_short_token_account: Pubkey,
+    collateral_account: Pubkey,
+    long_token_mint_account: Pubkey,
+    short_token_mint_account: Pubkey,
+    escrow_account: Pubkey,
+    escrow_authority_account: Pubkey,
+    fee_vault: Option<Pubkey>,
+) -> Instruction {
+    collect_instruction(
+        program_id,
+        pool_account,
+        collector_account,
+        collector_long_token_account,
+        collector_short_token_account,
+        collateral_account,
+        long_token_mint_account,
+        short_token_mint_account,
+        escrow_account,
+        escrow_authority_account,
+        fee_vault,
+        true,
+    )
+}
+
+/// Creates a MintSet instruction
+#[allow(clippy::too_many_arguments)]
+pub fn mint_set(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    user_long_token_account: Pubkey,
+    user_short_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    size: u64,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(escrow_account, false),
+            AccountMeta::new(long_token_mint, false),
+            AccountMeta::new(short_token_mint, false),
+            AccountMeta::new_readonly(user, true),
+            AccountMeta::new(user_account, false),
+            AccountMeta::new(user_long_token_account, false),
+            AccountMeta::new(user_short_token_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::MintSet(MintSetArgs { size })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a RedeemSet instruction
+#[allow(clippy::too_many_arguments)]
+pub fn redeem_set(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    user_long_token_account: Pubkey,
+    user_short_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    size: u64,
 ) -> Instruction {
     Instruction {
         program_id,
         accounts: vec![
             AccountMeta::new(pool_account, false),
-            AccountMeta::new_readonly(collector_account, false),
-            AccountMeta::new(collector_long_token_account, false),
-            AccountMeta::new(collector_short_token_account, false),
-            AccountMeta::new(collector_collateral_account, false),
-            AccountMeta::new(long_token_mint_account, false),
-            AccountMeta::new(short_token_mint_account, false),
             AccountMeta::new(escrow_account, false),
-            AccountMeta::new(escrow_authority_account, false),
-            AccountMeta::new_readonly(fee_payer_account, true),
+            AccountMeta::new(long_token_mint, false),
+            AccountMeta::new(short_token_mint, false),
+            AccountMeta::new_readonly(user, true),
+            AccountMeta::new(user_account, false),
+            AccountMeta::new(user_long_token_account, false),
+            AccountMeta::new(user_short_token_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::RedeemSet(RedeemSetArgs { size })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates an InitializeOrderBook instruction, the pool owner pays for the accounts
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_order_book(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    order_book_account: Pubkey,
+    vault_account: Pubkey,
+    escrow_mint: Pubkey,
+    escrow_authority: Pubkey,
+    pool_owner: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(order_book_account, true),
+            AccountMeta::new(vault_account, true),
+            AccountMeta::new_readonly(escrow_mint, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new(pool_owner, true),
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(solana_program::system_program::id(), false),
             AccountMeta::new_readonly(sysvar::rent::id(), false),
         ],
-        data: BinaryOptionInstruction::Collect.try_to_vec().unwrap(),
+        data: BinaryOptionInstruction::InitializeOrderBook
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a PlaceOrder instruction
+#[allow(clippy::too_many_arguments)]
+pub fn place_order(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    order_book_account: Pubkey,
+    vault_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    owner: Pubkey,
+    owner_account: Pubkey,
+    owner_long_token_account: Pubkey,
+    owner_short_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    side: OrderSide,
+    price: u64,
+    size: u64,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(pool_account, false),
+            AccountMeta::new(order_book_account, false),
+            AccountMeta::new(vault_account, false),
+            AccountMeta::new_readonly(long_token_mint, false),
+            AccountMeta::new_readonly(short_token_mint, false),
+            AccountMeta::new_readonly(owner, true),
+            AccountMeta::new(owner_account, false),
+            AccountMeta::new(owner_long_token_account, false),
+            AccountMeta::new(owner_short_token_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::PlaceOrder(PlaceOrderArgs { side, price, size })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a CancelOrder instruction
+#[allow(clippy::too_many_arguments)]
+pub fn cancel_order(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    order_book_account: Pubkey,
+    vault_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    owner: Pubkey,
+    owner_account: Pubkey,
+    owner_long_token_account: Pubkey,
+    owner_short_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    order_id: u64,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(pool_account, false),
+            AccountMeta::new(order_book_account, false),
+            AccountMeta::new(vault_account, false),
+            AccountMeta::new_readonly(long_token_mint, false),
+            AccountMeta::new_readonly(short_token_mint, false),
+            AccountMeta::new_readonly(owner, true),
+            AccountMeta::new(owner_account, false),
+            AccountMeta::new(owner_long_token_account, false),
+            AccountMeta::new(owner_short_token_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::CancelOrder(CancelOrderArgs { order_id })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a MatchOrders instruction, `fee_vault` is only needed by pools with fees
+#[allow(clippy::too_many_arguments)]
+pub fn match_orders(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    order_book_account: Pubkey,
+    vault_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    buyer_account: Pubkey,
+    buyer_long_token_account: Pubkey,
+    buyer_short_token_account: Pubkey,
+    seller_account: Pubkey,
+    seller_long_token_account: Pubkey,
+    seller_short_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    bid_order_id: u64,
+    ask_order_id: u64,
+    fee_vault: Option<Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(pool_account, false),
+        AccountMeta::new(order_book_account, false),
+        AccountMeta::new(vault_account, false),
+        AccountMeta::new(escrow_account, false),
+        AccountMeta::new(long_token_mint, false),
+        AccountMeta::new(short_token_mint, false),
+        AccountMeta::new(buyer_account, false),
+        AccountMeta::new(buyer_long_token_account, false),
+        AccountMeta::new(buyer_short_token_account, false),
+        AccountMeta::new(seller_account, false),
+        AccountMeta::new(seller_long_token_account, false),
+        AccountMeta::new(seller_short_token_account, false),
+        AccountMeta::new_readonly(escrow_authority, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if let Some(fee_vault) = fee_vault {
+        accounts.push(AccountMeta::new(fee_vault, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: BinaryOptionInstruction::MatchOrders(MatchOrdersArgs {
+            bid_order_id,
+            ask_order_id,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates an InitializeAmm instruction
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_amm(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    escrow_mint: Pubkey,
+    amm_vault_account: Pubkey,
+    amm_long_token_account: Pubkey,
+    amm_short_token_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    pool_owner: Pubkey,
+    pool_owner_account: Pubkey,
+    escrow_authority: Pubkey,
+    liquidity: u64,
+    subsidy: u64,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new_readonly(escrow_mint, false),
+            AccountMeta::new(amm_vault_account, true),
+            AccountMeta::new(amm_long_token_account, true),
+            AccountMeta::new(amm_short_token_account, true),
+            AccountMeta::new_readonly(long_token_mint, false),
+            AccountMeta::new_readonly(short_token_mint, false),
+            AccountMeta::new(pool_owner, true),
+            AccountMeta::new(pool_owner_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: BinaryOptionInstruction::InitializeAmm(InitializeAmmArgs { liquidity, subsidy })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn amm_trade(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    amm_vault_account: Pubkey,
+    amm_long_token_account: Pubkey,
+    amm_short_token_account: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    user_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    fee_vault: Option<Pubkey>,
+    data: Vec<u8>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(pool_account, false),
+        AccountMeta::new(escrow_account, false),
+        AccountMeta::new(long_token_mint, false),
+        AccountMeta::new(short_token_mint, false),
+        AccountMeta::new(amm_vault_account, false),
+        AccountMeta::new(amm_long_token_account, false),
+        AccountMeta::new(amm_short_token_account, false),
+        AccountMeta::new_readonly(user, true),
+        AccountMeta::new(user_account, false),
+        AccountMeta::new(user_token_account, false),
+        AccountMeta::new_readonly(escrow_authority, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    // Only pools with fees configured take the fee vault
+    if let Some(fee_vault) = fee_vault {
+        accounts.push(AccountMeta::new(fee_vault, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates an AmmBuy instruction, `user_token_account` holds the tokens of `side`
+#[allow(clippy::too_many_arguments)]
+pub fn amm_buy(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    amm_vault_account: Pubkey,
+    amm_long_token_account: Pubkey,
+    amm_short_token_account: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    user_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    fee_vault: Option<Pubkey>,
+    side: PositionSide,
+    size: u64,
+    max_cost: u64,
+) -> Instruction {
+    amm_trade(
+        program_id,
+        pool_account,
+        escrow_account,
+        long_token_mint,
+        short_token_mint,
+        amm_vault_account,
+        amm_long_token_account,
+        amm_short_token_account,
+        user,
+        user_account,
+        user_token_account,
+        escrow_authority,
+        fee_vault,
+        BinaryOptionInstruction::AmmBuy(AmmTradeArgs {
+            side,
+            size,
+            collateral_limit: max_cost,
+        })
+        .try_to_vec()
+        .unwrap(),
+    )
+}
+
+/// Creates an AmmSell instruction, `user_token_account` holds the tokens of `side`
+#[allow(clippy::too_many_arguments)]
+pub fn amm_sell(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    amm_vault_account: Pubkey,
+    amm_long_token_account: Pubkey,
+    amm_short_token_account: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    user_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    fee_vault: Option<Pubkey>,
+    side: PositionSide,
+    size: u64,
+    min_proceeds: u64,
+) -> Instruction {
+    amm_trade(
+        program_id,
+        pool_account,
+        escrow_account,
+        long_token_mint,
+        short_token_mint,
+        amm_vault_account,
+        amm_long_token_account,
+        amm_short_token_account,
+        user,
+        user_account,
+        user_token_account,
+        escrow_authority,
+        fee_vault,
+        BinaryOptionInstruction::AmmSell(AmmTradeArgs {
+            side,
+            size,
+            collateral_limit: min_proceeds,
+        })
+        .try_to_vec()
+        .unwrap(),
+    )
+}
+
+/// Creates an AmmWithdraw instruction
+#[allow(clippy::too_many_arguments)]
+pub fn amm_withdraw(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    amm_vault_account: Pubkey,
+    amm_long_token_account: Pubkey,
+    amm_short_token_account: Pubkey,
+    pool_owner: Pubkey,
+    pool_owner_account: Pubkey,
+    escrow_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(escrow_account, false),
+            AccountMeta::new(long_token_mint, false),
+            AccountMeta::new(short_token_mint, false),
+            AccountMeta::new(amm_vault_account, false),
+            AccountMeta::new(amm_long_token_account, false),
+            AccountMeta::new(amm_short_token_account, false),
+            AccountMeta::new_readonly(pool_owner, true),
+            AccountMeta::new(pool_owner_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::AmmWithdraw.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates an InitializeCpmm instruction, the pool owner pays for the accounts
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_cpmm(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    cpmm_account: Pubkey,
+    lp_mint: Pubkey,
+    cpmm_vault_account: Pubkey,
+    cpmm_long_token_account: Pubkey,
+    cpmm_short_token_account: Pubkey,
+    escrow_mint: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    escrow_authority: Pubkey,
+    pool_owner: Pubkey,
+    fee_bps: u16,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(cpmm_account, true),
+            AccountMeta::new(lp_mint, true),
+            AccountMeta::new(cpmm_vault_account, true),
+            AccountMeta::new(cpmm_long_token_account, true),
+            AccountMeta::new(cpmm_short_token_account, true),
+            AccountMeta::new_readonly(escrow_mint, false),
+            AccountMeta::new_readonly(long_token_mint, false),
+            AccountMeta::new_readonly(short_token_mint, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new(pool_owner, true),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: BinaryOptionInstruction::InitializeCpmm(InitializeCpmmArgs { fee_bps })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cpmm_liquidity(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    cpmm_account: Pubkey,
+    lp_mint: Pubkey,
+    cpmm_vault_account: Pubkey,
+    cpmm_long_token_account: Pubkey,
+    cpmm_short_token_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    provider: Pubkey,
+    provider_account: Pubkey,
+    provider_long_token_account: Pubkey,
+    provider_short_token_account: Pubkey,
+    provider_lp_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    data: Vec<u8>,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(cpmm_account, false),
+            AccountMeta::new(lp_mint, false),
+            AccountMeta::new(cpmm_vault_account, false),
+            AccountMeta::new(cpmm_long_token_account, false),
+            AccountMeta::new(cpmm_short_token_account, false),
+            AccountMeta::new(escrow_account, false),
+            AccountMeta::new(long_token_mint, false),
+            AccountMeta::new(short_token_mint, false),
+            AccountMeta::new_readonly(provider, true),
+            AccountMeta::new(provider_account, false),
+            AccountMeta::new(provider_long_token_account, false),
+            AccountMeta::new(provider_short_token_account, false),
+            AccountMeta::new(provider_lp_token_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data,
+    }
+}
+
+/// Creates a CpmmAddLiquidity instruction, `amount` is denominated in the escrow mint
+#[allow(clippy::too_many_arguments)]
+pub fn cpmm_add_liquidity(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    cpmm_account: Pubkey,
+    lp_mint: Pubkey,
+    cpmm_vault_account: Pubkey,
+    cpmm_long_token_account: Pubkey,
+    cpmm_short_token_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    provider: Pubkey,
+    provider_account: Pubkey,
+    provider_long_token_account: Pubkey,
+    provider_short_token_account: Pubkey,
+    provider_lp_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    amount: u64,
+) -> Instruction {
+    cpmm_liquidity(
+        program_id,
+        pool_account,
+        cpmm_account,
+        lp_mint,
+        cpmm_vault_account,
+        cpmm_long_token_account,
+        cpmm_short_token_account,
+        escrow_account,
+        long_token_mint,
+        short_token_mint,
+        provider,
+        provider_account,
+        provider_long_token_account,
+        provider_short_token_account,
+        provider_lp_token_account,
+        escrow_authority,
+        BinaryOptionInstruction::CpmmAddLiquidity(CpmmAddLiquidityArgs { amount })
+            .try_to_vec()
+            .unwrap(),
+    )
+}
+
+/// Creates a CpmmRemoveLiquidity instruction
+#[allow(clippy::too_many_arguments)]
+pub fn cpmm_remove_liquidity(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    cpmm_account: Pubkey,
+    lp_mint: Pubkey,
+    cpmm_vault_account: Pubkey,
+    cpmm_long_token_account: Pubkey,
+    cpmm_short_token_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    provider: Pubkey,
+    provider_account: Pubkey,
+    provider_long_token_account: Pubkey,
+    provider_short_token_account: Pubkey,
+    provider_lp_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    shares: u64,
+) -> Instruction {
+    cpmm_liquidity(
+        program_id,
+        pool_account,
+        cpmm_account,
+        lp_mint,
+        cpmm_vault_account,
+        cpmm_long_token_account,
+        cpmm_short_token_account,
+        escrow_account,
+        long_token_mint,
+        short_token_mint,
+        provider,
+        provider_account,
+        provider_long_token_account,
+        provider_short_token_account,
+        provider_lp_token_account,
+        escrow_authority,
+        BinaryOptionInstruction::CpmmRemoveLiquidity(CpmmRemoveLiquidityArgs { shares })
+            .try_to_vec()
+            .unwrap(),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cpmm_trade(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    cpmm_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    cpmm_vault_account: Pubkey,
+    cpmm_long_token_account: Pubkey,
+    cpmm_short_token_account: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    user_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    fee_vault: Option<Pubkey>,
+    data: Vec<u8>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(pool_account, false),
+        AccountMeta::new(cpmm_account, false),
+        AccountMeta::new(escrow_account, false),
+        AccountMeta::new(long_token_mint, false),
+        AccountMeta::new(short_token_mint, false),
+        AccountMeta::new(cpmm_vault_account, false),
+        AccountMeta::new(cpmm_long_token_account, false),
+        AccountMeta::new(cpmm_short_token_account, false),
+        AccountMeta::new_readonly(user, true),
+        AccountMeta::new(user_account, false),
+        AccountMeta::new(user_token_account, false),
+        AccountMeta::new_readonly(escrow_authority, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    // Only pools with fees configured take the fee vault
+    if let Some(fee_vault) = fee_vault {
+        accounts.push(AccountMeta::new(fee_vault, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates a CpmmBuy instruction, `user_token_account` holds the tokens of `side`
+#[allow(clippy::too_many_arguments)]
+pub fn cpmm_buy(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    cpmm_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    cpmm_vault_account: Pubkey,
+    cpmm_long_token_account: Pubkey,
+    cpmm_short_token_account: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    user_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    fee_vault: Option<Pubkey>,
+    side: PositionSide,
+    size: u64,
+    max_cost: u64,
+) -> Instruction {
+    cpmm_trade(
+        program_id,
+        pool_account,
+        cpmm_account,
+        escrow_account,
+        long_token_mint,
+        short_token_mint,
+        cpmm_vault_account,
+        cpmm_long_token_account,
+        cpmm_short_token_account,
+        user,
+        user_account,
+        user_token_account,
+        escrow_authority,
+        fee_vault,
+        BinaryOptionInstruction::CpmmBuy(AmmTradeArgs {
+            side,
+            size,
+            collateral_limit: max_cost,
+        })
+        .try_to_vec()
+        .unwrap(),
+    )
+}
+
+/// Creates a CpmmSell instruction, `user_token_account` holds the tokens of `side`
+#[allow(clippy::too_many_arguments)]
+pub fn cpmm_sell(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    cpmm_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    cpmm_vault_account: Pubkey,
+    cpmm_long_token_account: Pubkey,
+    cpmm_short_token_account: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    user_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    fee_vault: Option<Pubkey>,
+    side: PositionSide,
+    size: u64,
+    min_proceeds: u64,
+) -> Instruction {
+    cpmm_trade(
+        program_id,
+        pool_account,
+        cpmm_account,
+        escrow_account,
+        long_token_mint,
+        short_token_mint,
+        cpmm_vault_account,
+        cpmm_long_token_account,
+        cpmm_short_token_account,
+        user,
+        user_account,
+        user_token_account,
+        escrow_authority,
+        fee_vault,
+        BinaryOptionInstruction::CpmmSell(AmmTradeArgs {
+            side,
+            size,
+            collateral_limit: min_proceeds,
+        })
+        .try_to_vec()
+        .unwrap(),
+    )
+}
+
+/// Creates a Halt instruction
+pub fn halt(program_id: Pubkey, pool_account: Pubkey, pool_owner: Pubkey) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new_readonly(pool_owner, true),
+        ],
+        data: BinaryOptionInstruction::Halt.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a Resume instruction, `resolver_account` is only needed by pools resolved by an
+/// optimistic resolver
+pub fn resume(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    pool_owner: Pubkey,
+    resolver_account: Option<Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(pool_account, false),
+        AccountMeta::new_readonly(pool_owner, true),
+    ];
+    if let Some(resolver_account) = resolver_account {
+        accounts.push(AccountMeta::new_readonly(resolver_account, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: BinaryOptionInstruction::Resume.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a Void instruction, `void_price` is ignored by oracle pools which pay out the price
+/// set by `ConfigureOracle`
+pub fn void(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    pool_owner: Pubkey,
+    void_price: u64,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new_readonly(pool_owner, true),
+        ],
+        data: BinaryOptionInstruction::Void(VoidArgs { void_price })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a ConfigureOracle instruction
+#[allow(clippy::too_many_arguments)]
+pub fn configure_oracle(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    oracle_feed: Pubkey,
+    pool_owner: Pubkey,
+    strike_price: i64,
+    comparison: Comparison,
+    max_staleness_slots: u64,
+    max_confidence_bps: u16,
+    max_publish_delay: i64,
+    oracle_program_id: Pubkey,
+    void_price: u64,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new_readonly(oracle_feed, false),
+            AccountMeta::new_readonly(pool_owner, true),
+        ],
+        data: BinaryOptionInstruction::ConfigureOracle(ConfigureOracleArgs {
+            strike_price,
+            comparison,
+            max_staleness_slots,
+            max_confidence_bps,
+            max_publish_delay,
+            oracle_program_id,
+            void_price,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a SettleFromOracle instruction
+pub fn settle_from_oracle(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    oracle_feed: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new_readonly(oracle_feed, false),
+        ],
+        data: BinaryOptionInstruction::SettleFromOracle
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates an InitializeResolver instruction
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_resolver(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    resolver_account: Pubkey,
+    bond_vault_account: Pubkey,
+    escrow_mint: Pubkey,
+    arbiter: Pubkey,
+    escrow_authority: Pubkey,
+    pool_owner: Pubkey,
+    bond: u64,
+    dispute_window: i64,
+    void_price: u64,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(resolver_account, true),
+            AccountMeta::new(bond_vault_account, true),
+            AccountMeta::new_readonly(escrow_mint, false),
+            AccountMeta::new_readonly(arbiter, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new(pool_owner, true),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: BinaryOptionInstruction::InitializeResolver(InitializeResolverArgs {
+            bond,
+            dispute_window,
+            void_price,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a ProposeOutcome instruction, the bond is paid from `proposer_account`
+pub fn propose_outcome(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    resolver_account: Pubkey,
+    bond_vault_account: Pubkey,
+    proposer: Pubkey,
+    proposer_account: Pubkey,
+    outcome: Outcome,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(pool_account, false),
+            AccountMeta::new(resolver_account, false),
+            AccountMeta::new(bond_vault_account, false),
+            AccountMeta::new_readonly(proposer, true),
+            AccountMeta::new(proposer_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::ProposeOutcome(OutcomeArgs { outcome })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a DisputeOutcome instruction, the bond is paid from `disputer_account`
+pub fn dispute_outcome(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    resolver_account: Pubkey,
+    bond_vault_account: Pubkey,
+    disputer: Pubkey,
+    disputer_account: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(pool_account, false),
+            AccountMeta::new(resolver_account, false),
+            AccountMeta::new(bond_vault_account, false),
+            AccountMeta::new_readonly(disputer, true),
+            AccountMeta::new(disputer_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::DisputeOutcome
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a FinalizeOutcome instruction
+pub fn finalize_outcome(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    resolver_account: Pubkey,
+    bond_vault_account: Pubkey,
+    proposer_account: Pubkey,
+    escrow_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(resolver_account, false),
+            AccountMeta::new(bond_vault_account, false),
+            AccountMeta::new(proposer_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::FinalizeOutcome
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a ResolveDispute instruction
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_dispute(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    resolver_account: Pubkey,
+    bond_vault_account: Pubkey,
+    proposer_account: Pubkey,
+    disputer_account: Pubkey,
+    arbiter: Pubkey,
+    escrow_authority: Pubkey,
+    outcome: Outcome,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(resolver_account, false),
+            AccountMeta::new(bond_vault_account, false),
+            AccountMeta::new(proposer_account, false),
+            AccountMeta::new(disputer_account, false),
+            AccountMeta::new_readonly(arbiter, true),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::ResolveDispute(OutcomeArgs { outcome })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a RefundBonds instruction, `disputer_account` is only needed if the proposal was
+/// disputed
+#[allow(clippy::too_many_arguments)]
+pub fn refund_bonds(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    resolver_account: Pubkey,
+    bond_vault_account: Pubkey,
+    proposer_account: Pubkey,
+    disputer_account: Option<Pubkey>,
+    escrow_authority: Pubkey,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(pool_account, false),
+        AccountMeta::new(resolver_account, false),
+        AccountMeta::new(bond_vault_account, false),
+        AccountMeta::new(proposer_account, false),
+    ];
+    if let Some(disputer_account) = disputer_account {
+        accounts.push(AccountMeta::new(disputer_account, false));
+    }
+    accounts.push(AccountMeta::new_readonly(escrow_authority, false));
+    accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+    Instruction {
+        program_id,
+        accounts,
+        data: BinaryOptionInstruction::RefundBonds.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates an InitializeCommittee instruction
+pub fn initialize_committee(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    committee_account: Pubkey,
+    pool_owner: Pubkey,
+    voters: &[Pubkey],
+    threshold: u8,
+    void_price: u64,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(pool_account, false),
+        AccountMeta::new(committee_account, true),
+        AccountMeta::new(pool_owner, true),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+    accounts.extend(
+        voters
+            .iter()
+            .map(|voter| AccountMeta::new_readonly(*voter, false)),
+    );
+    Instruction {
+        program_id,
+        accounts,
+        data: BinaryOptionInstruction::InitializeCommittee(InitializeCommitteeArgs {
+            threshold,
+            void_price,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a Vote instruction
+pub fn vote(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    committee_account: Pubkey,
+    voter: Pubkey,
+    outcome: Outcome,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(committee_account, false),
+            AccountMeta::new_readonly(voter, true),
+        ],
+        data: BinaryOptionInstruction::Vote(OutcomeArgs { outcome })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates an InitializeCategorical instruction, one outcome is created per mint in
+/// `outcome_mints`
+#[allow(clippy::too_many_arguments)]
+pub fn initialize_categorical(
+    program_id: Pubkey,
+    market_account: Pubkey,
+    escrow_mint: Pubkey,
+    escrow_account: Pubkey,
+    escrow_authority: Pubkey,
+    owner: Pubkey,
+    outcome_mints: &[Pubkey],
+    decimals: u8,
+    expiry_unix_timestamp: i64,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(market_account, true),
+        AccountMeta::new_readonly(escrow_mint, false),
+        AccountMeta::new(escrow_account, true),
+        AccountMeta::new_readonly(escrow_authority, false),
+        AccountMeta::new(owner, true),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+    accounts.extend(
+        outcome_mints
+            .iter()
+            .map(|mint| AccountMeta::new(*mint, true)),
+    );
+    Instruction {
+        program_id,
+        accounts,
+        data: BinaryOptionInstruction::InitializeCategorical(InitializeCategoricalArgs {
+            decimals,
+            expiry_unix_timestamp,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn categorical_set(
+    program_id: Pubkey,
+    market_account: Pubkey,
+    escrow_account: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    escrow_authority: Pubkey,
+    outcome_mints: &[Pubkey],
+    user_outcome_token_accounts: &[Pubkey],
+    data: Vec<u8>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(market_account, false),
+        AccountMeta::new(escrow_account, false),
+        AccountMeta::new_readonly(user, true),
+        AccountMeta::new(user_account, false),
+        AccountMeta::new_readonly(escrow_authority, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    for (mint, token_account) in outcome_mints.iter().zip(user_outcome_token_accounts.iter()) {
+        accounts.push(AccountMeta::new(*mint, false));
+        accounts.push(AccountMeta::new(*token_account, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data,
+    }
+}
+
+/// Creates a CategoricalMintSet instruction, `user_outcome_token_accounts[i]` holds the tokens
+/// of `outcome_mints[i]`
+#[allow(clippy::too_many_arguments)]
+pub fn categorical_mint_set(
+    program_id: Pubkey,
+    market_account: Pubkey,
+    escrow_account: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    escrow_authority: Pubkey,
+    outcome_mints: &[Pubkey],
+    user_outcome_token_accounts: &[Pubkey],
+    size: u64,
+) -> Instruction {
+    categorical_set(
+        program_id,
+        market_account,
+        escrow_account,
+        user,
+        user_account,
+        escrow_authority,
+        outcome_mints,
+        user_outcome_token_accounts,
+        BinaryOptionInstruction::CategoricalMintSet(MintSetArgs { size })
+            .try_to_vec()
+            .unwrap(),
+    )
+}
+
+/// Creates a CategoricalRedeemSet instruction, `user_outcome_token_accounts[i]` holds the
+/// tokens of `outcome_mints[i]`
+#[allow(clippy::too_many_arguments)]
+pub fn categorical_redeem_set(
+    program_id: Pubkey,
+    market_account: Pubkey,
+    escrow_account: Pubkey,
+    user: Pubkey,
+    user_account: Pubkey,
+    escrow_authority: Pubkey,
+    outcome_mints: &[Pubkey],
+    user_outcome_token_accounts: &[Pubkey],
+    size: u64,
+) -> Instruction {
+    categorical_set(
+        program_id,
+        market_account,
+        escrow_account,
+        user,
+        user_account,
+        escrow_authority,
+        outcome_mints,
+        user_outcome_token_accounts,
+        BinaryOptionInstruction::CategoricalRedeemSet(RedeemSetArgs { size })
+            .try_to_vec()
+            .unwrap(),
+    )
+}
+
+/// Creates a CategoricalTrade instruction, the buyer pays `size * price` of collateral for
+/// `size` tokens of `outcome`
+#[allow(clippy::too_many_arguments)]
+pub fn categorical_trade(
+    program_id: Pubkey,
+    market_account: Pubkey,
+    outcome_mint: Pubkey,
+    buyer: Pubkey,
+    seller: Pubkey,
+    buyer_account: Pubkey,
+    seller_account: Pubkey,
+    buyer_outcome_token_account: Pubkey,
+    seller_outcome_token_account: Pubkey,
+    outcome: u8,
+    size: u64,
+    price: u64,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(market_account, false),
+            AccountMeta::new_readonly(outcome_mint, false),
+            AccountMeta::new_readonly(buyer, true),
+            AccountMeta::new_readonly(seller, true),
+            AccountMeta::new(buyer_account, false),
+            AccountMeta::new(seller_account, false),
+            AccountMeta::new(buyer_outcome_token_account, false),
+            AccountMeta::new(seller_outcome_token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::CategoricalTrade(CategoricalTradeArgs {
+            outcome,
+            size,
+            price,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a CategoricalSettle instruction
+pub fn categorical_settle(
+    program_id: Pubkey,
+    market_account: Pubkey,
+    owner: Pubkey,
+    winning_outcome: u8,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(market_account, false),
+            AccountMeta::new_readonly(owner, true),
+        ],
+        data: BinaryOptionInstruction::CategoricalSettle(CategoricalSettleArgs { winning_outcome })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a CategoricalCollect instruction
+#[allow(clippy::too_many_arguments)]
+pub fn categorical_collect(
+    program_id: Pubkey,
+    market_account: Pubkey,
+    escrow_account: Pubkey,
+    winning_mint: Pubkey,
+    collector: Pubkey,
+    collector_account: Pubkey,
+    collector_winning_token_account: Pubkey,
+    escrow_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(market_account, false),
+            AccountMeta::new(escrow_account, false),
+            AccountMeta::new(winning_mint, false),
+            AccountMeta::new_readonly(collector, true),
+            AccountMeta::new(collector_account, false),
+            AccountMeta::new(collector_winning_token_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::CategoricalCollect
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a ConfigureScalar instruction
+pub fn configure_scalar(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    pool_owner: Pubkey,
+    lower_bound: i64,
+    upper_bound: i64,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new_readonly(pool_owner, true),
+        ],
+        data: BinaryOptionInstruction::ConfigureScalar(ConfigureScalarArgs {
+            lower_bound,
+            upper_bound,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a SettleScalar instruction
+pub fn settle_scalar(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    pool_authority: Pubkey,
+    value: i64,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new_readonly(pool_authority, true),
+        ],
+        data: BinaryOptionInstruction::SettleScalar(SettleScalarArgs { value })
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates a ConfigureFees instruction
+#[allow(clippy::too_many_arguments)]
+pub fn configure_fees(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    escrow_mint: Pubkey,
+    fee_vault: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    pool_owner: Pubkey,
+    protocol_fee_recipient: Pubkey,
+    creator_fee_recipient: Pubkey,
+    escrow_authority: Pubkey,
+    protocol_fee_bps: u16,
+    creator_fee_bps: u16,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new_readonly(escrow_mint, false),
+            AccountMeta::new(fee_vault, true),
+            AccountMeta::new_readonly(long_token_mint, false),
+            AccountMeta::new_readonly(short_token_mint, false),
+            AccountMeta::new(pool_owner, true),
+            AccountMeta::new_readonly(protocol_fee_recipient, false),
+            AccountMeta::new_readonly(creator_fee_recipient, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: BinaryOptionInstruction::ConfigureFees(ConfigureFeesArgs {
+            protocol_fee_bps,
+            creator_fee_bps,
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a WithdrawFees instruction
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_fees(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    fee_vault: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    recipient: Pubkey,
+    recipient_account: Pubkey,
+    escrow_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new(fee_vault, false),
+            AccountMeta::new_readonly(long_token_mint, false),
+            AccountMeta::new_readonly(short_token_mint, false),
+            AccountMeta::new_readonly(recipient, true),
+            AccountMeta::new(recipient_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::WithdrawFees.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a ProposeOwner instruction
+pub fn propose_owner(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    pool_owner: Pubkey,
+    new_owner: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new_readonly(pool_owner, true),
+            AccountMeta::new_readonly(new_owner, false),
+        ],
+        data: BinaryOptionInstruction::ProposeOwner.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates an AcceptOwner instruction
+pub fn accept_owner(program_id: Pubkey, pool_account: Pubkey, new_owner: Pubkey) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_account, false),
+            AccountMeta::new_readonly(new_owner, true),
+        ],
+        data: BinaryOptionInstruction::AcceptOwner.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a ClosePool instruction, `fee_vault` is the fee vault and who paid for it.
+/// `venue_accounts` are the accounts of the venues recorded by the pool, see
+/// `BinaryOption::venue_accounts`.
+#[allow(clippy::too_many_arguments)]
+pub fn close_pool(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    pool_owner: Pubkey,
+    pool_owner_account: Pubkey,
+    payer: Pubkey,
+    escrow_authority: Pubkey,
+    fee_vault: Option<(Pubkey, Pubkey)>,
+    venue_accounts: &[Pubkey],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(pool_account, false),
+        AccountMeta::new(escrow_account, false),
+        AccountMeta::new_readonly(long_token_mint, false),
+        AccountMeta::new_readonly(short_token_mint, false),
+        AccountMeta::new(pool_owner, true),
+        AccountMeta::new(pool_owner_account, false),
+        AccountMeta::new(payer, false),
+        AccountMeta::new_readonly(escrow_authority, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if let Some((fee_vault, fee_vault_payer)) = fee_vault {
+        accounts.push(AccountMeta::new(fee_vault, false));
+        accounts.push(AccountMeta::new(fee_vault_payer, false));
+    }
+    accounts.extend(
+        venue_accounts
+            .iter()
+            .map(|venue_account| AccountMeta::new(*venue_account, false)),
+    );
+    Instruction {
+        program_id,
+        accounts,
+        data: BinaryOptionInstruction::ClosePool.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a SweepDust instruction
+#[allow(clippy::too_many_arguments)]
+pub fn sweep_dust(
+    program_id: Pubkey,
+    pool_account: Pubkey,
+    escrow_account: Pubkey,
+    long_token_mint: Pubkey,
+    short_token_mint: Pubkey,
+    pool_owner: Pubkey,
+    pool_owner_account: Pubkey,
+    escrow_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(pool_account, false),
+            AccountMeta::new(escrow_account, false),
+            AccountMeta::new_readonly(long_token_mint, false),
+            AccountMeta::new_readonly(short_token_mint, false),
+            AccountMeta::new_readonly(pool_owner, true),
+            AccountMeta::new(pool_owner_account, false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: BinaryOptionInstruction::SweepDust.try_to_vec().unwrap(),
     }
 }
@@ -1,6 +1,8 @@
 pub mod entrypoint;
 pub mod error;
 pub mod instruction;
+pub mod math_utils;
+pub mod oracle;
 pub mod processor;
 pub mod spl_utils;
 pub mod state;
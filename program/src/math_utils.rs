@@ -0,0 +1,369 @@
+// The code generated by construct_uint trips a few clippy lints
+#![allow(clippy::assign_op_pattern)]
+#![allow(clippy::manual_range_contains)]
+
+use {
+    crate::error::BinaryOptionError, solana_program::program_error::ProgramError,
+    std::convert::TryFrom, uint::construct_uint,
+};
+
+construct_uint! {
+    pub struct U256(4);
+}
+
+/// Fixed point numbers are represented as u128 scaled by 10^18
+pub const ONE: u128 = 1_000_000_000_000_000_000;
+/// ln(2) scaled by 10^18
+pub const LN_2: u128 = 693_147_180_559_945_309;
+
+/// e^(-x) for a non-negative fixed point x
+pub fn exp_neg(x: u128) -> u128 {
+    // e^-44 is below the precision of the representation
+    if x > 44 * ONE {
+        return 0;
+    }
+    // Reduce the argument so the series converges quickly: e^-x = 2^-k * e^-r with r < ln(2)
+    let k = x / LN_2;
+    let r = x - k * LN_2;
+    let mut term = ONE;
+    let mut sum = ONE;
+    let mut i = 1;
+    while term > 0 {
+        term = term * r / (i * ONE);
+        sum += term;
+        i += 1;
+    }
+    (ONE * ONE / sum) >> k
+}
+
+/// ln(1 + y) for a fixed point y in [0, 1]
+pub fn ln_1p(y: u128) -> u128 {
+    // ln(1 + y) = 2 * atanh(z) with z = y / (2 + y) <= 1/3
+    let z = y * ONE / (2 * ONE + y);
+    let z_squared = z * z / ONE;
+    let mut power = z;
+    let mut sum = 0;
+    let mut i = 1;
+    while power > 0 {
+        sum += power / i;
+        power = power * z_squared / ONE;
+        i += 2;
+    }
+    2 * sum
+}
+
+/// Cost function of a logarithmic market scoring rule with two outcomes, denominated in the
+/// escrow mint. `q_long` and `q_short` are the number of tokens sold by the market maker,
+/// `liquidity` is the parameter `b` and `unit` is the payout of a winning token (10^decimals).
+///
+/// C(q) = b * ln(e^(q_long / b) + e^(q_short / b)) is evaluated in the numerically stable form
+/// C(q) = max(q_long, q_short) + b * ln(1 + e^(-|q_long - q_short| / b)) and rounded down.
+pub fn lmsr_cost(
+    q_long: u64,
+    q_short: u64,
+    liquidity: u64,
+    unit: u64,
+) -> Result<u64, ProgramError> {
+    if liquidity == 0 {
+        return Err(BinaryOptionError::AmmNotInitialized.into());
+    }
+    let max = q_long.max(q_short) as u128;
+    let spread = (q_long.max(q_short) - q_long.min(q_short)) as u128;
+    let smoothing = ln_1p(exp_neg(spread * ONE / liquidity as u128));
+    let cost = (U256::from(max) * U256::from(ONE) + U256::from(liquidity) * U256::from(smoothing))
+        * U256::from(unit)
+        / U256::from(ONE);
+    if cost > U256::from(u64::MAX) {
+        return Err(BinaryOptionError::MathOverflow.into());
+    }
+    Ok(cost.as_u64())
+}
+
+/// Worst case loss of the market maker, b * ln(2), rounded up
+pub fn lmsr_max_loss(liquidity: u64, unit: u64) -> Result<u64, ProgramError> {
+    let loss = U256::from(liquidity) * U256::from(unit) * U256::from(LN_2) / U256::from(ONE);
+    u64::try_from(loss.as_u128() + 1).map_err(|_| BinaryOptionError::MathOverflow.into())
+}
+
+/// Largest integer whose square is at most x (Babylonian method)
+fn integer_sqrt(x: U256) -> U256 {
+    if x.is_zero() {
+        return x;
+    }
+    let mut root = x;
+    let mut next = (x >> 1) + 1;
+    while next < root {
+        root = next;
+        next = (x / next + next) >> 1;
+    }
+    root
+}
+
+fn ceil_sqrt(x: U256) -> U256 {
+    let root = integer_sqrt(x);
+    if root * root < x {
+        root + 1
+    } else {
+        root
+    }
+}
+
+fn to_u64(x: U256) -> Result<u64, ProgramError> {
+    if x > U256::from(u64::MAX) {
+        return Err(BinaryOptionError::MathOverflow.into());
+    }
+    Ok(x.as_u64())
+}
+
+fn checked_mul(x: U256, y: U256) -> Result<U256, ProgramError> {
+    x.checked_mul(y)
+        .ok_or_else(|| BinaryOptionError::MathOverflow.into())
+}
+
+fn checked_add(x: U256, y: U256) -> Result<U256, ProgramError> {
+    x.checked_add(y)
+        .ok_or_else(|| BinaryOptionError::MathOverflow.into())
+}
+
+/// Collateral that has to be added to a constant product pool in order to take `amount_out` out
+/// of the side with (virtual) balance `balance_out`, rounded up. All amounts are denominated in
+/// the escrow mint. Solves (balance_out + x - amount_out) * (balance_in + x) = balance_out * balance_in
+pub fn cpmm_buy_cost(
+    balance_out: u128,
+    balance_in: u128,
+    amount_out: u128,
+) -> Result<u64, ProgramError> {
+    let (balance_out, balance_in, amount_out) = (
+        U256::from(balance_out),
+        U256::from(balance_in),
+        U256::from(amount_out),
+    );
+    let total = balance_out + balance_in;
+    let four_ac = checked_mul(U256::from(4) * amount_out, balance_in)?;
+    // x = (sqrt(b^2 + 4ac) - b) / 2 with b = balance_out + balance_in - amount_out
+    let numerator = if total >= amount_out {
+        let b = total - amount_out;
+        ceil_sqrt(checked_add(checked_mul(b, b)?, four_ac)?) - b
+    } else {
+        let b = amount_out - total;
+        ceil_sqrt(checked_add(checked_mul(b, b)?, four_ac)?) + b
+    };
+    to_u64((numerator + 1) / 2)
+}
+
+/// Collateral that can be taken out of a constant product pool after adding `amount_in` to the
+/// side with (virtual) balance `balance_in`, rounded down. All amounts are denominated in the
+/// escrow mint. Solves (balance_in + amount_in - y) * (balance_out - y) = balance_in * balance_out
+pub fn cpmm_sell_proceeds(
+    balance_in: u128,
+    balance_out: u128,
+    amount_in: u128,
+) -> Result<u64, ProgramError> {
+    let (balance_in, balance_out, amount_in) = (
+        U256::from(balance_in),
+        U256::from(balance_out),
+        U256::from(amount_in),
+    );
+    // y = (b - sqrt(b^2 - 4ac)) / 2 with b = balance_in + amount_in + balance_out
+    let b = balance_in + amount_in + balance_out;
+    // 4ac <= b^2, since b >= a + c
+    let discriminant = checked_mul(b, b)? - U256::from(4) * amount_in * balance_out;
+    to_u64((b - ceil_sqrt(discriminant)) / 2)
+}
+
+/// Collateral paid for `size` contracts at `price` per contract
+pub fn notional(size: u64, price: u64) -> Result<u64, ProgramError> {
+    size.checked_mul(price)
+        .ok_or_else(|| BinaryOptionError::MathOverflow.into())
+}
+
+/// Share of `amount` that corresponds to `numerator / denominator`, rounded down
+pub fn pro_rata(amount: u128, numerator: u128, denominator: u128) -> Result<u64, ProgramError> {
+    if denominator == 0 {
+        return Err(BinaryOptionError::MathOverflow.into());
+    }
+    to_u64(U256::from(amount) * U256::from(numerator) / U256::from(denominator))
+}
+
+/// Fee of `fee_bps` basis points on `amount`, rounded up
+pub fn fee_amount(amount: u64, fee_bps: u16) -> u64 {
+    (amount as u128 * fee_bps as u128).div_ceil(10_000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const UNIT: u64 = 100;
+
+    fn to_f64(x: u128) -> f64 {
+        x as f64 / ONE as f64
+    }
+
+    #[test]
+    fn test_exp_neg_matches_f64() {
+        for i in 0..=4_400 {
+            let x = i as u128 * ONE / 100;
+            let expected = (-to_f64(x)).exp();
+            let error = (to_f64(exp_neg(x)) - expected).abs();
+            assert!(
+                error <= expected * 1e-12 + 1e-17,
+                "e^-{} = {}, expected {}",
+                to_f64(x),
+                to_f64(exp_neg(x)),
+                expected
+            );
+        }
+        assert_eq!(exp_neg(0), ONE);
+        assert_eq!(exp_neg(45 * ONE), 0);
+    }
+
+    #[test]
+    fn test_ln_1p_matches_f64() {
+        for i in 0..=1_000 {
+            let y = i as u128 * ONE / 1_000;
+            let expected = to_f64(y).ln_1p();
+            let error = (to_f64(ln_1p(y)) - expected).abs();
+            assert!(
+                error <= 1e-15,
+                "ln(1 + {}) = {}, expected {}",
+                to_f64(y),
+                to_f64(ln_1p(y)),
+                expected
+            );
+        }
+        assert_eq!(ln_1p(0), 0);
+    }
+
+    #[test]
+    fn test_lmsr_cost_matches_f64() {
+        for &(q_long, q_short, liquidity) in [
+            (0, 0, 100),
+            (10, 0, 100),
+            (0, 250, 100),
+            (1_000, 999, 50),
+            (123_456, 100_000, 10_000),
+        ]
+        .iter()
+        {
+            let b = liquidity as f64;
+            let expected =
+                b * ((q_long as f64 / b).exp() + (q_short as f64 / b).exp()).ln() * UNIT as f64;
+            let cost = lmsr_cost(q_long, q_short, liquidity, UNIT).unwrap();
+            assert!(
+                (cost as f64 - expected).abs() <= 1.0 + expected * 1e-12,
+                "C({}, {}) = {}, expected {}",
+                q_long,
+                q_short,
+                cost,
+                expected
+            );
+        }
+        assert_eq!(
+            lmsr_cost(1, 1, 0, UNIT),
+            Err(BinaryOptionError::AmmNotInitialized.into())
+        );
+    }
+
+    #[test]
+    fn test_lmsr_max_loss() {
+        // b * ln(2) = 69.3147... contracts of 100 base units
+        assert_eq!(lmsr_max_loss(10, UNIT).unwrap(), 694);
+        assert_eq!(lmsr_max_loss(0, UNIT).unwrap(), 1);
+        assert_eq!(lmsr_max_loss(1_000_000, UNIT).unwrap(), 69_314_719);
+    }
+
+    #[test]
+    fn test_cpmm_quotes() {
+        // (100 + 20 - 36) * (100 + 20) = 10080 is the first product of at least 100 * 100
+        assert_eq!(cpmm_buy_cost(100, 100, 36).unwrap(), 20);
+        // (100 + 36 - 16) * (100 - 16) = 10080 is the last one
+        assert_eq!(cpmm_sell_proceeds(100, 100, 36).unwrap(), 16);
+        assert_eq!(cpmm_buy_cost(100, 100, 0).unwrap(), 0);
+        assert_eq!(cpmm_sell_proceeds(100, 100, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_cpmm_overflow() {
+        let max = u128::MAX;
+        assert_eq!(
+            cpmm_buy_cost(max, max, max),
+            Err(BinaryOptionError::MathOverflow.into())
+        );
+        assert_eq!(
+            cpmm_sell_proceeds(max, max, max),
+            Err(BinaryOptionError::MathOverflow.into())
+        );
+        // Balances of u64::MAX tokens at 19 decimals overflow the square instead of panicking
+        let balance = u64::MAX as u128 * 10u128.pow(19);
+        assert_eq!(
+            cpmm_buy_cost(balance, balance, balance / 2),
+            Err(BinaryOptionError::MathOverflow.into())
+        );
+        assert_eq!(
+            cpmm_sell_proceeds(balance, balance, balance),
+            Err(BinaryOptionError::MathOverflow.into())
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_cpmm_buy_preserves_product(
+            balance_out in 1u128..1_000_000_000_000,
+            balance_in in 1u128..1_000_000_000_000,
+            amount_out in 0u128..1_000_000_000_000,
+        ) {
+            let product = balance_out * balance_in;
+            let x = cpmm_buy_cost(balance_out, balance_in, amount_out).unwrap() as u128;
+            // Rounding favors the pool, but by no more than one base unit
+            prop_assert!(balance_out + x >= amount_out);
+            prop_assert!((balance_out + x - amount_out) * (balance_in + x) >= product);
+            if x > 0 && balance_out + x > amount_out {
+                prop_assert!((balance_out + x - 1 - amount_out) * (balance_in + x - 1) < product);
+            }
+        }
+
+        #[test]
+        fn test_cpmm_sell_preserves_product(
+            balance_in in 1u128..1_000_000_000_000,
+            balance_out in 1u128..1_000_000_000_000,
+            amount_in in 0u128..1_000_000_000_000,
+        ) {
+            let product = balance_in * balance_out;
+            let y = cpmm_sell_proceeds(balance_in, balance_out, amount_in).unwrap() as u128;
+            prop_assert!(y <= balance_out);
+            prop_assert!((balance_in + amount_in - y) * (balance_out - y) >= product);
+            if y < balance_out {
+                prop_assert!((balance_in + amount_in - y - 1) * (balance_out - y - 1) < product);
+            }
+        }
+
+        #[test]
+        fn test_lmsr_cost_is_monotone(
+            q_long in 0u64..10_000_000,
+            q_short in 0u64..10_000_000,
+            liquidity in 1u64..10_000_000,
+            n in 1u64..1_000,
+        ) {
+            let cost = lmsr_cost(q_long, q_short, liquidity, UNIT).unwrap();
+            prop_assert!(lmsr_cost(q_long + n, q_short, liquidity, UNIT).unwrap() >= cost);
+            prop_assert!(lmsr_cost(q_long, q_short + n, liquidity, UNIT).unwrap() >= cost);
+        }
+
+        #[test]
+        fn test_lmsr_max_loss_bounds_market_maker_loss(
+            q_long in 0u64..10_000_000,
+            q_short in 0u64..10_000_000,
+            liquidity in 1u64..10_000_000,
+        ) {
+            // Traders paid C(q) - C(0) in total, and the winning side is paid out in full
+            let paid = lmsr_cost(q_long, q_short, liquidity, UNIT).unwrap()
+                - lmsr_cost(0, 0, liquidity, UNIT).unwrap();
+            let max_loss = lmsr_max_loss(liquidity, UNIT).unwrap();
+            for &winners in [q_long, q_short].iter() {
+                prop_assert!(winners * UNIT <= paid + max_loss);
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::error::BinaryOptionError;
+use arrayref::array_ref;
+use solana_program::{program_error::ProgramError, pubkey, pubkey::Pubkey};
+
+/// Pyth oracle program on mainnet, the only program whose price feeds can settle a pool
+pub const PYTH_PROGRAM_ID: Pubkey = pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+/// Owner of the price feeds of local tests, only accepted with the `mock-oracle` feature
+pub const MOCK_ORACLE_PROGRAM_ID: Pubkey = pubkey!("MockPythProgram1111111111111111111111111111");
+/// Magic number at the start of every Pyth account
+pub const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+pub const PYTH_VERSION_2: u32 = 2;
+pub const PYTH_ACCOUNT_TYPE_PRICE: u32 = 3;
+/// Aggregate status of a price that is currently being published
+pub const PYTH_STATUS_TRADING: u32 = 1;
+/// Size of a v2 price account up to (and including) the aggregate price
+const PYTH_PRICE_LEN: usize = 240;
+
+/// Magic number of the mock price layout ("mock"), only accepted with the `mock-oracle` feature
+pub const MOCK_MAGIC: u32 = 0x6d6f636b;
+/// magic (u32), expo (i32), price (i64), conf (u64), publish_slot (u64), publish_time (i64)
+pub const MOCK_PRICE_LEN: usize = 40;
+
+/// Whether the price feeds owned by `program_id` can be used to settle a pool
+pub fn is_supported_oracle_program(program_id: &Pubkey) -> bool {
+    *program_id == PYTH_PROGRAM_ID
+        || (cfg!(feature = "mock-oracle") && *program_id == MOCK_ORACLE_PROGRAM_ID)
+}
+
+/// Aggregate price of a feed, `price` and `conf` are scaled by 10^expo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_slot: u64,
+    /// Unix timestamp at which the aggregate price was published
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    /// Parses a Pyth v2 price account (or a mock price account in tests). Prices that are not
+    /// currently trading (halted, unknown, in auction) are rejected.
+    pub fn load(data: &[u8]) -> Result<OraclePrice, ProgramError> {
+        if data.len() < 4 {
+            return Err(BinaryOptionError::InvalidOracleAccount.into());
+        }
+        match u32::from_le_bytes(*array_ref![data, 0, 4]) {
+            PYTH_MAGIC => Self::load_pyth(data),
+            #[cfg(feature = "mock-oracle")]
+            MOCK_MAGIC => Self::load_mock(data),
+            _ => Err(BinaryOptionError::InvalidOracleAccount.into()),
+        }
+    }
+
+    fn load_pyth(data: &[u8]) -> Result<OraclePrice, ProgramError> {
+        if data.len() < PYTH_PRICE_LEN
+            || u32::from_le_bytes(*array_ref![data, 4, 4]) != PYTH_VERSION_2
+            || u32::from_le_bytes(*array_ref![data, 8, 4]) != PYTH_ACCOUNT_TYPE_PRICE
+        {
+            return Err(BinaryOptionError::InvalidOracleAccount.into());
+        }
+        // The aggregate price starts at offset 208: price, conf, status, corp_act, pub_slot
+        if u32::from_le_bytes(*array_ref![data, 224, 4]) != PYTH_STATUS_TRADING {
+            return Err(BinaryOptionError::InvalidOracleAccount.into());
+        }
+        Ok(OraclePrice {
+            expo: i32::from_le_bytes(*array_ref![data, 20, 4]),
+            price: i64::from_le_bytes(*array_ref![data, 208, 8]),
+            conf: u64::from_le_bytes(*array_ref![data, 216, 8]),
+            publish_slot: u64::from_le_bytes(*array_ref![data, 232, 8]),
+            publish_time: i64::from_le_bytes(*array_ref![data, 96, 8]),
+        })
+    }
+
+    #[cfg(feature = "mock-oracle")]
+    fn load_mock(data: &[u8]) -> Result<OraclePrice, ProgramError> {
+        if data.len() < MOCK_PRICE_LEN {
+            return Err(BinaryOptionError::InvalidOracleAccount.into());
+        }
+        Ok(OraclePrice {
+            expo: i32::from_le_bytes(*array_ref![data, 4, 4]),
+            price: i64::from_le_bytes(*array_ref![data, 8, 8]),
+            conf: u64::from_le_bytes(*array_ref![data, 16, 8]),
+            publish_slot: u64::from_le_bytes(*array_ref![data, 24, 8]),
+            publish_time: i64::from_le_bytes(*array_ref![data, 32, 8]),
+        })
+    }
+
+    /// Serializes a price in the mock layout
+    pub fn to_mock_bytes(&self) -> [u8; MOCK_PRICE_LEN] {
+        let mut data = [0; MOCK_PRICE_LEN];
+        data[0..4].copy_from_slice(&MOCK_MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&self.expo.to_le_bytes());
+        data[8..16].copy_from_slice(&self.price.to_le_bytes());
+        data[16..24].copy_from_slice(&self.conf.to_le_bytes());
+        data[24..32].copy_from_slice(&self.publish_slot.to_le_bytes());
+        data[32..40].copy_from_slice(&self.publish_time.to_le_bytes());
+        data
+    }
+
+    /// Whether the price was published between `start` and `end` (both inclusive)
+    pub fn is_published_between(&self, start: i64, end: i64) -> bool {
+        start <= self.publish_time && self.publish_time <= end
+    }
+
+    /// Whether the confidence interval is at most `max_confidence_bps` of the price
+    pub fn is_confident(&self, max_confidence_bps: u16) -> bool {
+        self.conf as u128 * 10_000 <= max_confidence_bps as u128 * self.price.unsigned_abs() as u128
+    }
+}
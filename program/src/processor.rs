@@ -1,12 +1,25 @@
 use crate::{
     error::BinaryOptionError,
-    instruction::BinaryOptionInstruction,
+    instruction::{
+        BinaryOptionInstruction, CollectAccounts, ConfigureOracleArgs, InitializeCommitteeArgs,
+        InitializeResolverArgs,
+    },
+    math_utils::{
+        cpmm_buy_cost, cpmm_sell_proceeds, fee_amount, lmsr_cost, lmsr_max_loss, notional, pro_rata,
+    },
+    oracle::{is_supported_oracle_program, OraclePrice},
     spl_utils::{
-        spl_approve, spl_burn, spl_burn_signed, spl_initialize, spl_mint_initialize, spl_mint_to,
-        spl_set_authority, spl_token_transfer, spl_token_transfer_signed,
+        spl_approve, spl_burn, spl_burn_signed, spl_close_account_signed, spl_initialize,
+        spl_mint_initialize, spl_mint_to, spl_set_authority, spl_token_transfer,
+        spl_token_transfer_signed,
     },
-    state::BinaryOption,
-    system_utils::{create_new_account, create_or_allocate_account_raw},
+    state::{
+        trade_effects, BinaryOption, CategoricalMarket, CpmmPool, MarketType, OptimisticResolver,
+        Order, OrderBook, OrderSide, Outcome, PoolStatus, PositionSide, ResolutionSource,
+        ResolverCommittee, Vote, MAX_DECIMALS, MAX_FEE_BPS, MAX_OUTCOMES, MAX_VOTERS,
+        ORACLE_VOID_GRACE_PERIOD,
+    },
+    system_utils::{close_account, create_new_account, create_or_allocate_account_raw},
     validation_utils::{
         assert_initialized, assert_keys_equal, assert_keys_unequal, assert_owned_by,
     },
@@ -14,12 +27,16 @@ use crate::{
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
     msg,
     program_error::ProgramError,
+    program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
+    sysvar::Sysvar,
 };
+use spl_associated_token_account::get_associated_token_address;
 use spl_token::{
     instruction::AuthorityType,
     state::{Account, Mint},
@@ -36,7 +53,12 @@ impl Processor {
         match instruction {
             BinaryOptionInstruction::InitializeBinaryOption(args) => {
                 msg!("Instruction: InitializeBinaryOption");
-                process_initialize_binary_option(program_id, accounts, args.decimals)
+                process_initialize_binary_option(
+                    program_id,
+                    accounts,
+                    args.decimals,
+                    args.expiry_unix_timestamp,
+                )
             }
             BinaryOptionInstruction::Trade(args) => {
                 msg!("Instruction: Trade");
@@ -50,12 +72,218 @@ impl Processor {
             }
             BinaryOptionInstruction::Settle => {
                 msg!("Instruction: Settle");
-                process_settle(program_id, accounts)
+                process_settle(program_id, accounts, None)
             }
             BinaryOptionInstruction::Collect => {
                 msg!("Instruction: Collect");
                 process_collect(program_id, accounts)
             }
+            BinaryOptionInstruction::MintSet(args) => {
+                msg!("Instruction: MintSet");
+                process_mint_set(program_id, accounts, args.size)
+            }
+            BinaryOptionInstruction::RedeemSet(args) => {
+                msg!("Instruction: RedeemSet");
+                process_redeem_set(program_id, accounts, args.size)
+            }
+            BinaryOptionInstruction::InitializeOrderBook => {
+                msg!("Instruction: InitializeOrderBook");
+                process_initialize_order_book(program_id, accounts)
+            }
+            BinaryOptionInstruction::PlaceOrder(args) => {
+                msg!("Instruction: PlaceOrder");
+                process_place_order(program_id, accounts, args.side, args.price, args.size)
+            }
+            BinaryOptionInstruction::CancelOrder(args) => {
+                msg!("Instruction: CancelOrder");
+                process_cancel_order(program_id, accounts, args.order_id)
+            }
+            BinaryOptionInstruction::MatchOrders(args) => {
+                msg!("Instruction: MatchOrders");
+                process_match_orders(program_id, accounts, args.bid_order_id, args.ask_order_id)
+            }
+            BinaryOptionInstruction::InitializeAmm(args) => {
+                msg!("Instruction: InitializeAmm");
+                process_initialize_amm(program_id, accounts, args.liquidity, args.subsidy)
+            }
+            BinaryOptionInstruction::AmmBuy(args) => {
+                msg!("Instruction: AmmBuy");
+                process_amm_trade(
+                    program_id,
+                    accounts,
+                    args.side,
+                    args.size,
+                    args.collateral_limit,
+                    true,
+                )
+            }
+            BinaryOptionInstruction::AmmSell(args) => {
+                msg!("Instruction: AmmSell");
+                process_amm_trade(
+                    program_id,
+                    accounts,
+                    args.side,
+                    args.size,
+                    args.collateral_limit,
+                    false,
+                )
+            }
+            BinaryOptionInstruction::AmmWithdraw => {
+                msg!("Instruction: AmmWithdraw");
+                process_amm_withdraw(program_id, accounts)
+            }
+            BinaryOptionInstruction::InitializeCpmm(args) => {
+                msg!("Instruction: InitializeCpmm");
+                process_initialize_cpmm(program_id, accounts, args.fee_bps)
+            }
+            BinaryOptionInstruction::CpmmAddLiquidity(args) => {
+                msg!("Instruction: CpmmAddLiquidity");
+                process_cpmm_liquidity(program_id, accounts, args.amount, true)
+            }
+            BinaryOptionInstruction::CpmmRemoveLiquidity(args) => {
+                msg!("Instruction: CpmmRemoveLiquidity");
+                process_cpmm_liquidity(program_id, accounts, args.shares, false)
+            }
+            BinaryOptionInstruction::CpmmBuy(args) => {
+                msg!("Instruction: CpmmBuy");
+                process_cpmm_trade(
+                    program_id,
+                    accounts,
+                    args.side,
+                    args.size,
+                    args.collateral_limit,
+                    true,
+                )
+            }
+            BinaryOptionInstruction::CpmmSell(args) => {
+                msg!("Instruction: CpmmSell");
+                process_cpmm_trade(
+                    program_id,
+                    accounts,
+                    args.side,
+                    args.size,
+                    args.collateral_limit,
+                    false,
+                )
+            }
+            BinaryOptionInstruction::Void(args) => {
+                msg!("Instruction: Void");
+                process_void(program_id, accounts, args.void_price)
+            }
+            BinaryOptionInstruction::ConfigureOracle(args) => {
+                msg!("Instruction: ConfigureOracle");
+                process_configure_oracle(program_id, accounts, args)
+            }
+            BinaryOptionInstruction::SettleFromOracle => {
+                msg!("Instruction: SettleFromOracle");
+                process_settle_from_oracle(program_id, accounts)
+            }
+            BinaryOptionInstruction::InitializeResolver(args) => {
+                msg!("Instruction: InitializeResolver");
+                process_initialize_resolver(program_id, accounts, args)
+            }
+            BinaryOptionInstruction::ProposeOutcome(args) => {
+                msg!("Instruction: ProposeOutcome");
+                process_post_bond(program_id, accounts, Some(args.outcome))
+            }
+            BinaryOptionInstruction::DisputeOutcome => {
+                msg!("Instruction: DisputeOutcome");
+                process_post_bond(program_id, accounts, None)
+            }
+            BinaryOptionInstruction::FinalizeOutcome => {
+                msg!("Instruction: FinalizeOutcome");
+                process_finalize_outcome(program_id, accounts, None)
+            }
+            BinaryOptionInstruction::ResolveDispute(args) => {
+                msg!("Instruction: ResolveDispute");
+                process_finalize_outcome(program_id, accounts, Some(args.outcome))
+            }
+            BinaryOptionInstruction::InitializeCommittee(args) => {
+                msg!("Instruction: InitializeCommittee");
+                process_initialize_committee(program_id, accounts, args)
+            }
+            BinaryOptionInstruction::Vote(args) => {
+                msg!("Instruction: Vote");
+                process_vote(program_id, accounts, args.outcome)
+            }
+            BinaryOptionInstruction::InitializeCategorical(args) => {
+                msg!("Instruction: InitializeCategorical");
+                process_initialize_categorical(
+                    program_id,
+                    accounts,
+                    args.decimals,
+                    args.expiry_unix_timestamp,
+                )
+            }
+            BinaryOptionInstruction::CategoricalMintSet(args) => {
+                msg!("Instruction: CategoricalMintSet");
+                process_categorical_set(program_id, accounts, args.size, true)
+            }
+            BinaryOptionInstruction::CategoricalRedeemSet(args) => {
+                msg!("Instruction: CategoricalRedeemSet");
+                process_categorical_set(program_id, accounts, args.size, false)
+            }
+            BinaryOptionInstruction::CategoricalTrade(args) => {
+                msg!("Instruction: CategoricalTrade");
+                process_categorical_trade(program_id, accounts, args.outcome, args.size, args.price)
+            }
+            BinaryOptionInstruction::CategoricalSettle(args) => {
+                msg!("Instruction: CategoricalSettle");
+                process_categorical_settle(program_id, accounts, args.winning_outcome)
+            }
+            BinaryOptionInstruction::CategoricalCollect => {
+                msg!("Instruction: CategoricalCollect");
+                process_categorical_collect(program_id, accounts)
+            }
+            BinaryOptionInstruction::ConfigureScalar(args) => {
+                msg!("Instruction: ConfigureScalar");
+                process_configure_scalar(program_id, accounts, args.lower_bound, args.upper_bound)
+            }
+            BinaryOptionInstruction::SettleScalar(args) => {
+                msg!("Instruction: SettleScalar");
+                process_settle(program_id, accounts, Some(args.value))
+            }
+            BinaryOptionInstruction::ConfigureFees(args) => {
+                msg!("Instruction: ConfigureFees");
+                process_configure_fees(
+                    program_id,
+                    accounts,
+                    args.protocol_fee_bps,
+                    args.creator_fee_bps,
+                )
+            }
+            BinaryOptionInstruction::WithdrawFees => {
+                msg!("Instruction: WithdrawFees");
+                process_withdraw_fees(program_id, accounts)
+            }
+            BinaryOptionInstruction::ProposeOwner => {
+                msg!("Instruction: ProposeOwner");
+                process_propose_owner(program_id, accounts)
+            }
+            BinaryOptionInstruction::AcceptOwner => {
+                msg!("Instruction: AcceptOwner");
+                process_accept_owner(program_id, accounts)
+            }
+            BinaryOptionInstruction::ClosePool => {
+                msg!("Instruction: ClosePool");
+                process_close_pool(program_id, accounts)
+            }
+            BinaryOptionInstruction::SweepDust => {
+                msg!("Instruction: SweepDust");
+                process_sweep_dust(program_id, accounts)
+            }
+            BinaryOptionInstruction::Halt => {
+                msg!("Instruction: Halt");
+                process_set_halted(program_id, accounts, true)
+            }
+            BinaryOptionInstruction::Resume => {
+                msg!("Instruction: Resume");
+                process_set_halted(program_id, accounts, false)
+            }
+            BinaryOptionInstruction::RefundBonds => {
+                msg!("Instruction: RefundBonds");
+                process_refund_bonds(program_id, accounts)
+            }
         }
     }
 }
@@ -64,6 +292,7 @@ pub fn process_initialize_binary_option(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     decimals: u8,
+    expiry_unix_timestamp: i64,
 ) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let binary_option_account_info = next_account_info(account_info_iter)?;
@@ -77,6 +306,9 @@ pub fn process_initialize_binary_option(
     let system_account_info = next_account_info(account_info_iter)?;
     let rent_info = next_account_info(account_info_iter)?;
 
+    if decimals > MAX_DECIMALS {
+        return Err(ProgramError::InvalidArgument);
+    }
     create_new_account(
         mint_authority_info,
         long_token_mint_info,
@@ -169,13 +401,15 @@ pub fn process_initialize_binary_option(
         BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
     binary_option.decimals = decimals;
     binary_option.circulation = 0;
-    binary_option.settled = false;
+    binary_option.status = PoolStatus::Open;
     binary_option.long_mint_account_pubkey = *long_token_mint_info.key;
     binary_option.short_mint_account_pubkey = *short_token_mint_info.key;
     binary_option.escrow_mint_account_pubkey = *escrow_mint_info.key;
     binary_option.escrow_account_pubkey = *escrow_account_info.key;
     binary_option.owner = *update_authority_info.key;
-    binary_option.serialize(&mut *binary_option_account_info.data.borrow_mut())?;
+    binary_option.payer = *update_authority_info.key;
+    binary_option.expiry_unix_timestamp = expiry_unix_timestamp;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
 
     Ok(())
 }
@@ -214,6 +448,7 @@ pub fn process_trade(
     let seller_account: Account = assert_initialized(seller_account_info)?;
     let mut binary_option =
         BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let fee_vault_info = next_fee_vault_info(account_info_iter, &binary_option)?;
 
     // Get program derived address for escrow
     let (authority_key, bump_seed) = Pubkey::find_program_address(
@@ -234,12 +469,21 @@ pub fn process_trade(
     ];
 
     // Validate data
-    if buy_price + sell_price != u64::pow(10, binary_option.decimals as u32) {
+    if !buyer_info.is_signer || !seller_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if buy_price.checked_add(sell_price) != Some(u64::pow(10, binary_option.decimals as u32)) {
         return Err(BinaryOptionError::TradePricesIncorrect.into());
     }
-    if binary_option.settled {
+    if binary_option.is_settled() {
         return Err(BinaryOptionError::AlreadySettled.into());
     }
+    if binary_option.status == PoolStatus::Halted
+        || binary_option.is_expired(Clock::get()?.unix_timestamp)
+    {
+        return Err(BinaryOptionError::TradingClosed.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
     assert_keys_equal(*token_program_info.key, spl_token::id())?;
     assert_keys_unequal(*buyer_info.key, *seller_info.key)?;
     assert_keys_equal(*long_token_mint_info.owner, spl_token::id())?;
@@ -288,251 +532,55 @@ pub fn process_trade(
     let n = size;
     let n_b = buyer_short_token_account.amount;
     let n_s = seller_long_token_account.amount;
-
-    let mut b_l = buyer_long_token_account.amount;
-    let mut b_s = n_b;
-    let mut s_l = n_s;
-    let mut s_s = seller_short_token_account.amount;
-
-    match [n_b >= n, n_s >= n] {
-        /*
-        When n is less than both n_b and n_s, this means that both buyer and seller are simply reducing their existing inventory.
-        Therefore, we can just remove n long tokens and n short tokens from circulation. Both parties are also entitled to the locked up
-        funds for their positions that were closed. This always results in a decrease in total circulation.
-        */
-        [true, true] => {
-            msg!("Case 1");
-            spl_burn(
-                token_program_info,
-                buyer_short_token_account_info,
-                short_token_mint_info,
-                buyer_info,
-                n,
-            )?;
-            spl_burn(
-                token_program_info,
-                seller_long_token_account_info,
-                long_token_mint_info,
-                seller_info,
-                n,
-            )?;
-            spl_token_transfer_signed(
-                token_program_info,
-                escrow_account_info,
-                buyer_account_info,
-                authority_info,
-                n * sell_price,
-                seeds,
-            )?;
-            spl_token_transfer_signed(
-                token_program_info,
-                escrow_account_info,
-                seller_account_info,
-                authority_info,
-                n * buy_price,
-                seeds,
-            )?;
-            b_s -= n;
-            s_l -= n;
-            binary_option.decrement_supply(n)?;
-        }
-        /*
-        When n is greater than both n_b and n_s, this means that both buyer and seller have put on a position that is different from their
-        existing position. We will first burn the tokens of representing the opposite position and then mint new tokens to ensure the buyer's
-        change is +n and the seller's change is -n. Both parties are also entitled to the locked up funds for their positions that were closed.
-        The net change in tokens can be calculated as follows: (-n_b - n_s + 2n - n_b - n_s) / 2 = n - n_b - n_s. If this quantity is positive, this
-        means that the trade causes a net increase in the total supply of contracts in the betting pool. Otherwise, it results in a net decrease
-        in total circulation.
-        */
-        [false, false] => {
-            msg!("Case 2");
-            spl_burn(
-                token_program_info,
-                buyer_short_token_account_info,
-                short_token_mint_info,
-                buyer_info,
-                n_b,
-            )?;
-            spl_burn(
-                token_program_info,
-                seller_long_token_account_info,
-                long_token_mint_info,
-                seller_info,
-                n_s,
-            )?;
-            b_s -= n_b;
-            s_l -= n_s;
-            spl_mint_to(
-                token_program_info,
-                buyer_long_token_account_info,
-                long_token_mint_info,
-                authority_info,
-                n - n_b,
-                seeds,
-            )?;
-            spl_mint_to(
-                token_program_info,
-                seller_short_token_account_info,
-                short_token_mint_info,
-                authority_info,
-                n - n_s,
-                seeds,
-            )?;
-            b_l += n - n_b;
-            s_s += n - n_s;
-            spl_token_transfer(
-                token_program_info,
-                buyer_account_info,
-                escrow_account_info,
-                buyer_info,
-                (n - n_b) * buy_price,
-            )?;
-            spl_token_transfer(
-                token_program_info,
-                seller_account_info,
-                escrow_account_info,
-                seller_info,
-                (n - n_s) * sell_price,
-            )?;
-            spl_token_transfer_signed(
-                token_program_info,
-                escrow_account_info,
-                buyer_account_info,
-                authority_info,
-                n_b * sell_price,
-                seeds,
-            )?;
-            spl_token_transfer_signed(
-                token_program_info,
-                escrow_account_info,
-                seller_account_info,
-                authority_info,
-                n_s * buy_price,
-                seeds,
-            )?;
-            if n > n_b + n_s {
-                binary_option.increment_supply(n - n_b - n_s);
-            } else {
-                binary_option.decrement_supply(n - n_b - n_s)?;
-            }
-        }
-        /*
-        When n is greater than n_b but less than n_s, this means that the buyer has put on a position that is different from their
-        existing position, and the seller has reduced their inventory. We will burn and mint tokens such the buyer's net change in
-        position is +n and the seller's net change is -n. Both parties are also entitled to the locked up funds for their positions that were closed.
-        The net change in tokens can be calculated as follows: (-n - n_s + n - n_s) / 2 = -n_s. This always results in a decrease in total
-        circulation.
-        */
-        [true, false] => {
-            msg!("Case 3");
-            spl_burn(
-                token_program_info,
-                buyer_short_token_account_info,
-                short_token_mint_info,
-                buyer_info,
-                n,
-            )?;
-            spl_burn(
-                token_program_info,
-                seller_long_token_account_info,
-                long_token_mint_info,
-                seller_info,
-                n_s,
-            )?;
-            b_s -= n;
-            s_l -= n_s;
-            spl_mint_to(
-                token_program_info,
-                seller_short_token_account_info,
-                short_token_mint_info,
-                authority_info,
-                n - n_s,
-                seeds,
-            )?;
-            s_s += n - n_s;
-            spl_token_transfer(
-                token_program_info,
-                seller_account_info,
-                escrow_account_info,
-                seller_info,
-                (n - n_s) * sell_price,
-            )?;
-            spl_token_transfer_signed(
-                token_program_info,
-                escrow_account_info,
-                seller_account_info,
-                authority_info,
-                n_s * buy_price,
-                seeds,
-            )?;
-            spl_token_transfer_signed(
-                token_program_info,
-                escrow_account_info,
-                buyer_account_info,
-                authority_info,
-                n * sell_price,
-                seeds,
-            )?;
-            binary_option.decrement_supply(n_s)?;
-        }
-        /*
-        When n is greater than n_s bust less than n_b, this means that the seller has put on a position that is different from their
-        existing position, and the buyer has reduced their inventory. We will burn and mint tokens such the buyer's net change in
-        position is +n and the seller's net change is -n. Both parties are also entitled to the locked up funds for their positions that were closed.
-        The net change in tokens can be calculated as follows: (-n - n_b + n - n_b) / 2 = -n_b. This always results in a decrease in total
-        circulation.
-        */
-        [false, true] => {
-            msg!("Case 4");
-            spl_burn(
-                token_program_info,
-                seller_long_token_account_info,
-                long_token_mint_info,
-                seller_info,
-                n,
-            )?;
-            spl_burn(
-                token_program_info,
-                buyer_short_token_account_info,
-                short_token_mint_info,
-                buyer_info,
-                n_b,
-            )?;
-            b_s -= n_b;
-            s_l -= n;
-            spl_mint_to(
-                token_program_info,
-                buyer_long_token_account_info,
-                long_token_mint_info,
-                authority_info,
-                n - n_b,
-                seeds,
-            )?;
-            b_l += n - n_b;
+    let (b_l, b_s, s_l, s_s) = execute_trade(
+        &mut binary_option,
+        &TradeAccounts {
+            token_program: token_program_info,
+            escrow_account: escrow_account_info,
+            long_token_mint: long_token_mint_info,
+            short_token_mint: short_token_mint_info,
+            escrow_authority: authority_info,
+        },
+        &TradeParty {
+            authority: buyer_info,
+            source_account: buyer_account_info,
+            destination_account: buyer_account_info,
+            long_token_account: buyer_long_token_account_info,
+            short_token_account: buyer_short_token_account_info,
+            long_amount: buyer_long_token_account.amount,
+            short_amount: n_b,
+        },
+        &TradeParty {
+            authority: seller_info,
+            source_account: seller_account_info,
+            destination_account: seller_account_info,
+            long_token_account: seller_long_token_account_info,
+            short_token_account: seller_short_token_account_info,
+            long_amount: n_s,
+            short_amount: seller_short_token_account.amount,
+        },
+        n,
+        buy_price,
+        sell_price,
+        seeds,
+    )?;
+    // Both traders pay fees on the notional of their side of the trade
+    if let Some(fee_vault_info) = fee_vault_info {
+        for (trader_info, trader_account_info, price) in [
+            (buyer_info, buyer_account_info, buy_price),
+            (seller_info, seller_account_info, sell_price),
+        ]
+        .iter()
+        {
+            let fees = binary_option.fees(notional(n, *price)?);
             spl_token_transfer(
                 token_program_info,
-                buyer_account_info,
-                escrow_account_info,
-                buyer_info,
-                (n - n_b) * buy_price,
-            )?;
-            spl_token_transfer_signed(
-                token_program_info,
-                escrow_account_info,
-                buyer_account_info,
-                authority_info,
-                n_b * sell_price,
-                seeds,
-            )?;
-            spl_token_transfer_signed(
-                token_program_info,
-                escrow_account_info,
-                seller_account_info,
-                authority_info,
-                n * buy_price,
-                seeds,
+                trader_account_info,
+                fee_vault_info,
+                trader_info,
+                total_fees(fees)?,
             )?;
-            binary_option.decrement_supply(n_b)?;
+            binary_option.accrue_fees(fees)?;
         }
     }
     // Delegate the burn authority to the PDA, so a private key is unnecessary on collection
@@ -543,7 +591,7 @@ pub fn process_trade(
         long_token_mint_info,
         authority_info,
         buyer_info,
-        b_l,
+        burn_allowance(&buyer_long_token_account, &authority_key, b_l)?,
         long_token_mint.decimals,
     )?;
     spl_approve(
@@ -552,7 +600,7 @@ pub fn process_trade(
         short_token_mint_info,
         authority_info,
         seller_info,
-        s_s,
+        burn_allowance(&seller_short_token_account, &authority_key, s_s)?,
         short_token_mint.decimals,
     )?;
     spl_approve(
@@ -561,7 +609,7 @@ pub fn process_trade(
         short_token_mint_info,
         authority_info,
         buyer_info,
-        b_s,
+        burn_allowance(&buyer_short_token_account, &authority_key, b_s)?,
         short_token_mint.decimals,
     )?;
     spl_approve(
@@ -570,77 +618,524 @@ pub fn process_trade(
         long_token_mint_info,
         authority_info,
         seller_info,
-        s_l,
+        burn_allowance(&seller_long_token_account, &authority_key, s_l)?,
         long_token_mint.decimals,
     )?;
-    binary_option.serialize(&mut *binary_option_account_info.data.borrow_mut())?;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
     Ok(())
 }
 
-pub fn process_settle(_program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
-    // This should NEVER be called directly (otherwise this is literally a rug)
-    // The `pool_owner_info` needs to approve this action, so the recommended use case is to have a higher
-    // level program own the pool and use an oracle to resolve settlements
-    let account_info_iter = &mut accounts.iter();
-    let binary_option_account_info = next_account_info(account_info_iter)?;
-    let winning_mint_account_info = next_account_info(account_info_iter)?;
-    let pool_owner_info = next_account_info(account_info_iter)?;
-
-    let mut binary_option =
-        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
-    if !pool_owner_info.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
-    }
-    if binary_option.settled {
-        return Err(BinaryOptionError::AlreadySettled.into());
-    }
+/// The accounts shared by both sides of a trade
+pub struct TradeAccounts<'a, 'b> {
+    pub token_program: &'b AccountInfo<'a>,
+    pub escrow_account: &'b AccountInfo<'a>,
+    pub long_token_mint: &'b AccountInfo<'a>,
+    pub short_token_mint: &'b AccountInfo<'a>,
+    pub escrow_authority: &'b AccountInfo<'a>,
+}
 
-    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
-    if *winning_mint_account_info.key == binary_option.long_mint_account_pubkey
-        || *winning_mint_account_info.key == binary_option.short_mint_account_pubkey
-    {
-        binary_option.winning_side_pubkey = *winning_mint_account_info.key;
-    } else {
-        return Err(BinaryOptionError::InvalidWinner.into());
-    }
-    binary_option.settled = true;
-    binary_option.serialize(&mut *binary_option_account_info.data.borrow_mut())?;
-    Ok(())
+/// One side of a trade. The `authority` is either the trader (who signed the transaction) or the
+/// escrow authority PDA, and must be able to move funds out of `source_account` and burn from both
+/// token accounts. `long_amount` and `short_amount` are the positions that may be netted out.
+pub struct TradeParty<'a, 'b> {
+    pub authority: &'b AccountInfo<'a>,
+    pub source_account: &'b AccountInfo<'a>,
+    pub destination_account: &'b AccountInfo<'a>,
+    pub long_token_account: &'b AccountInfo<'a>,
+    pub short_token_account: &'b AccountInfo<'a>,
+    pub long_amount: u64,
+    pub short_amount: u64,
 }
 
-pub fn process_collect(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
-    let account_info_iter = &mut accounts.iter();
-    let binary_option_account_info = next_account_info(account_info_iter)?;
-    let collector_info = next_account_info(account_info_iter)?;
-    let collector_long_token_account_info = next_account_info(account_info_iter)?;
-    let collector_short_token_account_info = next_account_info(account_info_iter)?;
-    let collector_account_info = next_account_info(account_info_iter)?;
-    let long_token_mint_info = next_account_info(account_info_iter)?;
-    let short_token_mint_info = next_account_info(account_info_iter)?;
-    let escrow_account_info = next_account_info(account_info_iter)?;
-    let escrow_authority_info = next_account_info(account_info_iter)?;
-    let token_program_info = next_account_info(account_info_iter)?;
+/// Moves `n` contracts from the seller to the buyer, burning and minting tokens so that existing
+/// positions are netted out. Returns the resulting long and short positions of the buyer followed
+/// by those of the seller.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_trade<'a>(
+    binary_option: &mut BinaryOption,
+    accounts: &TradeAccounts<'a, '_>,
+    buyer: &TradeParty<'a, '_>,
+    seller: &TradeParty<'a, '_>,
+    n: u64,
+    buy_price: u64,
+    sell_price: u64,
+    seeds: &[&[u8]],
+) -> Result<(u64, u64, u64, u64), ProgramError> {
+    let token_program_info = accounts.token_program;
+    let escrow_account_info = accounts.escrow_account;
+    let long_token_mint_info = accounts.long_token_mint;
+    let short_token_mint_info = accounts.short_token_mint;
+    let authority_info = accounts.escrow_authority;
 
-    let collector_long_token_account: Account =
-        assert_initialized(collector_long_token_account_info)?;
-    let collector_short_token_account: Account =
-        assert_initialized(collector_short_token_account_info)?;
-    let collector_account: Account = assert_initialized(collector_account_info)?;
-    let escrow_account: Account = assert_initialized(escrow_account_info)?;
-    let mut binary_option =
-        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let effects = trade_effects(
+        n,
+        buyer.short_amount,
+        seller.long_amount,
+        buyer.long_amount,
+        seller.short_amount,
+        buy_price,
+        sell_price,
+    )?;
 
-    // Get program derived address for escrow
-    let (escrow_owner_key, bump_seed) = Pubkey::find_program_address(
-        &[
-            long_token_mint_info.key.as_ref(),
-            short_token_mint_info.key.as_ref(),
-            token_program_info.key.as_ref(),
-            program_id.as_ref(),
-        ],
-        program_id,
-    );
-    let seeds = &[
+    // The seeds are only needed when an authority is the escrow PDA. Signing with them is
+    // a no-op for authorities that already signed the transaction.
+    if effects.buyer_burn > 0 {
+        spl_burn_signed(
+            token_program_info,
+            buyer.short_token_account,
+            short_token_mint_info,
+            buyer.authority,
+            effects.buyer_burn,
+            seeds,
+        )?;
+    }
+    if effects.seller_burn > 0 {
+        spl_burn_signed(
+            token_program_info,
+            seller.long_token_account,
+            long_token_mint_info,
+            seller.authority,
+            effects.seller_burn,
+            seeds,
+        )?;
+    }
+    if effects.buyer_mint > 0 {
+        spl_mint_to(
+            token_program_info,
+            buyer.long_token_account,
+            long_token_mint_info,
+            authority_info,
+            effects.buyer_mint,
+            seeds,
+        )?;
+    }
+    if effects.seller_mint > 0 {
+        spl_mint_to(
+            token_program_info,
+            seller.short_token_account,
+            short_token_mint_info,
+            authority_info,
+            effects.seller_mint,
+            seeds,
+        )?;
+    }
+    if effects.buyer_deposit > 0 {
+        spl_token_transfer_signed(
+            token_program_info,
+            buyer.source_account,
+            escrow_account_info,
+            buyer.authority,
+            effects.buyer_deposit,
+            seeds,
+        )?;
+    }
+    if effects.seller_deposit > 0 {
+        spl_token_transfer_signed(
+            token_program_info,
+            seller.source_account,
+            escrow_account_info,
+            seller.authority,
+            effects.seller_deposit,
+            seeds,
+        )?;
+    }
+    if effects.buyer_withdrawal > 0 {
+        spl_token_transfer_signed(
+            token_program_info,
+            escrow_account_info,
+            buyer.destination_account,
+            authority_info,
+            effects.buyer_withdrawal,
+            seeds,
+        )?;
+    }
+    if effects.seller_withdrawal > 0 {
+        spl_token_transfer_signed(
+            token_program_info,
+            escrow_account_info,
+            seller.destination_account,
+            authority_info,
+            effects.seller_withdrawal,
+            seeds,
+        )?;
+    }
+    binary_option.apply_supply_delta(effects.supply_delta)?;
+    Ok((
+        effects.buyer_long,
+        effects.buyer_short,
+        effects.seller_long,
+        effects.seller_short,
+    ))
+}
+
+pub fn process_settle(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    resolution_value: Option<i64>,
+) -> ProgramResult {
+    // This should NEVER be called directly (otherwise this is literally a rug)
+    // The `pool_owner_info` needs to approve this action, so the recommended use case is to have a higher
+    // level program own the pool and use an oracle to resolve settlements
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    // Scalar markets are settled with a value instead of a winning mint
+    let winning_mint_account_info = match resolution_value {
+        Some(_) => None,
+        None => Some(next_account_info(account_info_iter)?),
+    };
+    let pool_owner_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+
+    if binary_option.resolution_source != ResolutionSource::Owner {
+        return Err(BinaryOptionError::InvalidResolutionSource.into());
+    }
+
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    match (winning_mint_account_info, resolution_value) {
+        (None, Some(value)) if binary_option.market_type == MarketType::Scalar => {
+            binary_option.resolve_scalar(value);
+        }
+        (Some(winning_mint_account_info), None)
+            if binary_option.market_type == MarketType::Binary =>
+        {
+            if *winning_mint_account_info.key == binary_option.long_mint_account_pubkey {
+                binary_option.resolve(Outcome::Long, 0);
+            } else if *winning_mint_account_info.key == binary_option.short_mint_account_pubkey {
+                binary_option.resolve(Outcome::Short, 0);
+            } else {
+                return Err(BinaryOptionError::InvalidWinner.into());
+            }
+        }
+        _ => return Err(BinaryOptionError::InvalidMarketType.into()),
+    }
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_configure_scalar(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    lower_bound: i64,
+    upper_bound: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.status != PoolStatus::Open {
+        return Err(BinaryOptionError::InvalidPoolStatus.into());
+    }
+    // The terms of the market can't change once positions exist
+    if binary_option.circulation > 0 {
+        return Err(BinaryOptionError::InvalidSupply.into());
+    }
+    if lower_bound >= upper_bound {
+        return Err(ProgramError::InvalidArgument);
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+
+    binary_option.market_type = MarketType::Scalar;
+    binary_option.scalar_lower_bound = lower_bound;
+    binary_option.scalar_upper_bound = upper_bound;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_configure_oracle(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    args: ConfigureOracleArgs,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let oracle_feed_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.status != PoolStatus::Open {
+        return Err(BinaryOptionError::InvalidPoolStatus.into());
+    }
+    // The terms of the market can't change once positions exist
+    if binary_option.circulation > 0 {
+        return Err(BinaryOptionError::InvalidSupply.into());
+    }
+    // Oracle settlement happens at expiry, so the pool has to have one
+    if binary_option.expiry_unix_timestamp == 0
+        || args.max_confidence_bps > 10_000
+        || args.max_publish_delay < 0
+        || args.void_price > u64::pow(10, binary_option.decimals as u32)
+        || binary_option
+            .expiry_unix_timestamp
+            .checked_add(args.max_publish_delay)
+            .is_none()
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    if !is_supported_oracle_program(&args.oracle_program_id) {
+        return Err(BinaryOptionError::UnsupportedOracleProgram.into());
+    }
+    assert_owned_by(oracle_feed_info, &args.oracle_program_id)?;
+    OraclePrice::load(&oracle_feed_info.data.borrow())?;
+
+    binary_option.resolution_source = ResolutionSource::Oracle;
+    binary_option.oracle_feed_pubkey = *oracle_feed_info.key;
+    binary_option.strike_price = args.strike_price;
+    binary_option.comparison = args.comparison;
+    binary_option.max_staleness_slots = args.max_staleness_slots;
+    binary_option.max_confidence_bps = args.max_confidence_bps;
+    binary_option.oracle_program_id = args.oracle_program_id;
+    binary_option.oracle_max_publish_delay = args.max_publish_delay;
+    binary_option.void_price = args.void_price;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_settle_from_oracle(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Permissionless, the outcome only depends on the price feed pinned by ConfigureOracle
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let oracle_feed_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if binary_option.resolution_source != ResolutionSource::Oracle {
+        return Err(BinaryOptionError::InvalidResolutionSource.into());
+    }
+    let clock = Clock::get()?;
+    if !binary_option.is_expired(clock.unix_timestamp) {
+        return Err(BinaryOptionError::MarketNotExpired.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*oracle_feed_info.key, binary_option.oracle_feed_pubkey)?;
+    assert_owned_by(oracle_feed_info, &binary_option.oracle_program_id)?;
+
+    let oracle_price = OraclePrice::load(&oracle_feed_info.data.borrow())?;
+    // The delay bounds how long the caller can wait for a price that suits them
+    if !oracle_price.is_published_between(
+        binary_option.expiry_unix_timestamp,
+        binary_option.oracle_publish_deadline(),
+    ) {
+        return Err(BinaryOptionError::OraclePriceNotAtExpiry.into());
+    }
+    if clock.slot.saturating_sub(oracle_price.publish_slot) > binary_option.max_staleness_slots {
+        return Err(BinaryOptionError::StaleOraclePrice.into());
+    }
+    if !oracle_price.is_confident(binary_option.max_confidence_bps) {
+        return Err(BinaryOptionError::OracleConfidenceTooWide.into());
+    }
+
+    if binary_option.market_type == MarketType::Scalar {
+        // The price is the resolved value, the bounds are expressed with the feed's exponent
+        binary_option.resolve_scalar(oracle_price.price);
+    } else {
+        let outcome = if binary_option
+            .comparison
+            .holds(oracle_price.price, binary_option.strike_price)
+        {
+            Outcome::Long
+        } else {
+            Outcome::Short
+        };
+        binary_option.resolve(outcome, 0);
+    }
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_void(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    void_price: u64,
+) -> ProgramResult {
+    // Like `Settle`, the pool owner is trusted to call this only when the event is cancelled
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    let void_price = match binary_option.resolution_source {
+        ResolutionSource::Owner => void_price,
+        // An oracle pool that no price settled in time can only be voided at the preset price,
+        // once a price published before the deadline has had the grace period to settle it
+        ResolutionSource::Oracle => {
+            let voidable_at = binary_option
+                .oracle_publish_deadline()
+                .saturating_add(ORACLE_VOID_GRACE_PERIOD);
+            if Clock::get()?.unix_timestamp < voidable_at {
+                return Err(BinaryOptionError::MarketNotExpired.into());
+            }
+            binary_option.void_price
+        }
+        _ => return Err(BinaryOptionError::InvalidResolutionSource.into()),
+    };
+    if void_price > u64::pow(10, binary_option.decimals as u32) {
+        return Err(ProgramError::InvalidArgument);
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+
+    binary_option.resolve(Outcome::Void, void_price);
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_set_halted(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    halted: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    // Trading can't resume under a proposed outcome, resuming optimistic pools needs their
+    // resolver to check there is none
+    if !halted && binary_option.resolution_source == ResolutionSource::Optimistic {
+        let resolver_account_info = next_account_info(account_info_iter)?;
+        let resolver = OptimisticResolver::from_account_info(resolver_account_info)?;
+        assert_owned_by(resolver_account_info, program_id)?;
+        assert_keys_equal(
+            resolver.binary_option_account_pubkey,
+            *binary_option_account_info.key,
+        )?;
+        assert_keys_equal(
+            resolver.bond_vault_account_pubkey,
+            binary_option.bond_vault_account_pubkey,
+        )?;
+        if resolver.has_proposal() {
+            return Err(BinaryOptionError::InvalidProposalState.into());
+        }
+    }
+
+    // Only Open -> Halted and Halted -> Open, settlement can happen from either state
+    binary_option.status = match (binary_option.status, halted) {
+        (PoolStatus::Open, true) => PoolStatus::Halted,
+        (PoolStatus::Halted, false) => PoolStatus::Open,
+        _ => return Err(BinaryOptionError::InvalidPoolStatus.into()),
+    };
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_propose_owner(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+    let new_owner_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+
+    // Ownership only moves once the new owner accepts. Proposing again replaces the pending
+    // owner, and proposing the default key cancels the transfer.
+    binary_option.pending_owner = *new_owner_info.key;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_accept_owner(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let new_owner_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    if !new_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*new_owner_info.key, binary_option.pending_owner)?;
+
+    binary_option.owner = binary_option.pending_owner;
+    binary_option.pending_owner = Pubkey::default();
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_collect(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let CollectAccounts {
+        pool: binary_option_account_info,
+        collector: collector_info,
+        collector_long_token_account: collector_long_token_account_info,
+        collector_short_token_account: collector_short_token_account_info,
+        collector_collateral_account: collector_account_info,
+        long_token_mint: long_token_mint_info,
+        short_token_mint: short_token_mint_info,
+        escrow_account: escrow_account_info,
+        escrow_authority: escrow_authority_info,
+        token_program: token_program_info,
+    } = CollectAccounts::from_account_infos(account_info_iter)?;
+
+    let collector_long_token_account: Account =
+        assert_initialized(collector_long_token_account_info)?;
+    let collector_short_token_account: Account =
+        assert_initialized(collector_short_token_account_info)?;
+    let collector_account: Account = assert_initialized(collector_account_info)?;
+    let long_token_mint: Mint = assert_initialized(long_token_mint_info)?;
+    let short_token_mint: Mint = assert_initialized(short_token_mint_info)?;
+    let escrow_account: Account = assert_initialized(escrow_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let fee_vault_info = next_fee_vault_info(account_info_iter, &binary_option)?;
+
+    // Get program derived address for escrow
+    let (escrow_owner_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
         long_token_mint_info.key.as_ref(),
         short_token_mint_info.key.as_ref(),
         token_program_info.key.as_ref(),
@@ -648,14 +1143,28 @@ pub fn process_collect(program_id: &Pubkey, accounts: &[AccountInfo]) -> Program
         &[bump_seed],
     ];
 
-    if !binary_option.settled {
+    if !binary_option.is_settled() {
         return Err(BinaryOptionError::BetNotSettled.into());
     }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
     assert_owned_by(long_token_mint_info, &spl_token::id())?;
     assert_owned_by(short_token_mint_info, &spl_token::id())?;
     assert_keys_equal(collector_long_token_account.owner, *collector_info.key)?;
     assert_keys_equal(collector_short_token_account.owner, *collector_info.key)?;
-    assert_keys_equal(collector_account.owner, *collector_info.key)?;
+    // Anyone can collect on behalf of a holder, but then the payout has to go to the holder's
+    // associated token account. A collector who signs can send it to any account.
+    if !collector_info.is_signer {
+        assert_keys_equal(collector_account.owner, *collector_info.key)?;
+        if *collector_account_info.key
+            != get_associated_token_address(
+                collector_info.key,
+                &binary_option.escrow_mint_account_pubkey,
+            )
+        {
+            return Err(BinaryOptionError::InvalidPayoutDestination.into());
+        }
+    }
     assert_keys_equal(escrow_owner_key, *escrow_authority_info.key)?;
     assert_keys_equal(
         *long_token_mint_info.key,
@@ -682,13 +1191,13 @@ pub fn process_collect(program_id: &Pubkey, accounts: &[AccountInfo]) -> Program
         binary_option.escrow_mint_account_pubkey,
     )?;
 
-    let reward = if collector_long_token_account.mint == binary_option.winning_side_pubkey {
-        collector_long_token_account.amount
-    } else if collector_short_token_account.mint == binary_option.winning_side_pubkey {
-        collector_short_token_account.amount
-    } else {
-        return Err(BinaryOptionError::TokenNotFoundInPool.into());
-    };
+    let (amount, reward) = binary_option.payout(
+        collector_long_token_account.amount,
+        collector_short_token_account.amount,
+        long_token_mint.supply,
+        short_token_mint.supply,
+        escrow_account.amount,
+    )?;
 
     spl_burn_signed(
         token_program_info,
@@ -706,18 +1215,3706 @@ pub fn process_collect(program_id: &Pubkey, accounts: &[AccountInfo]) -> Program
         collector_short_token_account.amount,
         seeds,
     )?;
-    if reward > 0 {
-        let amount = (reward * escrow_account.amount) / binary_option.circulation;
+    let fees = match fee_vault_info {
+        Some(fee_vault_info) => {
+            let fees = binary_option.fees(amount);
+            spl_token_transfer_signed(
+                token_program_info,
+                escrow_account_info,
+                fee_vault_info,
+                escrow_authority_info,
+                total_fees(fees)?,
+                seeds,
+            )?;
+            binary_option.accrue_fees(fees)?;
+            total_fees(fees)?
+        }
+        None => 0,
+    };
+    if amount > fees {
         spl_token_transfer_signed(
             token_program_info,
             escrow_account_info,
             collector_account_info,
             escrow_authority_info,
-            amount,
+            amount - fees,
             seeds,
         )?;
-        binary_option.decrement_supply(reward)?;
     }
-    binary_option.serialize(&mut *binary_option_account_info.data.borrow_mut())?;
+    binary_option.decrement_supply(reward)?;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
     Ok(())
 }
+
+/// Sum of the protocol and creator fees returned by `BinaryOption::fees`
+fn total_fees((protocol_fee, creator_fee): (u64, u64)) -> Result<u64, ProgramError> {
+    protocol_fee
+        .checked_add(creator_fee)
+        .ok_or_else(|| BinaryOptionError::MathOverflow.into())
+}
+
+/// Finds the fee vault among the trailing accounts of an instruction that charges fees. Pools
+/// without fees don't need it.
+fn next_fee_vault_info<'a, 'b>(
+    account_info_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+    binary_option: &BinaryOption,
+) -> Result<Option<&'b AccountInfo<'a>>, ProgramError> {
+    if !binary_option.has_fees() {
+        return Ok(None);
+    }
+    account_info_iter
+        .find(|account_info| *account_info.key == binary_option.fee_vault_account_pubkey)
+        .map(Some)
+        .ok_or(ProgramError::NotEnoughAccountKeys)
+}
+
+/// Finds the account with `key` among the accounts of an instruction
+fn find_account_info<'a, 'b>(
+    accounts: &'b [AccountInfo<'a>],
+    key: &Pubkey,
+) -> Result<&'b AccountInfo<'a>, ProgramError> {
+    accounts
+        .iter()
+        .find(|account_info| account_info.key == key)
+        .ok_or(ProgramError::NotEnoughAccountKeys)
+}
+
+pub fn process_mint_set(program_id: &Pubkey, accounts: &[AccountInfo], size: u64) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let user_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let user_long_token_account_info = next_account_info(account_info_iter)?;
+    let user_short_token_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    // Unpack accounts
+    let long_token_mint: Mint = assert_initialized(long_token_mint_info)?;
+    let short_token_mint: Mint = assert_initialized(short_token_mint_info)?;
+    let user_long_token_account: Account = assert_initialized(user_long_token_account_info)?;
+    let user_short_token_account: Account = assert_initialized(user_short_token_account_info)?;
+    let user_account: Account = assert_initialized(user_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        long_token_mint_info.key.as_ref(),
+        short_token_mint_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !user_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if binary_option.status == PoolStatus::Halted
+        || binary_option.is_expired(Clock::get()?.unix_timestamp)
+    {
+        return Err(BinaryOptionError::TradingClosed.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*long_token_mint_info.owner, spl_token::id())?;
+    assert_keys_equal(*short_token_mint_info.owner, spl_token::id())?;
+    assert_keys_equal(user_long_token_account.owner, *user_info.key)?;
+    assert_keys_equal(user_short_token_account.owner, *user_info.key)?;
+    assert_keys_equal(user_account.owner, *user_info.key)?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *escrow_account_info.key,
+        binary_option.escrow_account_pubkey,
+    )?;
+    assert_keys_equal(
+        user_long_token_account.mint,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        user_short_token_account.mint,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(user_account.mint, binary_option.escrow_mint_account_pubkey)?;
+
+    // A complete set (1 long + 1 short) is always worth exactly 10^decimals of collateral,
+    // so minting sets keeps the pool fully collateralized without a counterparty
+    let n = size;
+    let collateral = notional(n, u64::pow(10, binary_option.decimals as u32))?;
+    spl_token_transfer(
+        token_program_info,
+        user_account_info,
+        escrow_account_info,
+        user_info,
+        collateral,
+    )?;
+    spl_mint_to(
+        token_program_info,
+        user_long_token_account_info,
+        long_token_mint_info,
+        authority_info,
+        n,
+        seeds,
+    )?;
+    spl_mint_to(
+        token_program_info,
+        user_short_token_account_info,
+        short_token_mint_info,
+        authority_info,
+        n,
+        seeds,
+    )?;
+    binary_option.increment_supply(n)?;
+
+    // Delegate the burn authority to the PDA, so a private key is unnecessary on collection
+    spl_approve(
+        token_program_info,
+        user_long_token_account_info,
+        long_token_mint_info,
+        authority_info,
+        user_info,
+        burn_allowance(
+            &user_long_token_account,
+            &authority_key,
+            user_long_token_account
+                .amount
+                .checked_add(n)
+                .ok_or(BinaryOptionError::MathOverflow)?,
+        )?,
+        long_token_mint.decimals,
+    )?;
+    spl_approve(
+        token_program_info,
+        user_short_token_account_info,
+        short_token_mint_info,
+        authority_info,
+        user_info,
+        burn_allowance(
+            &user_short_token_account,
+            &authority_key,
+            user_short_token_account
+                .amount
+                .checked_add(n)
+                .ok_or(BinaryOptionError::MathOverflow)?,
+        )?,
+        short_token_mint.decimals,
+    )?;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_redeem_set(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    size: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let user_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let user_long_token_account_info = next_account_info(account_info_iter)?;
+    let user_short_token_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    // Unpack accounts
+    let long_token_mint: Mint = assert_initialized(long_token_mint_info)?;
+    let short_token_mint: Mint = assert_initialized(short_token_mint_info)?;
+    let user_long_token_account: Account = assert_initialized(user_long_token_account_info)?;
+    let user_short_token_account: Account = assert_initialized(user_short_token_account_info)?;
+    let user_account: Account = assert_initialized(user_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        long_token_mint_info.key.as_ref(),
+        short_token_mint_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !user_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*long_token_mint_info.owner, spl_token::id())?;
+    assert_keys_equal(*short_token_mint_info.owner, spl_token::id())?;
+    assert_keys_equal(user_long_token_account.owner, *user_info.key)?;
+    assert_keys_equal(user_short_token_account.owner, *user_info.key)?;
+    assert_keys_equal(user_account.owner, *user_info.key)?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *escrow_account_info.key,
+        binary_option.escrow_account_pubkey,
+    )?;
+    assert_keys_equal(
+        user_long_token_account.mint,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        user_short_token_account.mint,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(user_account.mint, binary_option.escrow_mint_account_pubkey)?;
+
+    let n = size;
+    if user_long_token_account.amount < n || user_short_token_account.amount < n {
+        return Err(BinaryOptionError::InvalidSupply.into());
+    }
+    spl_burn(
+        token_program_info,
+        user_long_token_account_info,
+        long_token_mint_info,
+        user_info,
+        n,
+    )?;
+    spl_burn(
+        token_program_info,
+        user_short_token_account_info,
+        short_token_mint_info,
+        user_info,
+        n,
+    )?;
+    spl_token_transfer_signed(
+        token_program_info,
+        escrow_account_info,
+        user_account_info,
+        authority_info,
+        notional(n, u64::pow(10, binary_option.decimals as u32))?,
+        seeds,
+    )?;
+    binary_option.decrement_supply(n)?;
+
+    // Keep the PDA delegation in sync with the remaining inventory
+    spl_approve(
+        token_program_info,
+        user_long_token_account_info,
+        long_token_mint_info,
+        authority_info,
+        user_info,
+        burn_allowance(
+            &user_long_token_account,
+            &authority_key,
+            user_long_token_account
+                .amount
+                .checked_sub(n)
+                .ok_or(BinaryOptionError::MathOverflow)?,
+        )?,
+        long_token_mint.decimals,
+    )?;
+    spl_approve(
+        token_program_info,
+        user_short_token_account_info,
+        short_token_mint_info,
+        authority_info,
+        user_info,
+        burn_allowance(
+            &user_short_token_account,
+            &authority_key,
+            user_short_token_account
+                .amount
+                .checked_sub(n)
+                .ok_or(BinaryOptionError::MathOverflow)?,
+        )?,
+        short_token_mint.decimals,
+    )?;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+/// Number of tokens in `account` that `delegate` is allowed to burn
+fn delegated_amount(account: &Account, delegate: &Pubkey) -> u64 {
+    if account.delegate == COption::Some(*delegate) {
+        account.amount.min(account.delegated_amount)
+    } else {
+        0
+    }
+}
+
+/// Allowance that lets `delegate` burn `balance` tokens from `account`, on top of what the open
+/// orders of its owner may still mint into it
+fn burn_allowance(account: &Account, delegate: &Pubkey, balance: u64) -> Result<u64, ProgramError> {
+    let open_orders = if account.delegate == COption::Some(*delegate) {
+        account.delegated_amount.saturating_sub(account.amount)
+    } else {
+        0
+    };
+    balance
+        .checked_add(open_orders)
+        .ok_or_else(|| BinaryOptionError::MathOverflow.into())
+}
+
+pub fn process_initialize_order_book(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let order_book_account_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let escrow_mint_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let system_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, _) = Pubkey::find_program_address(
+        &[
+            binary_option.long_mint_account_pubkey.as_ref(),
+            binary_option.short_mint_account_pubkey.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+
+    // Validate data
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    // The pool tracks the vault so that it can't be closed while orders hold collateral
+    if binary_option.order_book_vault_account_pubkey != Pubkey::default() {
+        return Err(BinaryOptionError::OrderBookAlreadyInitialized.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *escrow_mint_info.key,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+
+    // The vault holds the collateral of resting orders and is owned by the escrow PDA
+    create_new_account(
+        pool_owner_info,
+        vault_account_info,
+        Account::LEN,
+        token_program_info,
+        rent_info,
+    )?;
+    spl_initialize(
+        token_program_info,
+        vault_account_info,
+        escrow_mint_info,
+        authority_info,
+        rent_info,
+    )?;
+    create_or_allocate_account_raw(
+        *program_id,
+        order_book_account_info,
+        rent_info,
+        system_account_info,
+        pool_owner_info,
+        OrderBook::LEN,
+    )?;
+
+    let mut order_book = OrderBook::try_from_slice(&order_book_account_info.data.borrow_mut())?;
+    order_book.binary_option_account_pubkey = *binary_option_account_info.key;
+    order_book.vault_account_pubkey = *vault_account_info.key;
+    order_book.next_order_id = 0;
+    order_book.serialize(&mut &mut order_book_account_info.data.borrow_mut()[..])?;
+
+    binary_option.order_book_vault_account_pubkey = *vault_account_info.key;
+    binary_option.order_book_account_pubkey = *order_book_account_info.key;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_place_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    side: OrderSide,
+    price: u64,
+    size: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let order_book_account_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let owner_long_token_account_info = next_account_info(account_info_iter)?;
+    let owner_short_token_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    // Unpack accounts
+    let long_token_mint: Mint = assert_initialized(long_token_mint_info)?;
+    let short_token_mint: Mint = assert_initialized(short_token_mint_info)?;
+    let owner_long_token_account: Account = assert_initialized(owner_long_token_account_info)?;
+    let owner_short_token_account: Account = assert_initialized(owner_short_token_account_info)?;
+    let owner_account: Account = assert_initialized(owner_account_info)?;
+    let binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let mut order_book = OrderBook::try_from_slice(&order_book_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, _) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+
+    // Validate data
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if binary_option.status == PoolStatus::Halted
+        || binary_option.is_expired(Clock::get()?.unix_timestamp)
+    {
+        return Err(BinaryOptionError::TradingClosed.into());
+    }
+    if size == 0 || price == 0 || price >= u64::pow(10, binary_option.decimals as u32) {
+        return Err(BinaryOptionError::InvalidOrder.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_owned_by(order_book_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        order_book.binary_option_account_pubkey,
+        *binary_option_account_info.key,
+    )?;
+    assert_keys_equal(order_book.vault_account_pubkey, *vault_account_info.key)?;
+    assert_keys_equal(owner_long_token_account.owner, *owner_info.key)?;
+    assert_keys_equal(owner_short_token_account.owner, *owner_info.key)?;
+    assert_keys_equal(owner_account.owner, *owner_info.key)?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        owner_long_token_account.mint,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        owner_short_token_account.mint,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(owner_account.mint, binary_option.escrow_mint_account_pubkey)?;
+
+    let slot = order_book
+        .orders
+        .iter()
+        .position(|o| o.size == 0)
+        .ok_or(BinaryOptionError::OrderBookFull)?;
+    let mut order = Order {
+        order_id: order_book.next_order_id,
+        side,
+        owner: *owner_info.key,
+        collateral_account_pubkey: *owner_account_info.key,
+        long_token_account_pubkey: *owner_long_token_account_info.key,
+        short_token_account_pubkey: *owner_short_token_account_info.key,
+        price,
+        size,
+        fee_per_contract: 0,
+    };
+    // The fees of a single contract at the limit price cover the fees of every match, which fills
+    // at that price or better and rounds the fees up once for all of its contracts
+    order.fee_per_contract =
+        total_fees(binary_option.fees(order.collateral_per_contract(binary_option.decimals)))?;
+
+    // Escrow the full collateral and fees of the order, any part that is not needed because of
+    // price improvement or because existing positions get netted out is refunded when the order
+    // is matched
+    spl_token_transfer(
+        token_program_info,
+        owner_account_info,
+        vault_account_info,
+        owner_info,
+        notional(size, order.escrow_per_contract(binary_option.decimals)?)?,
+    )?;
+    // Matching happens without the owner's signature, so the PDA needs to be able to burn the
+    // tokens that the order mints to the owner on collection. The allowance grows by the size of
+    // the order and shrinks again as the order is filled or cancelled.
+    let (position_token_account_info, position_token_account, position_mint_info, decimals) =
+        match side {
+            OrderSide::Bid => (
+                owner_long_token_account_info,
+                &owner_long_token_account,
+                long_token_mint_info,
+                long_token_mint.decimals,
+            ),
+            OrderSide::Ask => (
+                owner_short_token_account_info,
+                &owner_short_token_account,
+                short_token_mint_info,
+                short_token_mint.decimals,
+            ),
+        };
+    spl_approve(
+        token_program_info,
+        position_token_account_info,
+        position_mint_info,
+        authority_info,
+        owner_info,
+        burn_allowance(
+            position_token_account,
+            &authority_key,
+            position_token_account.amount,
+        )?
+        .checked_add(size)
+        .ok_or(BinaryOptionError::MathOverflow)?,
+        decimals,
+    )?;
+
+    msg!("Placed order {}", order.order_id);
+    order_book.orders[slot] = order;
+    order_book.next_order_id = order_book
+        .next_order_id
+        .checked_add(1)
+        .ok_or(BinaryOptionError::MathOverflow)?;
+    order_book.serialize(&mut &mut order_book_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_cancel_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    order_id: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let order_book_account_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let owner_long_token_account_info = next_account_info(account_info_iter)?;
+    let owner_short_token_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let long_token_mint: Mint = assert_initialized(long_token_mint_info)?;
+    let short_token_mint: Mint = assert_initialized(short_token_mint_info)?;
+    let owner_long_token_account: Account = assert_initialized(owner_long_token_account_info)?;
+    let owner_short_token_account: Account = assert_initialized(owner_short_token_account_info)?;
+    let binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let mut order_book = OrderBook::try_from_slice(&order_book_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            binary_option.long_mint_account_pubkey.as_ref(),
+            binary_option.short_mint_account_pubkey.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        binary_option.long_mint_account_pubkey.as_ref(),
+        binary_option.short_mint_account_pubkey.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data. Orders can be cancelled at any time, including after settlement.
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_owned_by(order_book_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        order_book.binary_option_account_pubkey,
+        *binary_option_account_info.key,
+    )?;
+    assert_keys_equal(order_book.vault_account_pubkey, *vault_account_info.key)?;
+    let slot = order_book.find_order(order_id)?;
+    let order = order_book.orders[slot];
+    assert_keys_equal(order.owner, *owner_info.key)?;
+    assert_keys_equal(order.collateral_account_pubkey, *owner_account_info.key)?;
+    assert_keys_equal(
+        order.long_token_account_pubkey,
+        *owner_long_token_account_info.key,
+    )?;
+    assert_keys_equal(
+        order.short_token_account_pubkey,
+        *owner_short_token_account_info.key,
+    )?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+
+    // Take back the allowance the order added for the contracts it will no longer mint
+    let (position_token_account_info, position_token_account, position_mint_info, decimals) =
+        match order.side {
+            OrderSide::Bid => (
+                owner_long_token_account_info,
+                &owner_long_token_account,
+                long_token_mint_info,
+                long_token_mint.decimals,
+            ),
+            OrderSide::Ask => (
+                owner_short_token_account_info,
+                &owner_short_token_account,
+                short_token_mint_info,
+                short_token_mint.decimals,
+            ),
+        };
+    spl_approve(
+        token_program_info,
+        position_token_account_info,
+        position_mint_info,
+        authority_info,
+        owner_info,
+        burn_allowance(
+            position_token_account,
+            &authority_key,
+            position_token_account.amount,
+        )?
+        .saturating_sub(order.size)
+        .max(position_token_account.amount),
+        decimals,
+    )?;
+    spl_token_transfer_signed(
+        token_program_info,
+        vault_account_info,
+        owner_account_info,
+        authority_info,
+        notional(
+            order.size,
+            order.escrow_per_contract(binary_option.decimals)?,
+        )?,
+        seeds,
+    )?;
+    order_book.orders[slot] = Order::default();
+    order_book.serialize(&mut &mut order_book_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_match_orders(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    bid_order_id: u64,
+    ask_order_id: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let order_book_account_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let buyer_account_info = next_account_info(account_info_iter)?;
+    let buyer_long_token_account_info = next_account_info(account_info_iter)?;
+    let buyer_short_token_account_info = next_account_info(account_info_iter)?;
+    let seller_account_info = next_account_info(account_info_iter)?;
+    let seller_long_token_account_info = next_account_info(account_info_iter)?;
+    let seller_short_token_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    // Unpack accounts
+    let buyer_long_token_account: Account = assert_initialized(buyer_long_token_account_info)?;
+    let buyer_short_token_account: Account = assert_initialized(buyer_short_token_account_info)?;
+    let seller_long_token_account: Account = assert_initialized(seller_long_token_account_info)?;
+    let seller_short_token_account: Account = assert_initialized(seller_short_token_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let mut order_book = OrderBook::try_from_slice(&order_book_account_info.data.borrow_mut())?;
+    let fee_vault_info = next_fee_vault_info(account_info_iter, &binary_option)?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        long_token_mint_info.key.as_ref(),
+        short_token_mint_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if binary_option.status == PoolStatus::Halted
+        || binary_option.is_expired(Clock::get()?.unix_timestamp)
+    {
+        return Err(BinaryOptionError::TradingClosed.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_owned_by(order_book_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        order_book.binary_option_account_pubkey,
+        *binary_option_account_info.key,
+    )?;
+    assert_keys_equal(order_book.vault_account_pubkey, *vault_account_info.key)?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *escrow_account_info.key,
+        binary_option.escrow_account_pubkey,
+    )?;
+
+    let bid_slot = order_book.find_order(bid_order_id)?;
+    let ask_slot = order_book.find_order(ask_order_id)?;
+    let bid = order_book.orders[bid_slot];
+    let ask = order_book.orders[ask_slot];
+    if bid.side != OrderSide::Bid || ask.side != OrderSide::Ask {
+        return Err(BinaryOptionError::InvalidOrder.into());
+    }
+    if bid.price < ask.price {
+        return Err(BinaryOptionError::OrdersDoNotCross.into());
+    }
+    assert_keys_unequal(bid.owner, ask.owner)?;
+    assert_keys_equal(bid.collateral_account_pubkey, *buyer_account_info.key)?;
+    assert_keys_equal(
+        bid.long_token_account_pubkey,
+        *buyer_long_token_account_info.key,
+    )?;
+    assert_keys_equal(
+        bid.short_token_account_pubkey,
+        *buyer_short_token_account_info.key,
+    )?;
+    assert_keys_equal(ask.collateral_account_pubkey, *seller_account_info.key)?;
+    assert_keys_equal(
+        ask.long_token_account_pubkey,
+        *seller_long_token_account_info.key,
+    )?;
+    assert_keys_equal(
+        ask.short_token_account_pubkey,
+        *seller_short_token_account_info.key,
+    )?;
+    assert_keys_equal(
+        buyer_long_token_account.mint,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        buyer_short_token_account.mint,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        seller_long_token_account.mint,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        seller_short_token_account.mint,
+        binary_option.short_mint_account_pubkey,
+    )?;
+
+    // The order that has been resting on the book the longest sets the price
+    let price = if bid.order_id < ask.order_id {
+        bid.price
+    } else {
+        ask.price
+    };
+    let unit = u64::pow(10, binary_option.decimals as u32);
+    let buy_price = price;
+    let sell_price = unit - price;
+    let n = bid.size.min(ask.size);
+    // Both sides pay fees on the notional of their side, like on a `Trade`, out of the fees their
+    // orders escrowed. Orders placed before `ConfigureFees` escrowed none and can only be cancelled.
+    let buyer_fees = binary_option.fees(notional(n, buy_price)?);
+    let seller_fees = binary_option.fees(notional(n, sell_price)?);
+    let buyer_fee_escrow = notional(n, bid.fee_per_contract)?;
+    let seller_fee_escrow = notional(n, ask.fee_per_contract)?;
+    if total_fees(buyer_fees)? > buyer_fee_escrow || total_fees(seller_fees)? > seller_fee_escrow {
+        return Err(BinaryOptionError::FeesNotSupported.into());
+    }
+    // Only net out the positions that the PDA is still allowed to burn
+    let n_b = delegated_amount(&buyer_short_token_account, &authority_key).min(n);
+    let n_s = delegated_amount(&seller_long_token_account, &authority_key).min(n);
+
+    // Both orders are filled in full first, so the tokens minted to their owners use up the
+    // allowance that was approved for them when the orders were placed. Existing positions are
+    // then netted out by redeeming complete sets, which moves the same collateral as burning
+    // them in the trade would.
+    execute_trade(
+        &mut binary_option,
+        &TradeAccounts {
+            token_program: token_program_info,
+            escrow_account: escrow_account_info,
+            long_token_mint: long_token_mint_info,
+            short_token_mint: short_token_mint_info,
+            escrow_authority: authority_info,
+        },
+        &TradeParty {
+            authority: authority_info,
+            source_account: vault_account_info,
+            destination_account: buyer_account_info,
+            long_token_account: buyer_long_token_account_info,
+            short_token_account: buyer_short_token_account_info,
+            long_amount: buyer_long_token_account.amount,
+            short_amount: 0,
+        },
+        &TradeParty {
+            authority: authority_info,
+            source_account: vault_account_info,
+            destination_account: seller_account_info,
+            long_token_account: seller_long_token_account_info,
+            short_token_account: seller_short_token_account_info,
+            long_amount: 0,
+            short_amount: seller_short_token_account.amount,
+        },
+        n,
+        buy_price,
+        sell_price,
+        seeds,
+    )?;
+    for (n_net, destination_account, long_token_account, short_token_account) in [
+        (
+            n_b,
+            buyer_account_info,
+            buyer_long_token_account_info,
+            buyer_short_token_account_info,
+        ),
+        (
+            n_s,
+            seller_account_info,
+            seller_long_token_account_info,
+            seller_short_token_account_info,
+        ),
+    ]
+    .iter()
+    {
+        if *n_net > 0 {
+            redeem_sets_from_inventory(
+                &mut binary_option,
+                &InventoryAccounts {
+                    token_program: token_program_info,
+                    escrow_account: escrow_account_info,
+                    long_token_mint: long_token_mint_info,
+                    short_token_mint: short_token_mint_info,
+                    vault_account: destination_account,
+                    long_token_account,
+                    short_token_account,
+                    escrow_authority: authority_info,
+                },
+                *n_net,
+                unit,
+                seeds,
+            )?;
+        }
+    }
+
+    if let Some(fee_vault_info) = fee_vault_info {
+        for fees in [buyer_fees, seller_fees].iter() {
+            spl_token_transfer_signed(
+                token_program_info,
+                vault_account_info,
+                fee_vault_info,
+                authority_info,
+                total_fees(*fees)?,
+                seeds,
+            )?;
+            binary_option.accrue_fees(*fees)?;
+        }
+    }
+
+    // Refund the collateral and fees that were escrowed for the filled contracts but not used
+    // because of price improvement
+    let buyer_refund = notional(
+        n,
+        bid.price
+            .checked_sub(buy_price)
+            .ok_or(BinaryOptionError::MathOverflow)?,
+    )?
+    .checked_add(buyer_fee_escrow - total_fees(buyer_fees)?)
+    .ok_or(BinaryOptionError::MathOverflow)?;
+    let seller_refund = notional(
+        n,
+        ask.collateral_per_contract(binary_option.decimals)
+            .checked_sub(sell_price)
+            .ok_or(BinaryOptionError::MathOverflow)?,
+    )?
+    .checked_add(seller_fee_escrow - total_fees(seller_fees)?)
+    .ok_or(BinaryOptionError::MathOverflow)?;
+    spl_token_transfer_signed(
+        token_program_info,
+        vault_account_info,
+        buyer_account_info,
+        authority_info,
+        buyer_refund,
+        seeds,
+    )?;
+    spl_token_transfer_signed(
+        token_program_info,
+        vault_account_info,
+        seller_account_info,
+        authority_info,
+        seller_refund,
+        seeds,
+    )?;
+
+    msg!("Matched {} contracts at {}", n, price);
+    for slot in [bid_slot, ask_slot].iter() {
+        let order = &mut order_book.orders[*slot];
+        order.size = order
+            .size
+            .checked_sub(n)
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        if order.size == 0 {
+            *order = Order::default();
+        }
+    }
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    order_book.serialize(&mut &mut order_book_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+/// Accounts of a holder of complete sets, such as a market maker's inventory. The escrow
+/// authority PDA owns both token accounts or is approved to burn from them.
+pub struct InventoryAccounts<'a, 'b> {
+    pub token_program: &'b AccountInfo<'a>,
+    pub escrow_account: &'b AccountInfo<'a>,
+    pub long_token_mint: &'b AccountInfo<'a>,
+    pub short_token_mint: &'b AccountInfo<'a>,
+    pub vault_account: &'b AccountInfo<'a>,
+    pub long_token_account: &'b AccountInfo<'a>,
+    pub short_token_account: &'b AccountInfo<'a>,
+    pub escrow_authority: &'b AccountInfo<'a>,
+}
+
+/// Moves `n * unit` of collateral from the vault into the escrow and mints `n` complete sets
+/// into the inventory
+pub fn mint_sets_into_inventory(
+    binary_option: &mut BinaryOption,
+    accounts: &InventoryAccounts,
+    n: u64,
+    unit: u64,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    if n == 0 {
+        return Ok(());
+    }
+    spl_token_transfer_signed(
+        accounts.token_program,
+        accounts.vault_account,
+        accounts.escrow_account,
+        accounts.escrow_authority,
+        notional(n, unit)?,
+        seeds,
+    )?;
+    spl_mint_to(
+        accounts.token_program,
+        accounts.long_token_account,
+        accounts.long_token_mint,
+        accounts.escrow_authority,
+        n,
+        seeds,
+    )?;
+    spl_mint_to(
+        accounts.token_program,
+        accounts.short_token_account,
+        accounts.short_token_mint,
+        accounts.escrow_authority,
+        n,
+        seeds,
+    )?;
+    binary_option.increment_supply(n)?;
+    Ok(())
+}
+
+/// Burns `n` complete sets out of the inventory and moves `n * unit` of collateral from the
+/// escrow back into the vault
+pub fn redeem_sets_from_inventory(
+    binary_option: &mut BinaryOption,
+    accounts: &InventoryAccounts,
+    n: u64,
+    unit: u64,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    spl_burn_signed(
+        accounts.token_program,
+        accounts.long_token_account,
+        accounts.long_token_mint,
+        accounts.escrow_authority,
+        n,
+        seeds,
+    )?;
+    spl_burn_signed(
+        accounts.token_program,
+        accounts.short_token_account,
+        accounts.short_token_mint,
+        accounts.escrow_authority,
+        n,
+        seeds,
+    )?;
+    spl_token_transfer_signed(
+        accounts.token_program,
+        accounts.escrow_account,
+        accounts.vault_account,
+        accounts.escrow_authority,
+        notional(n, unit)?,
+        seeds,
+    )?;
+    binary_option.decrement_supply(n)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn process_initialize_amm(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    liquidity: u64,
+    subsidy: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let escrow_mint_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let amm_long_token_account_info = next_account_info(account_info_iter)?;
+    let amm_short_token_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+    let pool_owner_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let _system_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+
+    let pool_owner_account: Account = assert_initialized(pool_owner_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, _) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+
+    // Validate data
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if binary_option.amm_liquidity > 0 {
+        return Err(BinaryOptionError::AmmAlreadyInitialized.into());
+    }
+    if liquidity == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let unit = u64::pow(10, binary_option.decimals as u32);
+    if subsidy < lmsr_max_loss(liquidity, unit)? {
+        return Err(BinaryOptionError::InsufficientSubsidy.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *escrow_mint_info.key,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(pool_owner_account.owner, *pool_owner_info.key)?;
+    assert_keys_equal(
+        pool_owner_account.mint,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+
+    // The market maker's collateral and inventory are held by the escrow PDA
+    for (account_info, mint_info) in [
+        (vault_account_info, escrow_mint_info),
+        (amm_long_token_account_info, long_token_mint_info),
+        (amm_short_token_account_info, short_token_mint_info),
+    ]
+    .iter()
+    {
+        create_new_account(
+            pool_owner_info,
+            account_info,
+            Account::LEN,
+            token_program_info,
+            rent_info,
+        )?;
+        spl_initialize(
+            token_program_info,
+            account_info,
+            mint_info,
+            authority_info,
+            rent_info,
+        )?;
+    }
+    // The subsidy has to cover the worst case loss of the market maker, b * ln(2)
+    spl_token_transfer(
+        token_program_info,
+        pool_owner_account_info,
+        vault_account_info,
+        pool_owner_info,
+        subsidy,
+    )?;
+
+    binary_option.amm_liquidity = liquidity;
+    binary_option.amm_long_outstanding = 0;
+    binary_option.amm_short_outstanding = 0;
+    binary_option.amm_vault_account_pubkey = *vault_account_info.key;
+    binary_option.amm_long_token_account_pubkey = *amm_long_token_account_info.key;
+    binary_option.amm_short_token_account_pubkey = *amm_short_token_account_info.key;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_amm_trade(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    side: PositionSide,
+    size: u64,
+    collateral_limit: u64,
+    buy: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let amm_long_token_account_info = next_account_info(account_info_iter)?;
+    let amm_short_token_account_info = next_account_info(account_info_iter)?;
+    let user_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let user_token_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    // Unpack accounts
+    let long_token_mint: Mint = assert_initialized(long_token_mint_info)?;
+    let short_token_mint: Mint = assert_initialized(short_token_mint_info)?;
+    let amm_long_token_account: Account = assert_initialized(amm_long_token_account_info)?;
+    let amm_short_token_account: Account = assert_initialized(amm_short_token_account_info)?;
+    let user_account: Account = assert_initialized(user_account_info)?;
+    let user_token_account: Account = assert_initialized(user_token_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let fee_vault_info = next_fee_vault_info(account_info_iter, &binary_option)?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        long_token_mint_info.key.as_ref(),
+        short_token_mint_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !user_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if binary_option.status == PoolStatus::Halted
+        || binary_option.is_expired(Clock::get()?.unix_timestamp)
+    {
+        return Err(BinaryOptionError::TradingClosed.into());
+    }
+    if binary_option.amm_liquidity == 0 {
+        return Err(BinaryOptionError::AmmNotInitialized.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*long_token_mint_info.owner, spl_token::id())?;
+    assert_keys_equal(*short_token_mint_info.owner, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *escrow_account_info.key,
+        binary_option.escrow_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *vault_account_info.key,
+        binary_option.amm_vault_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *amm_long_token_account_info.key,
+        binary_option.amm_long_token_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *amm_short_token_account_info.key,
+        binary_option.amm_short_token_account_pubkey,
+    )?;
+    assert_keys_equal(user_account.owner, *user_info.key)?;
+    assert_keys_equal(user_token_account.owner, *user_info.key)?;
+    assert_keys_equal(user_account.mint, binary_option.escrow_mint_account_pubkey)?;
+
+    let (token_mint_info, decimals, inventory_info, inventory, other_inventory) = match side {
+        PositionSide::Long => (
+            long_token_mint_info,
+            long_token_mint.decimals,
+            amm_long_token_account_info,
+            amm_long_token_account.amount,
+            amm_short_token_account.amount,
+        ),
+        PositionSide::Short => (
+            short_token_mint_info,
+            short_token_mint.decimals,
+            amm_short_token_account_info,
+            amm_short_token_account.amount,
+            amm_long_token_account.amount,
+        ),
+    };
+    assert_keys_equal(user_token_account.mint, *token_mint_info.key)?;
+
+    let inventory_accounts = InventoryAccounts {
+        token_program: token_program_info,
+        escrow_account: escrow_account_info,
+        long_token_mint: long_token_mint_info,
+        short_token_mint: short_token_mint_info,
+        vault_account: vault_account_info,
+        long_token_account: amm_long_token_account_info,
+        short_token_account: amm_short_token_account_info,
+        escrow_authority: authority_info,
+    };
+    let n = size;
+    let unit = u64::pow(10, binary_option.decimals as u32);
+    let liquidity = binary_option.amm_liquidity;
+    let q_long = binary_option.amm_long_outstanding;
+    let q_short = binary_option.amm_short_outstanding;
+    let current_cost = lmsr_cost(q_long, q_short, liquidity, unit)?;
+
+    // The market maker only ever holds complete sets that it mints from (or redeems into) its
+    // vault, so the escrow stays at exactly circulation * 10^decimals. Quotes are rounded by one
+    // base unit in favor of the market maker to absorb the fixed point approximation error.
+    // The differences of the cost function are checked, so a quote never wraps around even if
+    // the approximation is off.
+    if buy {
+        let (q_long, q_short) = match side {
+            PositionSide::Long => (
+                q_long
+                    .checked_add(n)
+                    .ok_or(BinaryOptionError::MathOverflow)?,
+                q_short,
+            ),
+            PositionSide::Short => (
+                q_long,
+                q_short
+                    .checked_add(n)
+                    .ok_or(BinaryOptionError::MathOverflow)?,
+            ),
+        };
+        let cost = lmsr_cost(q_long, q_short, liquidity, unit)?
+            .checked_sub(current_cost)
+            .and_then(|cost| cost.checked_add(1))
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        // Pool fees are charged on top of the cost, like on a `Trade`
+        let fees = binary_option.fees(cost);
+        let total = total_fees(fees)?
+            .checked_add(cost)
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        if total > collateral_limit {
+            return Err(BinaryOptionError::SlippageExceeded.into());
+        }
+        spl_token_transfer(
+            token_program_info,
+            user_account_info,
+            vault_account_info,
+            user_info,
+            cost,
+        )?;
+        if let Some(fee_vault_info) = fee_vault_info {
+            spl_token_transfer(
+                token_program_info,
+                user_account_info,
+                fee_vault_info,
+                user_info,
+                total_fees(fees)?,
+            )?;
+            binary_option.accrue_fees(fees)?;
+        }
+        mint_sets_into_inventory(
+            &mut binary_option,
+            &inventory_accounts,
+            n.saturating_sub(inventory),
+            unit,
+            seeds,
+        )?;
+        spl_token_transfer_signed(
+            token_program_info,
+            inventory_info,
+            user_token_account_info,
+            authority_info,
+            n,
+            seeds,
+        )?;
+        binary_option.amm_long_outstanding = q_long;
+        binary_option.amm_short_outstanding = q_short;
+        // Delegate the burn authority to the PDA, so a private key is unnecessary on collection
+        spl_approve(
+            token_program_info,
+            user_token_account_info,
+            token_mint_info,
+            authority_info,
+            user_info,
+            burn_allowance(
+                &user_token_account,
+                &authority_key,
+                user_token_account
+                    .amount
+                    .checked_add(n)
+                    .ok_or(BinaryOptionError::MathOverflow)?,
+            )?,
+            decimals,
+        )?;
+    } else {
+        let (q_long, q_short) = match side {
+            PositionSide::Long if q_long >= n => (q_long - n, q_short),
+            PositionSide::Short if q_short >= n => (q_long, q_short - n),
+            _ => return Err(BinaryOptionError::InvalidSupply.into()),
+        };
+        let proceeds = current_cost
+            .checked_sub(lmsr_cost(q_long, q_short, liquidity, unit)?)
+            .ok_or(BinaryOptionError::MathOverflow)?
+            .saturating_sub(1);
+        // Pool fees are kept out of the proceeds
+        let fees = binary_option.fees(proceeds);
+        let payout = proceeds - total_fees(fees)?;
+        if payout < collateral_limit {
+            return Err(BinaryOptionError::SlippageExceeded.into());
+        }
+        spl_token_transfer(
+            token_program_info,
+            user_token_account_info,
+            inventory_info,
+            user_info,
+            n,
+        )?;
+        // Redeem the complete sets that are now in inventory to fund the payout
+        redeem_sets_from_inventory(
+            &mut binary_option,
+            &inventory_accounts,
+            other_inventory.min(
+                inventory
+                    .checked_add(n)
+                    .ok_or(BinaryOptionError::MathOverflow)?,
+            ),
+            unit,
+            seeds,
+        )?;
+        spl_token_transfer_signed(
+            token_program_info,
+            vault_account_info,
+            user_account_info,
+            authority_info,
+            payout,
+            seeds,
+        )?;
+        if let Some(fee_vault_info) = fee_vault_info {
+            spl_token_transfer_signed(
+                token_program_info,
+                vault_account_info,
+                fee_vault_info,
+                authority_info,
+                total_fees(fees)?,
+                seeds,
+            )?;
+            binary_option.accrue_fees(fees)?;
+        }
+        binary_option.amm_long_outstanding = q_long;
+        binary_option.amm_short_outstanding = q_short;
+        spl_approve(
+            token_program_info,
+            user_token_account_info,
+            token_mint_info,
+            authority_info,
+            user_info,
+            burn_allowance(
+                &user_token_account,
+                &authority_key,
+                user_token_account
+                    .amount
+                    .checked_sub(n)
+                    .ok_or(BinaryOptionError::MathOverflow)?,
+            )?,
+            decimals,
+        )?;
+    }
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_amm_withdraw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let amm_long_token_account_info = next_account_info(account_info_iter)?;
+    let amm_short_token_account_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+    let pool_owner_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let long_token_mint: Mint = assert_initialized(long_token_mint_info)?;
+    let short_token_mint: Mint = assert_initialized(short_token_mint_info)?;
+    let escrow_account: Account = assert_initialized(escrow_account_info)?;
+    let vault_account: Account = assert_initialized(vault_account_info)?;
+    let amm_long_token_account: Account = assert_initialized(amm_long_token_account_info)?;
+    let amm_short_token_account: Account = assert_initialized(amm_short_token_account_info)?;
+    let pool_owner_account: Account = assert_initialized(pool_owner_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        long_token_mint_info.key.as_ref(),
+        short_token_mint_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !binary_option.is_settled() {
+        return Err(BinaryOptionError::BetNotSettled.into());
+    }
+    if binary_option.amm_liquidity == 0 {
+        return Err(BinaryOptionError::AmmNotInitialized.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *escrow_account_info.key,
+        binary_option.escrow_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *vault_account_info.key,
+        binary_option.amm_vault_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *amm_long_token_account_info.key,
+        binary_option.amm_long_token_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *amm_short_token_account_info.key,
+        binary_option.amm_short_token_account_pubkey,
+    )?;
+    assert_keys_equal(pool_owner_account.owner, *pool_owner_info.key)?;
+    assert_keys_equal(
+        pool_owner_account.mint,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+
+    // Collect the market maker's inventory like any other holder
+    let (amount, reward) = binary_option.payout(
+        amm_long_token_account.amount,
+        amm_short_token_account.amount,
+        long_token_mint.supply,
+        short_token_mint.supply,
+        escrow_account.amount,
+    )?;
+    spl_burn_signed(
+        token_program_info,
+        amm_long_token_account_info,
+        long_token_mint_info,
+        authority_info,
+        amm_long_token_account.amount,
+        seeds,
+    )?;
+    spl_burn_signed(
+        token_program_info,
+        amm_short_token_account_info,
+        short_token_mint_info,
+        authority_info,
+        amm_short_token_account.amount,
+        seeds,
+    )?;
+    if amount > 0 {
+        spl_token_transfer_signed(
+            token_program_info,
+            escrow_account_info,
+            pool_owner_account_info,
+            authority_info,
+            amount,
+            seeds,
+        )?;
+    }
+    binary_option.decrement_supply(reward)?;
+    // Return what is left of the subsidy and the market maker's profits
+    spl_token_transfer_signed(
+        token_program_info,
+        vault_account_info,
+        pool_owner_account_info,
+        authority_info,
+        vault_account.amount,
+        seeds,
+    )?;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_initialize_cpmm(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fee_bps: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let cpmm_account_info = next_account_info(account_info_iter)?;
+    let lp_mint_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let cpmm_long_token_account_info = next_account_info(account_info_iter)?;
+    let cpmm_short_token_account_info = next_account_info(account_info_iter)?;
+    let escrow_mint_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let system_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+
+    let escrow_mint: Mint = assert_initialized(escrow_mint_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, _) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+
+    // Validate data
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    // The pool tracks the vault and LP mint so that it can't be closed while LPs have collateral
+    if binary_option.cpmm_vault_account_pubkey != Pubkey::default() {
+        return Err(BinaryOptionError::CpmmAlreadyInitialized.into());
+    }
+    if fee_bps > MAX_FEE_BPS {
+        return Err(BinaryOptionError::FeeTooHigh.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *escrow_mint_info.key,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+
+    // LP shares are minted by the escrow PDA and use the decimals of the collateral
+    create_new_account(
+        pool_owner_info,
+        lp_mint_info,
+        Mint::LEN,
+        token_program_info,
+        rent_info,
+    )?;
+    spl_mint_initialize(
+        token_program_info,
+        lp_mint_info,
+        authority_info,
+        authority_info,
+        rent_info,
+        escrow_mint.decimals,
+    )?;
+    // The pool's collateral and inventory are held by the escrow PDA
+    for (account_info, mint_info) in [
+        (vault_account_info, escrow_mint_info),
+        (cpmm_long_token_account_info, long_token_mint_info),
+        (cpmm_short_token_account_info, short_token_mint_info),
+    ]
+    .iter()
+    {
+        create_new_account(
+            pool_owner_info,
+            account_info,
+            Account::LEN,
+            token_program_info,
+            rent_info,
+        )?;
+        spl_initialize(
+            token_program_info,
+            account_info,
+            mint_info,
+            authority_info,
+            rent_info,
+        )?;
+    }
+    create_or_allocate_account_raw(
+        *program_id,
+        cpmm_account_info,
+        rent_info,
+        system_account_info,
+        pool_owner_info,
+        CpmmPool::LEN,
+    )?;
+
+    let mut cpmm_pool = CpmmPool::from_account_info(cpmm_account_info)?;
+    cpmm_pool.binary_option_account_pubkey = *binary_option_account_info.key;
+    cpmm_pool.lp_mint_pubkey = *lp_mint_info.key;
+    cpmm_pool.vault_account_pubkey = *vault_account_info.key;
+    cpmm_pool.long_token_account_pubkey = *cpmm_long_token_account_info.key;
+    cpmm_pool.short_token_account_pubkey = *cpmm_short_token_account_info.key;
+    cpmm_pool.fee_bps = fee_bps;
+    cpmm_pool.total_fees_accrued = 0;
+    cpmm_pool.serialize(&mut &mut cpmm_account_info.data.borrow_mut()[..])?;
+
+    binary_option.cpmm_vault_account_pubkey = *vault_account_info.key;
+    binary_option.cpmm_lp_mint_pubkey = *lp_mint_info.key;
+    binary_option.cpmm_account_pubkey = *cpmm_account_info.key;
+    binary_option.cpmm_long_token_account_pubkey = *cpmm_long_token_account_info.key;
+    binary_option.cpmm_short_token_account_pubkey = *cpmm_short_token_account_info.key;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_cpmm_liquidity(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+    add: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let cpmm_account_info = next_account_info(account_info_iter)?;
+    let lp_mint_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let cpmm_long_token_account_info = next_account_info(account_info_iter)?;
+    let cpmm_short_token_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let provider_info = next_account_info(account_info_iter)?;
+    let provider_account_info = next_account_info(account_info_iter)?;
+    let provider_long_token_account_info = next_account_info(account_info_iter)?;
+    let provider_short_token_account_info = next_account_info(account_info_iter)?;
+    let provider_lp_token_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    // Unpack accounts
+    let long_token_mint: Mint = assert_initialized(long_token_mint_info)?;
+    let short_token_mint: Mint = assert_initialized(short_token_mint_info)?;
+    let lp_mint: Mint = assert_initialized(lp_mint_info)?;
+    let vault_account: Account = assert_initialized(vault_account_info)?;
+    let cpmm_long_token_account: Account = assert_initialized(cpmm_long_token_account_info)?;
+    let cpmm_short_token_account: Account = assert_initialized(cpmm_short_token_account_info)?;
+    let provider_account: Account = assert_initialized(provider_account_info)?;
+    let provider_long_token_account: Account =
+        assert_initialized(provider_long_token_account_info)?;
+    let provider_short_token_account: Account =
+        assert_initialized(provider_short_token_account_info)?;
+    let provider_lp_token_account: Account = assert_initialized(provider_lp_token_account_info)?;
+    let cpmm_pool = CpmmPool::from_account_info(cpmm_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        long_token_mint_info.key.as_ref(),
+        short_token_mint_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !provider_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if add && binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_owned_by(cpmm_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        cpmm_pool.binary_option_account_pubkey,
+        *binary_option_account_info.key,
+    )?;
+    assert_keys_equal(cpmm_pool.lp_mint_pubkey, *lp_mint_info.key)?;
+    assert_keys_equal(cpmm_pool.vault_account_pubkey, *vault_account_info.key)?;
+    assert_keys_equal(
+        cpmm_pool.long_token_account_pubkey,
+        *cpmm_long_token_account_info.key,
+    )?;
+    assert_keys_equal(
+        cpmm_pool.short_token_account_pubkey,
+        *cpmm_short_token_account_info.key,
+    )?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *escrow_account_info.key,
+        binary_option.escrow_account_pubkey,
+    )?;
+    assert_keys_equal(provider_account.owner, *provider_info.key)?;
+    assert_keys_equal(provider_long_token_account.owner, *provider_info.key)?;
+    assert_keys_equal(provider_short_token_account.owner, *provider_info.key)?;
+    assert_keys_equal(provider_lp_token_account.owner, *provider_info.key)?;
+    assert_keys_equal(
+        provider_account.mint,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        provider_long_token_account.mint,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        provider_short_token_account.mint,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(provider_lp_token_account.mint, *lp_mint_info.key)?;
+
+    let inventory_accounts = InventoryAccounts {
+        token_program: token_program_info,
+        escrow_account: escrow_account_info,
+        long_token_mint: long_token_mint_info,
+        short_token_mint: short_token_mint_info,
+        vault_account: vault_account_info,
+        long_token_account: cpmm_long_token_account_info,
+        short_token_account: cpmm_short_token_account_info,
+        escrow_authority: authority_info,
+    };
+    let unit = u64::pow(10, binary_option.decimals as u32);
+    let supply = lp_mint.supply;
+
+    if add {
+        // Collateral in the vault is a claim on both sides, so the pool balances are virtual:
+        // tokens held (in units of collateral) plus the collateral that has not been minted yet
+        let long_balance =
+            cpmm_long_token_account.amount as u128 * unit as u128 + vault_account.amount as u128;
+        let short_balance =
+            cpmm_short_token_account.amount as u128 * unit as u128 + vault_account.amount as u128;
+        let max_balance = long_balance.max(short_balance);
+        spl_token_transfer(
+            token_program_info,
+            provider_account_info,
+            vault_account_info,
+            provider_info,
+            amount,
+        )?;
+        let shares = if supply == 0 {
+            amount
+        } else {
+            pro_rata(amount as u128, supply as u128, max_balance)?
+        };
+        if shares == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if supply > 0 && long_balance != short_balance {
+            // Keep the pool's prices unchanged by only adding to the lighter side in proportion
+            // and sending the rest of it back to the provider as tokens
+            let (balance, token_mint_info, decimals, token_account_info, token_account) =
+                if long_balance < short_balance {
+                    (
+                        long_balance,
+                        long_token_mint_info,
+                        long_token_mint.decimals,
+                        provider_long_token_account_info,
+                        &provider_long_token_account,
+                    )
+                } else {
+                    (
+                        short_balance,
+                        short_token_mint_info,
+                        short_token_mint.decimals,
+                        provider_short_token_account_info,
+                        &provider_short_token_account,
+                    )
+                };
+            let inventory_info = if long_balance < short_balance {
+                cpmm_long_token_account_info
+            } else {
+                cpmm_short_token_account_info
+            };
+            let kept = pro_rata(amount as u128, balance, max_balance)?;
+            let returned = (amount - kept) / unit;
+            if returned > 0 {
+                mint_sets_into_inventory(
+                    &mut binary_option,
+                    &inventory_accounts,
+                    returned,
+                    unit,
+                    seeds,
+                )?;
+                spl_token_transfer_signed(
+                    token_program_info,
+                    inventory_info,
+                    token_account_info,
+                    authority_info,
+                    returned,
+                    seeds,
+                )?;
+                // Delegate the burn authority to the PDA, so a private key is unnecessary on collection
+                spl_approve(
+                    token_program_info,
+                    token_account_info,
+                    token_mint_info,
+                    authority_info,
+                    provider_info,
+                    burn_allowance(
+                        token_account,
+                        &authority_key,
+                        token_account
+                            .amount
+                            .checked_add(returned)
+                            .ok_or(BinaryOptionError::MathOverflow)?,
+                    )?,
+                    decimals,
+                )?;
+            }
+        }
+        spl_mint_to(
+            token_program_info,
+            provider_lp_token_account_info,
+            lp_mint_info,
+            authority_info,
+            shares,
+            seeds,
+        )?;
+    } else {
+        let shares = amount;
+        if shares > supply {
+            return Err(ProgramError::InvalidArgument);
+        }
+        // Pay out a pro rata share of everything the pool holds, accrued fees included
+        let long_amount = pro_rata(
+            cpmm_long_token_account.amount as u128,
+            shares as u128,
+            supply as u128,
+        )?;
+        let short_amount = pro_rata(
+            cpmm_short_token_account.amount as u128,
+            shares as u128,
+            supply as u128,
+        )?;
+        let collateral = pro_rata(vault_account.amount as u128, shares as u128, supply as u128)?;
+        spl_burn(
+            token_program_info,
+            provider_lp_token_account_info,
+            lp_mint_info,
+            provider_info,
+            shares,
+        )?;
+        spl_token_transfer_signed(
+            token_program_info,
+            vault_account_info,
+            provider_account_info,
+            authority_info,
+            collateral,
+            seeds,
+        )?;
+        for (
+            inventory_info,
+            token_account_info,
+            token_account,
+            token_mint_info,
+            decimals,
+            token_amount,
+        ) in [
+            (
+                cpmm_long_token_account_info,
+                provider_long_token_account_info,
+                &provider_long_token_account,
+                long_token_mint_info,
+                long_token_mint.decimals,
+                long_amount,
+            ),
+            (
+                cpmm_short_token_account_info,
+                provider_short_token_account_info,
+                &provider_short_token_account,
+                short_token_mint_info,
+                short_token_mint.decimals,
+                short_amount,
+            ),
+        ]
+        .iter()
+        {
+            if *token_amount == 0 {
+                continue;
+            }
+            spl_token_transfer_signed(
+                token_program_info,
+                inventory_info,
+                token_account_info,
+                authority_info,
+                *token_amount,
+                seeds,
+            )?;
+            spl_approve(
+                token_program_info,
+                token_account_info,
+                token_mint_info,
+                authority_info,
+                provider_info,
+                burn_allowance(
+                    token_account,
+                    &authority_key,
+                    token_account
+                        .amount
+                        .checked_add(*token_amount)
+                        .ok_or(BinaryOptionError::MathOverflow)?,
+                )?,
+                *decimals,
+            )?;
+        }
+    }
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_cpmm_trade(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    side: PositionSide,
+    size: u64,
+    collateral_limit: u64,
+    buy: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let cpmm_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let cpmm_long_token_account_info = next_account_info(account_info_iter)?;
+    let cpmm_short_token_account_info = next_account_info(account_info_iter)?;
+    let user_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let user_token_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    // Unpack accounts
+    let long_token_mint: Mint = assert_initialized(long_token_mint_info)?;
+    let short_token_mint: Mint = assert_initialized(short_token_mint_info)?;
+    let vault_account: Account = assert_initialized(vault_account_info)?;
+    let cpmm_long_token_account: Account = assert_initialized(cpmm_long_token_account_info)?;
+    let cpmm_short_token_account: Account = assert_initialized(cpmm_short_token_account_info)?;
+    let user_account: Account = assert_initialized(user_account_info)?;
+    let user_token_account: Account = assert_initialized(user_token_account_info)?;
+    let mut cpmm_pool = CpmmPool::from_account_info(cpmm_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let fee_vault_info = next_fee_vault_info(account_info_iter, &binary_option)?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        long_token_mint_info.key.as_ref(),
+        short_token_mint_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !user_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if binary_option.status == PoolStatus::Halted
+        || binary_option.is_expired(Clock::get()?.unix_timestamp)
+    {
+        return Err(BinaryOptionError::TradingClosed.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_owned_by(cpmm_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        cpmm_pool.binary_option_account_pubkey,
+        *binary_option_account_info.key,
+    )?;
+    assert_keys_equal(cpmm_pool.vault_account_pubkey, *vault_account_info.key)?;
+    assert_keys_equal(
+        cpmm_pool.long_token_account_pubkey,
+        *cpmm_long_token_account_info.key,
+    )?;
+    assert_keys_equal(
+        cpmm_pool.short_token_account_pubkey,
+        *cpmm_short_token_account_info.key,
+    )?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *escrow_account_info.key,
+        binary_option.escrow_account_pubkey,
+    )?;
+    assert_keys_equal(user_account.owner, *user_info.key)?;
+    assert_keys_equal(user_token_account.owner, *user_info.key)?;
+    assert_keys_equal(user_account.mint, binary_option.escrow_mint_account_pubkey)?;
+
+    let (token_mint_info, decimals, inventory_info, inventory, other_inventory) = match side {
+        PositionSide::Long => (
+            long_token_mint_info,
+            long_token_mint.decimals,
+            cpmm_long_token_account_info,
+            cpmm_long_token_account.amount,
+            cpmm_short_token_account.amount,
+        ),
+        PositionSide::Short => (
+            short_token_mint_info,
+            short_token_mint.decimals,
+            cpmm_short_token_account_info,
+            cpmm_short_token_account.amount,
+            cpmm_long_token_account.amount,
+        ),
+    };
+    assert_keys_equal(user_token_account.mint, *token_mint_info.key)?;
+
+    let inventory_accounts = InventoryAccounts {
+        token_program: token_program_info,
+        escrow_account: escrow_account_info,
+        long_token_mint: long_token_mint_info,
+        short_token_mint: short_token_mint_info,
+        vault_account: vault_account_info,
+        long_token_account: cpmm_long_token_account_info,
+        short_token_account: cpmm_short_token_account_info,
+        escrow_authority: authority_info,
+    };
+    let n = size;
+    let unit = u64::pow(10, binary_option.decimals as u32);
+    // Virtual balances, see process_cpmm_liquidity
+    let balance = inventory as u128 * unit as u128 + vault_account.amount as u128;
+    let other_balance = other_inventory as u128 * unit as u128 + vault_account.amount as u128;
+    if balance == 0 || other_balance == 0 {
+        return Err(BinaryOptionError::InsufficientLiquidity.into());
+    }
+
+    // Like the LMSR market maker, the pool only holds complete sets that it mints from (or
+    // redeems into) its vault. Fees stay in the vault and accrue to the liquidity providers, the
+    // fees of the market itself go to its fee vault like on a `Trade`.
+    if buy {
+        let cost = cpmm_buy_cost(balance, other_balance, n as u128 * unit as u128)?;
+        let fee = fee_amount(cost, cpmm_pool.fee_bps);
+        let fees = binary_option.fees(cost);
+        let total = cost
+            .checked_add(fee)
+            .and_then(|total| total.checked_add(fees.0))
+            .and_then(|total| total.checked_add(fees.1))
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        if total > collateral_limit {
+            return Err(BinaryOptionError::SlippageExceeded.into());
+        }
+        spl_token_transfer(
+            token_program_info,
+            user_account_info,
+            vault_account_info,
+            user_info,
+            cost + fee,
+        )?;
+        if let Some(fee_vault_info) = fee_vault_info {
+            spl_token_transfer(
+                token_program_info,
+                user_account_info,
+                fee_vault_info,
+                user_info,
+                total_fees(fees)?,
+            )?;
+            binary_option.accrue_fees(fees)?;
+        }
+        mint_sets_into_inventory(
+            &mut binary_option,
+            &inventory_accounts,
+            n.saturating_sub(inventory),
+            unit,
+            seeds,
+        )?;
+        spl_token_transfer_signed(
+            token_program_info,
+            inventory_info,
+            user_token_account_info,
+            authority_info,
+            n,
+            seeds,
+        )?;
+        cpmm_pool.total_fees_accrued = cpmm_pool
+            .total_fees_accrued
+            .checked_add(fee)
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        // Delegate the burn authority to the PDA, so a private key is unnecessary on collection
+        spl_approve(
+            token_program_info,
+            user_token_account_info,
+            token_mint_info,
+            authority_info,
+            user_info,
+            burn_allowance(
+                &user_token_account,
+                &authority_key,
+                user_token_account
+                    .amount
+                    .checked_add(n)
+                    .ok_or(BinaryOptionError::MathOverflow)?,
+            )?,
+            decimals,
+        )?;
+    } else {
+        let proceeds = cpmm_sell_proceeds(balance, other_balance, n as u128 * unit as u128)?;
+        let fees = binary_option.fees(proceeds);
+        let fee = fee_amount(proceeds, cpmm_pool.fee_bps).min(proceeds - total_fees(fees)?);
+        let payout = proceeds - total_fees(fees)? - fee;
+        if payout < collateral_limit {
+            return Err(BinaryOptionError::SlippageExceeded.into());
+        }
+        spl_token_transfer(
+            token_program_info,
+            user_token_account_info,
+            inventory_info,
+            user_info,
+            n,
+        )?;
+        // Redeem the complete sets that are now in inventory to fund the payout
+        redeem_sets_from_inventory(
+            &mut binary_option,
+            &inventory_accounts,
+            other_inventory.min(
+                inventory
+                    .checked_add(n)
+                    .ok_or(BinaryOptionError::MathOverflow)?,
+            ),
+            unit,
+            seeds,
+        )?;
+        spl_token_transfer_signed(
+            token_program_info,
+            vault_account_info,
+            user_account_info,
+            authority_info,
+            payout,
+            seeds,
+        )?;
+        if let Some(fee_vault_info) = fee_vault_info {
+            spl_token_transfer_signed(
+                token_program_info,
+                vault_account_info,
+                fee_vault_info,
+                authority_info,
+                total_fees(fees)?,
+                seeds,
+            )?;
+            binary_option.accrue_fees(fees)?;
+        }
+        cpmm_pool.total_fees_accrued = cpmm_pool
+            .total_fees_accrued
+            .checked_add(fee)
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        spl_approve(
+            token_program_info,
+            user_token_account_info,
+            token_mint_info,
+            authority_info,
+            user_info,
+            burn_allowance(
+                &user_token_account,
+                &authority_key,
+                user_token_account
+                    .amount
+                    .checked_sub(n)
+                    .ok_or(BinaryOptionError::MathOverflow)?,
+            )?,
+            decimals,
+        )?;
+    }
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    cpmm_pool.serialize(&mut &mut cpmm_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_initialize_resolver(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    args: InitializeResolverArgs,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let resolver_account_info = next_account_info(account_info_iter)?;
+    let bond_vault_account_info = next_account_info(account_info_iter)?;
+    let escrow_mint_info = next_account_info(account_info_iter)?;
+    let arbiter_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let system_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, _) = Pubkey::find_program_address(
+        &[
+            binary_option.long_mint_account_pubkey.as_ref(),
+            binary_option.short_mint_account_pubkey.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+
+    // Validate data
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.status != PoolStatus::Open {
+        return Err(BinaryOptionError::InvalidPoolStatus.into());
+    }
+    // The terms of the market can't change once positions exist
+    if binary_option.circulation > 0 {
+        return Err(BinaryOptionError::InvalidSupply.into());
+    }
+    // Bonds are refunded from the one vault the pool knows about
+    if binary_option.bond_vault_account_pubkey != Pubkey::default() {
+        return Err(BinaryOptionError::ResolverAlreadyInitialized.into());
+    }
+    if args.bond == 0
+        || args.dispute_window <= 0
+        || args.void_price > u64::pow(10, binary_option.decimals as u32)
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *escrow_mint_info.key,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+
+    // Bonds are held by the escrow PDA until the outcome is final
+    create_new_account(
+        pool_owner_info,
+        bond_vault_account_info,
+        Account::LEN,
+        token_program_info,
+        rent_info,
+    )?;
+    spl_initialize(
+        token_program_info,
+        bond_vault_account_info,
+        escrow_mint_info,
+        authority_info,
+        rent_info,
+    )?;
+    create_or_allocate_account_raw(
+        *program_id,
+        resolver_account_info,
+        rent_info,
+        system_account_info,
+        pool_owner_info,
+        OptimisticResolver::LEN,
+    )?;
+
+    let mut resolver = OptimisticResolver::from_account_info(resolver_account_info)?;
+    resolver.binary_option_account_pubkey = *binary_option_account_info.key;
+    resolver.bond_vault_account_pubkey = *bond_vault_account_info.key;
+    resolver.arbiter = *arbiter_info.key;
+    resolver.bond = args.bond;
+    resolver.dispute_window = args.dispute_window;
+    resolver.serialize(&mut &mut resolver_account_info.data.borrow_mut()[..])?;
+
+    binary_option.resolution_source = ResolutionSource::Optimistic;
+    binary_option.void_price = args.void_price;
+    binary_option.bond_vault_account_pubkey = *bond_vault_account_info.key;
+    binary_option.resolver_account_pubkey = *resolver_account_info.key;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_post_bond(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    proposal: Option<Outcome>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let resolver_account_info = next_account_info(account_info_iter)?;
+    let bond_vault_account_info = next_account_info(account_info_iter)?;
+    let bonder_info = next_account_info(account_info_iter)?;
+    let bonder_account_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let bonder_account: Account = assert_initialized(bonder_account_info)?;
+    let binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let mut resolver = OptimisticResolver::from_account_info(resolver_account_info)?;
+
+    // Validate data
+    if !bonder_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if binary_option.resolution_source != ResolutionSource::Optimistic {
+        return Err(BinaryOptionError::InvalidResolutionSource.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_owned_by(resolver_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(
+        resolver.binary_option_account_pubkey,
+        *binary_option_account_info.key,
+    )?;
+    assert_keys_equal(
+        resolver.bond_vault_account_pubkey,
+        *bond_vault_account_info.key,
+    )?;
+    assert_keys_equal(bonder_account.owner, *bonder_info.key)?;
+    assert_keys_equal(
+        bonder_account.mint,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+
+    let now = Clock::get()?.unix_timestamp;
+    match proposal {
+        Some(outcome) => {
+            // Outcomes can only be proposed once trading has stopped
+            if binary_option.status != PoolStatus::Halted && !binary_option.is_expired(now) {
+                return Err(BinaryOptionError::MarketNotExpired.into());
+            }
+            if resolver.has_proposal() {
+                return Err(BinaryOptionError::InvalidProposalState.into());
+            }
+            resolver.proposer_account_pubkey = *bonder_account_info.key;
+            resolver.proposed_outcome = outcome;
+            resolver.proposed_at = now;
+        }
+        None => {
+            if !resolver.has_proposal() || resolver.is_disputed() {
+                return Err(BinaryOptionError::InvalidProposalState.into());
+            }
+            if now >= resolver.dispute_deadline()? {
+                return Err(BinaryOptionError::DisputeWindowClosed.into());
+            }
+            resolver.disputer_account_pubkey = *bonder_account_info.key;
+        }
+    }
+    spl_token_transfer(
+        token_program_info,
+        bonder_account_info,
+        bond_vault_account_info,
+        bonder_info,
+        resolver.bond,
+    )?;
+    resolver.serialize(&mut &mut resolver_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_finalize_outcome(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    arbiter_outcome: Option<Outcome>,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let resolver_account_info = next_account_info(account_info_iter)?;
+    let bond_vault_account_info = next_account_info(account_info_iter)?;
+    let proposer_account_info = next_account_info(account_info_iter)?;
+    // Only disputed proposals need the disputer's account and the arbiter
+    let dispute = match arbiter_outcome {
+        Some(outcome) => Some((
+            outcome,
+            next_account_info(account_info_iter)?,
+            next_account_info(account_info_iter)?,
+        )),
+        None => None,
+    };
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let bond_vault_account: Account = assert_initialized(bond_vault_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let resolver = OptimisticResolver::from_account_info(resolver_account_info)?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            binary_option.long_mint_account_pubkey.as_ref(),
+            binary_option.short_mint_account_pubkey.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        binary_option.long_mint_account_pubkey.as_ref(),
+        binary_option.short_mint_account_pubkey.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if binary_option.resolution_source != ResolutionSource::Optimistic {
+        return Err(BinaryOptionError::InvalidResolutionSource.into());
+    }
+    // Like proposals, outcomes only become final once trading has stopped
+    let now = Clock::get()?.unix_timestamp;
+    if binary_option.status != PoolStatus::Halted && !binary_option.is_expired(now) {
+        return Err(BinaryOptionError::MarketNotExpired.into());
+    }
+    if !resolver.has_proposal() {
+        return Err(BinaryOptionError::InvalidProposalState.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_owned_by(resolver_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        resolver.binary_option_account_pubkey,
+        *binary_option_account_info.key,
+    )?;
+    assert_keys_equal(
+        resolver.bond_vault_account_pubkey,
+        *bond_vault_account_info.key,
+    )?;
+    assert_keys_equal(resolver.proposer_account_pubkey, *proposer_account_info.key)?;
+
+    let (outcome, winner_account_info) = match dispute {
+        Some((outcome, disputer_account_info, arbiter_info)) => {
+            // Disputed proposals are decided by the arbiter, the side that was right takes
+            // both bonds
+            if !arbiter_info.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if !resolver.is_disputed() {
+                return Err(BinaryOptionError::InvalidProposalState.into());
+            }
+            assert_keys_equal(resolver.arbiter, *arbiter_info.key)?;
+            assert_keys_equal(resolver.disputer_account_pubkey, *disputer_account_info.key)?;
+            if outcome == resolver.proposed_outcome {
+                (outcome, proposer_account_info)
+            } else {
+                (outcome, disputer_account_info)
+            }
+        }
+        None => {
+            // Undisputed proposals become final once the dispute window is over
+            if resolver.is_disputed() {
+                return Err(BinaryOptionError::InvalidProposalState.into());
+            }
+            if now < resolver.dispute_deadline()? {
+                return Err(BinaryOptionError::DisputeWindowOpen.into());
+            }
+            (resolver.proposed_outcome, proposer_account_info)
+        }
+    };
+    spl_token_transfer_signed(
+        token_program_info,
+        bond_vault_account_info,
+        winner_account_info,
+        authority_info,
+        bond_vault_account.amount,
+        seeds,
+    )?;
+
+    binary_option.resolve(outcome, binary_option.void_price);
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_refund_bonds(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Permissionless, the bonds can only go back to the accounts they came from
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let resolver_account_info = next_account_info(account_info_iter)?;
+    let bond_vault_account_info = next_account_info(account_info_iter)?;
+    let proposer_account_info = next_account_info(account_info_iter)?;
+
+    let binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let mut resolver = OptimisticResolver::from_account_info(resolver_account_info)?;
+    // Only disputed proposals need the disputer's account
+    let disputer_account_info = if resolver.is_disputed() {
+        Some(next_account_info(account_info_iter)?)
+    } else {
+        None
+    };
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            binary_option.long_mint_account_pubkey.as_ref(),
+            binary_option.short_mint_account_pubkey.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        binary_option.long_mint_account_pubkey.as_ref(),
+        binary_option.short_mint_account_pubkey.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    // The resolver can no longer settle the pool once it has been switched to another
+    // source, so the proposal will never be finalized
+    if binary_option.resolution_source == ResolutionSource::Optimistic {
+        return Err(BinaryOptionError::InvalidResolutionSource.into());
+    }
+    if !resolver.has_proposal() {
+        return Err(BinaryOptionError::InvalidProposalState.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_owned_by(resolver_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        resolver.binary_option_account_pubkey,
+        *binary_option_account_info.key,
+    )?;
+    assert_keys_equal(
+        resolver.bond_vault_account_pubkey,
+        *bond_vault_account_info.key,
+    )?;
+    assert_keys_equal(resolver.proposer_account_pubkey, *proposer_account_info.key)?;
+    if let Some(disputer_account_info) = disputer_account_info {
+        assert_keys_equal(resolver.disputer_account_pubkey, *disputer_account_info.key)?;
+    }
+
+    for bonder_account_info in std::iter::once(proposer_account_info).chain(disputer_account_info) {
+        spl_token_transfer_signed(
+            token_program_info,
+            bond_vault_account_info,
+            bonder_account_info,
+            authority_info,
+            resolver.bond,
+            seeds,
+        )?;
+    }
+    resolver.proposer_account_pubkey = Pubkey::default();
+    resolver.disputer_account_pubkey = Pubkey::default();
+    resolver.proposed_at = 0;
+    resolver.serialize(&mut &mut resolver_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_initialize_committee(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    args: InitializeCommitteeArgs,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let committee_account_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+    let system_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    // The remaining accounts are the voters
+    let voter_infos = account_info_iter.as_slice();
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Validate data
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.status != PoolStatus::Open {
+        return Err(BinaryOptionError::InvalidPoolStatus.into());
+    }
+    // The terms of the market can't change once positions exist
+    if binary_option.circulation > 0 {
+        return Err(BinaryOptionError::InvalidSupply.into());
+    }
+    // Only the committee the pool knows about can vote
+    if binary_option.committee_account_pubkey != Pubkey::default() {
+        return Err(BinaryOptionError::CommitteeAlreadyInitialized.into());
+    }
+    if voter_infos.len() > MAX_VOTERS
+        || args.threshold == 0
+        || args.threshold as usize > voter_infos.len()
+        || args.void_price > u64::pow(10, binary_option.decimals as u32)
+    {
+        return Err(ProgramError::InvalidArgument);
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    for (i, voter_info) in voter_infos.iter().enumerate() {
+        for other_info in voter_infos[..i].iter() {
+            assert_keys_unequal(*voter_info.key, *other_info.key)?;
+        }
+    }
+
+    create_or_allocate_account_raw(
+        *program_id,
+        committee_account_info,
+        rent_info,
+        system_account_info,
+        pool_owner_info,
+        ResolverCommittee::LEN,
+    )?;
+
+    let mut committee = ResolverCommittee::from_account_info(committee_account_info)?;
+    committee.binary_option_account_pubkey = *binary_option_account_info.key;
+    committee.threshold = args.threshold;
+    committee.voter_count = voter_infos.len() as u8;
+    for (voter, voter_info) in committee.voters.iter_mut().zip(voter_infos.iter()) {
+        *voter = *voter_info.key;
+    }
+    committee.serialize(&mut &mut committee_account_info.data.borrow_mut()[..])?;
+
+    binary_option.resolution_source = ResolutionSource::Committee;
+    binary_option.void_price = args.void_price;
+    binary_option.committee_account_pubkey = *committee_account_info.key;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_vote(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    outcome: Outcome,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let committee_account_info = next_account_info(account_info_iter)?;
+    let voter_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let mut committee = ResolverCommittee::from_account_info(committee_account_info)?;
+
+    // Validate data
+    if !voter_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.is_settled() {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if binary_option.resolution_source != ResolutionSource::Committee {
+        return Err(BinaryOptionError::InvalidResolutionSource.into());
+    }
+    // Votes can only be cast once trading has stopped
+    if binary_option.status != PoolStatus::Halted
+        && !binary_option.is_expired(Clock::get()?.unix_timestamp)
+    {
+        return Err(BinaryOptionError::MarketNotExpired.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_owned_by(committee_account_info, program_id)?;
+    assert_keys_equal(
+        committee.binary_option_account_pubkey,
+        *binary_option_account_info.key,
+    )?;
+    assert_keys_equal(
+        binary_option.committee_account_pubkey,
+        *committee_account_info.key,
+    )?;
+
+    // Voters can change their vote until the threshold is reached
+    let i = committee.find_voter(voter_info.key)?;
+    committee.votes[i] = Vote::from(outcome);
+    if committee.tally(committee.votes[i]) >= committee.threshold as usize {
+        binary_option.resolve(outcome, binary_option.void_price);
+        binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    }
+    committee.serialize(&mut &mut committee_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_initialize_categorical(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    decimals: u8,
+    expiry_unix_timestamp: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let market_account_info = next_account_info(account_info_iter)?;
+    let escrow_mint_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let system_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+    // The remaining accounts are the outcome mints
+    let outcome_mint_infos = account_info_iter.as_slice();
+
+    // Get program derived address for escrow
+    let (authority_key, _) = Pubkey::find_program_address(
+        &[
+            market_account_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+
+    // Validate data
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if outcome_mint_infos.len() < 2 || outcome_mint_infos.len() > MAX_OUTCOMES {
+        return Err(BinaryOptionError::InvalidOutcome.into());
+    }
+    if decimals > MAX_DECIMALS {
+        return Err(ProgramError::InvalidArgument);
+    }
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+
+    // The mints and the escrow are owned by the PDA from the start
+    for outcome_mint_info in outcome_mint_infos.iter() {
+        create_new_account(
+            owner_info,
+            outcome_mint_info,
+            Mint::LEN,
+            token_program_info,
+            rent_info,
+        )?;
+        spl_mint_initialize(
+            token_program_info,
+            outcome_mint_info,
+            authority_info,
+            authority_info,
+            rent_info,
+            0,
+        )?;
+    }
+    create_new_account(
+        owner_info,
+        escrow_account_info,
+        Account::LEN,
+        token_program_info,
+        rent_info,
+    )?;
+    spl_initialize(
+        token_program_info,
+        escrow_account_info,
+        escrow_mint_info,
+        authority_info,
+        rent_info,
+    )?;
+    create_or_allocate_account_raw(
+        *program_id,
+        market_account_info,
+        rent_info,
+        system_account_info,
+        owner_info,
+        CategoricalMarket::LEN,
+    )?;
+
+    let mut market = CategoricalMarket::from_account_info(market_account_info)?;
+    market.decimals = decimals;
+    market.circulation = 0;
+    market.status = PoolStatus::Open;
+    market.escrow_mint_account_pubkey = *escrow_mint_info.key;
+    market.escrow_account_pubkey = *escrow_account_info.key;
+    market.owner = *owner_info.key;
+    market.outcome_count = outcome_mint_infos.len() as u8;
+    market.expiry_unix_timestamp = expiry_unix_timestamp;
+    for (mint, outcome_mint_info) in market
+        .outcome_mints
+        .iter_mut()
+        .zip(outcome_mint_infos.iter())
+    {
+        *mint = *outcome_mint_info.key;
+    }
+    market.serialize(&mut &mut market_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_categorical_set(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    size: u64,
+    mint: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let market_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let user_info = next_account_info(account_info_iter)?;
+    let user_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    // The remaining accounts are (outcome mint, user token account) pairs in outcome order
+    let outcome_infos = account_info_iter.as_slice();
+
+    let user_account: Account = assert_initialized(user_account_info)?;
+    let mut market = CategoricalMarket::from_account_info(market_account_info)?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            market_account_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        market_account_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !user_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if market.status != PoolStatus::Open {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    // Like `MintSet`, no new contracts after expiry, but sets can still be redeemed
+    if mint && market.is_expired(Clock::get()?.unix_timestamp) {
+        return Err(BinaryOptionError::TradingClosed.into());
+    }
+    if outcome_infos.len() != 2 * market.outcome_count as usize {
+        return Err(BinaryOptionError::InvalidOutcome.into());
+    }
+    assert_owned_by(market_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(*escrow_account_info.key, market.escrow_account_pubkey)?;
+    assert_keys_equal(user_account.owner, *user_info.key)?;
+    assert_keys_equal(user_account.mint, market.escrow_mint_account_pubkey)?;
+
+    // Like binary options, a complete set is always worth exactly 10^decimals of collateral
+    let n = size;
+    let collateral = notional(n, u64::pow(10, market.decimals as u32))?;
+    if mint {
+        spl_token_transfer(
+            token_program_info,
+            user_account_info,
+            escrow_account_info,
+            user_info,
+            collateral,
+        )?;
+    }
+    for (outcome_mint, pair) in market.outcome_mints().iter().zip(outcome_infos.chunks(2)) {
+        let (outcome_mint_info, user_token_account_info) = (&pair[0], &pair[1]);
+        let user_token_account: Account = assert_initialized(user_token_account_info)?;
+        assert_keys_equal(*outcome_mint_info.key, *outcome_mint)?;
+        assert_keys_equal(user_token_account.mint, *outcome_mint)?;
+        assert_keys_equal(user_token_account.owner, *user_info.key)?;
+        if mint {
+            spl_mint_to(
+                token_program_info,
+                user_token_account_info,
+                outcome_mint_info,
+                authority_info,
+                n,
+                seeds,
+            )?;
+        } else {
+            spl_burn(
+                token_program_info,
+                user_token_account_info,
+                outcome_mint_info,
+                user_info,
+                n,
+            )?;
+        }
+    }
+    if mint {
+        market.increment_supply(n)?;
+    } else {
+        spl_token_transfer_signed(
+            token_program_info,
+            escrow_account_info,
+            user_account_info,
+            authority_info,
+            collateral,
+            seeds,
+        )?;
+        market.decrement_supply(n)?;
+    }
+    market.serialize(&mut &mut market_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_categorical_trade(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    outcome: u8,
+    size: u64,
+    price: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let market_account_info = next_account_info(account_info_iter)?;
+    let outcome_mint_info = next_account_info(account_info_iter)?;
+    let buyer_info = next_account_info(account_info_iter)?;
+    let seller_info = next_account_info(account_info_iter)?;
+    let buyer_account_info = next_account_info(account_info_iter)?;
+    let seller_account_info = next_account_info(account_info_iter)?;
+    let buyer_token_account_info = next_account_info(account_info_iter)?;
+    let seller_token_account_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let buyer_account: Account = assert_initialized(buyer_account_info)?;
+    let seller_account: Account = assert_initialized(seller_account_info)?;
+    let buyer_token_account: Account = assert_initialized(buyer_token_account_info)?;
+    let seller_token_account: Account = assert_initialized(seller_token_account_info)?;
+    let market = CategoricalMarket::from_account_info(market_account_info)?;
+
+    // Validate data
+    if !buyer_info.is_signer || !seller_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if market.status != PoolStatus::Open {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if market.is_expired(Clock::get()?.unix_timestamp) {
+        return Err(BinaryOptionError::TradingClosed.into());
+    }
+    if outcome >= market.outcome_count {
+        return Err(BinaryOptionError::InvalidOutcome.into());
+    }
+    if price == 0 || price > u64::pow(10, market.decimals as u32) {
+        return Err(BinaryOptionError::TradePricesIncorrect.into());
+    }
+    assert_owned_by(market_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_unequal(*buyer_info.key, *seller_info.key)?;
+    assert_keys_equal(
+        *outcome_mint_info.key,
+        market.outcome_mints[outcome as usize],
+    )?;
+    assert_keys_equal(buyer_account.owner, *buyer_info.key)?;
+    assert_keys_equal(seller_account.owner, *seller_info.key)?;
+    assert_keys_equal(buyer_account.mint, market.escrow_mint_account_pubkey)?;
+    assert_keys_equal(seller_account.mint, market.escrow_mint_account_pubkey)?;
+    assert_keys_equal(buyer_token_account.owner, *buyer_info.key)?;
+    assert_keys_equal(seller_token_account.owner, *seller_info.key)?;
+    assert_keys_equal(buyer_token_account.mint, *outcome_mint_info.key)?;
+    assert_keys_equal(seller_token_account.mint, *outcome_mint_info.key)?;
+
+    // Outcome tokens only change hands, new ones are created through complete sets. There is no
+    // fee: fees are configured on binary pools, and a categorical market has no fee vault.
+    spl_token_transfer(
+        token_program_info,
+        buyer_account_info,
+        seller_account_info,
+        buyer_info,
+        notional(size, price)?,
+    )?;
+    spl_token_transfer(
+        token_program_info,
+        seller_token_account_info,
+        buyer_token_account_info,
+        seller_info,
+        size,
+    )?;
+    Ok(())
+}
+
+pub fn process_categorical_settle(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    winning_outcome: u8,
+) -> ProgramResult {
+    // Like `Settle`, the owner is trusted to pick the winner
+    let account_info_iter = &mut accounts.iter();
+    let market_account_info = next_account_info(account_info_iter)?;
+    let owner_info = next_account_info(account_info_iter)?;
+
+    let mut market = CategoricalMarket::from_account_info(market_account_info)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if market.status != PoolStatus::Open {
+        return Err(BinaryOptionError::AlreadySettled.into());
+    }
+    if winning_outcome >= market.outcome_count {
+        return Err(BinaryOptionError::InvalidWinner.into());
+    }
+    assert_owned_by(market_account_info, program_id)?;
+    assert_keys_equal(*owner_info.key, market.owner)?;
+
+    market.winning_outcome = winning_outcome;
+    market.status = PoolStatus::Settled;
+    market.serialize(&mut &mut market_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_categorical_collect(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let market_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let winning_mint_info = next_account_info(account_info_iter)?;
+    let collector_info = next_account_info(account_info_iter)?;
+    let collector_account_info = next_account_info(account_info_iter)?;
+    let collector_token_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let escrow_account: Account = assert_initialized(escrow_account_info)?;
+    let collector_account: Account = assert_initialized(collector_account_info)?;
+    let collector_token_account: Account = assert_initialized(collector_token_account_info)?;
+    let mut market = CategoricalMarket::from_account_info(market_account_info)?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            market_account_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        market_account_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !collector_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if market.status != PoolStatus::Settled {
+        return Err(BinaryOptionError::BetNotSettled.into());
+    }
+    assert_owned_by(market_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(*escrow_account_info.key, market.escrow_account_pubkey)?;
+    assert_keys_equal(
+        *winning_mint_info.key,
+        market.outcome_mints[market.winning_outcome as usize],
+    )?;
+    assert_keys_equal(collector_account.owner, *collector_info.key)?;
+    assert_keys_equal(collector_account.mint, market.escrow_mint_account_pubkey)?;
+    assert_keys_equal(collector_token_account.owner, *collector_info.key)?;
+    assert_keys_equal(collector_token_account.mint, *winning_mint_info.key)?;
+
+    // Tokens of the other outcomes are worthless, only the winning ones are burned
+    let reward = collector_token_account.amount;
+    if reward > 0 {
+        let amount = market.payout(reward, escrow_account.amount)?;
+        spl_burn(
+            token_program_info,
+            collector_token_account_info,
+            winning_mint_info,
+            collector_info,
+            reward,
+        )?;
+        spl_token_transfer_signed(
+            token_program_info,
+            escrow_account_info,
+            collector_account_info,
+            authority_info,
+            amount,
+            seeds,
+        )?;
+        market.decrement_supply(reward)?;
+    }
+    market.serialize(&mut &mut market_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_configure_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    protocol_fee_bps: u16,
+    creator_fee_bps: u16,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let escrow_mint_info = next_account_info(account_info_iter)?;
+    let fee_vault_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+    let protocol_fee_recipient_info = next_account_info(account_info_iter)?;
+    let creator_fee_recipient_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+    let _system_account_info = next_account_info(account_info_iter)?;
+    let rent_info = next_account_info(account_info_iter)?;
+
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, _) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+
+    // Validate data
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if binary_option.has_fees() {
+        return Err(BinaryOptionError::FeesAlreadyConfigured.into());
+    }
+    // Fees can only be set up before anyone holds a position, so traders know them up front
+    if binary_option.status != PoolStatus::Open || binary_option.circulation > 0 {
+        return Err(BinaryOptionError::InvalidPoolStatus.into());
+    }
+    if protocol_fee_bps as u32 + creator_fee_bps as u32 > MAX_FEE_BPS as u32 {
+        return Err(BinaryOptionError::FeeTooHigh.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *escrow_mint_info.key,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+
+    // The fee vault is held by the escrow PDA, fees leave it through WithdrawFees only
+    create_new_account(
+        pool_owner_info,
+        fee_vault_info,
+        Account::LEN,
+        token_program_info,
+        rent_info,
+    )?;
+    spl_initialize(
+        token_program_info,
+        fee_vault_info,
+        escrow_mint_info,
+        authority_info,
+        rent_info,
+    )?;
+
+    binary_option.fee_vault_account_pubkey = *fee_vault_info.key;
+    binary_option.fee_vault_payer = *pool_owner_info.key;
+    binary_option.protocol_fee_recipient = *protocol_fee_recipient_info.key;
+    binary_option.protocol_fee_bps = protocol_fee_bps;
+    binary_option.creator_fee_recipient = *creator_fee_recipient_info.key;
+    binary_option.creator_fee_bps = creator_fee_bps;
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_withdraw_fees(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let fee_vault_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let recipient_info = next_account_info(account_info_iter)?;
+    let recipient_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let recipient_account: Account = assert_initialized(recipient_account_info)?;
+    let mut binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        long_token_mint_info.key.as_ref(),
+        short_token_mint_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !recipient_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !binary_option.has_fees() {
+        return Err(BinaryOptionError::NotAFeeRecipient.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(*fee_vault_info.key, binary_option.fee_vault_account_pubkey)?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(recipient_account.owner, *recipient_info.key)?;
+    assert_keys_equal(
+        recipient_account.mint,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+
+    // A recipient of both fees withdraws both at once
+    let mut amount = 0;
+    let mut is_recipient = false;
+    if *recipient_info.key == binary_option.protocol_fee_recipient {
+        amount = binary_option.protocol_fees_accrued;
+        binary_option.protocol_fees_accrued = 0;
+        is_recipient = true;
+    }
+    if *recipient_info.key == binary_option.creator_fee_recipient {
+        amount = amount
+            .checked_add(binary_option.creator_fees_accrued)
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        binary_option.creator_fees_accrued = 0;
+        is_recipient = true;
+    }
+    if !is_recipient {
+        return Err(BinaryOptionError::NotAFeeRecipient.into());
+    }
+    if amount > 0 {
+        spl_token_transfer_signed(
+            token_program_info,
+            fee_vault_info,
+            recipient_account_info,
+            authority_info,
+            amount,
+            seeds,
+        )?;
+    }
+    binary_option.serialize(&mut &mut binary_option_account_info.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+pub fn process_close_pool(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+    let pool_owner_account_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let escrow_account: Account = assert_initialized(escrow_account_info)?;
+    let pool_owner_account: Account = assert_initialized(pool_owner_account_info)?;
+    let binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+    let fee_vault_info = next_fee_vault_info(account_info_iter, &binary_option)?;
+    // The fee vault is followed by whoever paid for it
+    let fee_vault_payer_info = match fee_vault_info {
+        Some(_) => Some(next_account_info(account_info_iter)?),
+        None => None,
+    };
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        long_token_mint_info.key.as_ref(),
+        short_token_mint_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !binary_option.is_settled() {
+        return Err(BinaryOptionError::BetNotSettled.into());
+    }
+    // Every token has to be collected, and fees withdrawn, before the pool goes away
+    if binary_option.circulation > 0
+        || binary_option.protocol_fees_accrued > 0
+        || binary_option.creator_fees_accrued > 0
+    {
+        return Err(BinaryOptionError::PoolNotEmpty.into());
+    }
+    // So does the collateral of liquidity providers, resting orders and bonds, which is held
+    // outside of the escrow
+    for vault_key in binary_option.venue_token_accounts().iter() {
+        let vault: Account = assert_initialized(find_account_info(accounts, vault_key)?)?;
+        if vault.amount > 0 {
+            return Err(BinaryOptionError::PoolNotEmpty.into());
+        }
+    }
+    if binary_option.cpmm_lp_mint_pubkey != Pubkey::default() {
+        let lp_mint: Mint = assert_initialized(find_account_info(
+            accounts,
+            &binary_option.cpmm_lp_mint_pubkey,
+        )?)?;
+        if lp_mint.supply > 0 {
+            return Err(BinaryOptionError::PoolNotEmpty.into());
+        }
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    assert_keys_equal(*payer_info.key, binary_option.payer)?;
+    if let Some(fee_vault_payer_info) = fee_vault_payer_info {
+        assert_keys_equal(*fee_vault_payer_info.key, binary_option.fee_vault_payer)?;
+    }
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *escrow_account_info.key,
+        binary_option.escrow_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(pool_owner_account.owner, *pool_owner_info.key)?;
+    assert_keys_equal(
+        pool_owner_account.mint,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+
+    // Rounding dust that is left in the escrow once everyone has collected goes to the owner
+    spl_token_transfer_signed(
+        token_program_info,
+        escrow_account_info,
+        pool_owner_account_info,
+        authority_info,
+        escrow_account.amount,
+        seeds,
+    )?;
+    spl_close_account_signed(
+        token_program_info,
+        escrow_account_info,
+        payer_info,
+        authority_info,
+        seeds,
+    )?;
+    // The owner at the time paid for the fee vault when configuring fees
+    if let (Some(fee_vault_info), Some(fee_vault_payer_info)) =
+        (fee_vault_info, fee_vault_payer_info)
+    {
+        let fee_vault: Account = assert_initialized(fee_vault_info)?;
+        spl_token_transfer_signed(
+            token_program_info,
+            fee_vault_info,
+            pool_owner_account_info,
+            authority_info,
+            fee_vault.amount,
+            seeds,
+        )?;
+        spl_close_account_signed(
+            token_program_info,
+            fee_vault_info,
+            fee_vault_payer_info,
+            authority_info,
+            seeds,
+        )?;
+    }
+    // The venues go away with the pool. Only the owner can pay for them, so their rent goes back to
+    // the owner
+    for venue_key in binary_option.venue_token_accounts().iter() {
+        spl_close_account_signed(
+            token_program_info,
+            find_account_info(accounts, venue_key)?,
+            pool_owner_info,
+            authority_info,
+            seeds,
+        )?;
+    }
+    for venue_key in binary_option.venue_state_accounts().iter() {
+        let venue_info = find_account_info(accounts, venue_key)?;
+        assert_owned_by(venue_info, program_id)?;
+        close_account(venue_info, pool_owner_info)?;
+    }
+    close_account(binary_option_account_info, payer_info)
+}
+
+pub fn process_sweep_dust(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let binary_option_account_info = next_account_info(account_info_iter)?;
+    let escrow_account_info = next_account_info(account_info_iter)?;
+    let long_token_mint_info = next_account_info(account_info_iter)?;
+    let short_token_mint_info = next_account_info(account_info_iter)?;
+    let pool_owner_info = next_account_info(account_info_iter)?;
+    let pool_owner_account_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let escrow_account: Account = assert_initialized(escrow_account_info)?;
+    let pool_owner_account: Account = assert_initialized(pool_owner_account_info)?;
+    let binary_option =
+        BinaryOption::try_from_slice(&binary_option_account_info.data.borrow_mut())?;
+
+    // Get program derived address for escrow
+    let (authority_key, bump_seed) = Pubkey::find_program_address(
+        &[
+            long_token_mint_info.key.as_ref(),
+            short_token_mint_info.key.as_ref(),
+            token_program_info.key.as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    );
+    let seeds = &[
+        long_token_mint_info.key.as_ref(),
+        short_token_mint_info.key.as_ref(),
+        token_program_info.key.as_ref(),
+        program_id.as_ref(),
+        &[bump_seed],
+    ];
+
+    // Validate data
+    if !pool_owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !binary_option.is_settled() {
+        return Err(BinaryOptionError::BetNotSettled.into());
+    }
+    // Anything left in the escrow before the last collection belongs to the collectors
+    if binary_option.circulation > 0 {
+        return Err(BinaryOptionError::PoolNotEmpty.into());
+    }
+    assert_owned_by(binary_option_account_info, program_id)?;
+    assert_keys_equal(*token_program_info.key, spl_token::id())?;
+    assert_keys_equal(*pool_owner_info.key, binary_option.owner)?;
+    assert_keys_equal(authority_key, *authority_info.key)?;
+    assert_keys_equal(
+        *escrow_account_info.key,
+        binary_option.escrow_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *long_token_mint_info.key,
+        binary_option.long_mint_account_pubkey,
+    )?;
+    assert_keys_equal(
+        *short_token_mint_info.key,
+        binary_option.short_mint_account_pubkey,
+    )?;
+    assert_keys_equal(pool_owner_account.owner, *pool_owner_info.key)?;
+    assert_keys_equal(
+        pool_owner_account.mint,
+        binary_option.escrow_mint_account_pubkey,
+    )?;
+
+    spl_token_transfer_signed(
+        token_program_info,
+        escrow_account_info,
+        pool_owner_account_info,
+        authority_info,
+        escrow_account.amount,
+        seeds,
+    )
+}
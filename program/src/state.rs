@@ -3,33 +3,726 @@ use solana_program::{
     pubkey::Pubkey,
 };
 
-use crate::error::BinaryOptionError;
+use crate::{
+    error::BinaryOptionError,
+    math_utils::{fee_amount, notional, pro_rata},
+};
 use borsh::{BorshDeserialize, BorshSerialize};
+use std::convert::TryFrom;
+
+/// `winning_side_pubkey` of a voided pool, it can never be a mint
+pub const VOID_SENTINEL: Pubkey = Pubkey::new_from_array([255; 32]);
+
+/// Upper bound on the combined protocol and creator fee
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+/// Largest `decimals` of a pool, 10^decimals has to fit in a u64
+pub const MAX_DECIMALS: u8 = 19;
+
+/// Seconds the owner of an oracle pool waits after the publish deadline before it can `Void`
+/// the pool, so that a price published in time can still be settled
+pub const ORACLE_VOID_GRACE_PERIOD: i64 = 24 * 60 * 60;
 
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
 pub struct BinaryOption {
     pub decimals: u8,
     pub circulation: u64,
-    pub settled: bool,
+    pub status: PoolStatus,
     pub escrow_mint_account_pubkey: Pubkey,
     pub escrow_account_pubkey: Pubkey,
     pub long_mint_account_pubkey: Pubkey,
     pub short_mint_account_pubkey: Pubkey,
     pub owner: Pubkey,
     pub winning_side_pubkey: Pubkey,
+    /// Liquidity parameter `b` of the LMSR market maker, 0 if the pool has no market maker
+    pub amm_liquidity: u64,
+    /// Number of long tokens the market maker has sold (net of buybacks)
+    pub amm_long_outstanding: u64,
+    /// Number of short tokens the market maker has sold (net of buybacks)
+    pub amm_short_outstanding: u64,
+    pub amm_vault_account_pubkey: Pubkey,
+    pub amm_long_token_account_pubkey: Pubkey,
+    pub amm_short_token_account_pubkey: Pubkey,
+    /// Trading stops at this unix timestamp, 0 if the pool never expires
+    pub expiry_unix_timestamp: i64,
+    /// Payout of a long token in a voided pool, a short token pays out the rest of 10^decimals.
+    /// Set up front by `ConfigureOracle`, `InitializeResolver` and `InitializeCommittee`, or by
+    /// `Void` on a pool settled by its owner
+    pub void_price: u64,
+    pub resolution_source: ResolutionSource,
+    /// Price feed that settles the pool when `resolution_source` is `Oracle`
+    pub oracle_feed_pubkey: Pubkey,
+    /// Strike, expressed with the exponent of the price feed
+    pub strike_price: i64,
+    /// Long wins if `price <comparison> strike_price` holds at settlement
+    pub comparison: Comparison,
+    pub max_staleness_slots: u64,
+    pub max_confidence_bps: u16,
+    /// Program that owns `oracle_feed_pubkey`, Pyth on mainnet
+    pub oracle_program_id: Pubkey,
+    /// Seconds after the expiry during which a published price can settle the pool
+    pub oracle_max_publish_delay: i64,
+    pub market_type: MarketType,
+    /// A scalar market pays out all of the collateral to long at or above the upper bound and
+    /// all of it to short at or below the lower bound
+    pub scalar_lower_bound: i64,
+    pub scalar_upper_bound: i64,
+    /// Value a scalar market was settled at
+    pub resolved_value: i64,
+    /// Token account of the escrow PDA collecting fees, default if the pool charges no fees
+    pub fee_vault_account_pubkey: Pubkey,
+    pub protocol_fee_recipient: Pubkey,
+    /// Charged on the notional of each trade and on every collection
+    pub protocol_fee_bps: u16,
+    /// Protocol fees in the vault that have not been withdrawn yet
+    pub protocol_fees_accrued: u64,
+    pub creator_fee_recipient: Pubkey,
+    pub creator_fee_bps: u16,
+    pub creator_fees_accrued: u64,
+    /// Owner proposed by `ProposeOwner`, default if there is no pending transfer
+    pub pending_owner: Pubkey,
+    /// Paid the rent of the pool and the escrow, and gets it back on `ClosePool`
+    pub payer: Pubkey,
+    /// Owner who paid the rent of the fee vault on `ConfigureFees`, and gets it back on `ClosePool`
+    pub fee_vault_payer: Pubkey,
+    /// Collateral vault of the order book, default if the pool has none
+    pub order_book_vault_account_pubkey: Pubkey,
+    /// Collateral vault of the constant product market maker, default if the pool has none
+    pub cpmm_vault_account_pubkey: Pubkey,
+    pub cpmm_lp_mint_pubkey: Pubkey,
+    /// Vault of the optimistic resolver's bonds, default if the pool has none
+    pub bond_vault_account_pubkey: Pubkey,
+    /// Committee that votes on the outcome, default if the pool has none
+    pub committee_account_pubkey: Pubkey,
+    /// State of the order book, default if the pool has none
+    pub order_book_account_pubkey: Pubkey,
+    /// State and inventory of the constant product market maker, default if the pool has none
+    pub cpmm_account_pubkey: Pubkey,
+    pub cpmm_long_token_account_pubkey: Pubkey,
+    pub cpmm_short_token_account_pubkey: Pubkey,
+    /// State of the optimistic resolver, default if the pool has none
+    pub resolver_account_pubkey: Pubkey,
 }
 
 impl BinaryOption {
-    pub const LEN: usize = 202;
+    pub const LEN: usize = 987;
 
     pub fn from_account_info(a: &AccountInfo) -> Result<BinaryOption, ProgramError> {
         let binary_option = BinaryOption::try_from_slice(&a.data.borrow_mut())?;
         Ok(binary_option)
     }
 
-    pub fn increment_supply(&mut self, n: u64) {
-        self.circulation += n;
+    /// Every account of the trading venues, the resolver and the committee. `ClosePool` takes
+    /// them to check that nothing is left in them and to close them.
+    pub fn venue_accounts(&self) -> Vec<Pubkey> {
+        let mut accounts = self.venue_token_accounts();
+        accounts.extend(self.venue_state_accounts());
+        if self.cpmm_lp_mint_pubkey != Pubkey::default() {
+            accounts.push(self.cpmm_lp_mint_pubkey);
+        }
+        accounts
+    }
+
+    /// Vaults and inventories of the trading venues and the bond vault, all held by the escrow
+    /// PDA
+    pub fn venue_token_accounts(&self) -> Vec<Pubkey> {
+        [
+            self.amm_vault_account_pubkey,
+            self.amm_long_token_account_pubkey,
+            self.amm_short_token_account_pubkey,
+            self.order_book_vault_account_pubkey,
+            self.cpmm_vault_account_pubkey,
+            self.cpmm_long_token_account_pubkey,
+            self.cpmm_short_token_account_pubkey,
+            self.bond_vault_account_pubkey,
+        ]
+        .iter()
+        .filter(|key| **key != Pubkey::default())
+        .copied()
+        .collect()
+    }
+
+    /// Program owned state of the order book, the constant product market maker, the resolver
+    /// and the committee
+    pub fn venue_state_accounts(&self) -> Vec<Pubkey> {
+        [
+            self.order_book_account_pubkey,
+            self.cpmm_account_pubkey,
+            self.resolver_account_pubkey,
+            self.committee_account_pubkey,
+        ]
+        .iter()
+        .filter(|key| **key != Pubkey::default())
+        .copied()
+        .collect()
+    }
+
+    /// Settled and voided pools are final, their tokens can only be collected
+    pub fn is_settled(&self) -> bool {
+        matches!(self.status, PoolStatus::Settled | PoolStatus::Voided)
+    }
+
+    /// Final state transition shared by every settlement path. `void_price` is only used when
+    /// the outcome is `Void`.
+    pub fn resolve(&mut self, outcome: Outcome, void_price: u64) {
+        match outcome {
+            Outcome::Long => {
+                self.winning_side_pubkey = self.long_mint_account_pubkey;
+                self.resolved_value = self.scalar_upper_bound;
+                self.status = PoolStatus::Settled;
+            }
+            Outcome::Short => {
+                self.winning_side_pubkey = self.short_mint_account_pubkey;
+                self.resolved_value = self.scalar_lower_bound;
+                self.status = PoolStatus::Settled;
+            }
+            Outcome::Void => {
+                self.winning_side_pubkey = VOID_SENTINEL;
+                self.void_price = void_price;
+                self.status = PoolStatus::Voided;
+            }
+        }
+    }
+
+    /// Settles a scalar market at `value`, which is clamped to the bounds on collection
+    pub fn resolve_scalar(&mut self, value: i64) {
+        self.resolved_value = value;
+        self.status = PoolStatus::Settled;
+    }
+
+    /// Payout of a long token of a scalar market, (value - lower) / (upper - lower) of 10^decimals
+    pub fn scalar_long_price(&self) -> u64 {
+        let unit = u64::pow(10, self.decimals as u32) as i128;
+        let (lower, upper) = (
+            self.scalar_lower_bound as i128,
+            self.scalar_upper_bound as i128,
+        );
+        let value = (self.resolved_value as i128).max(lower).min(upper);
+        ((value - lower) * unit / (upper - lower)) as u64
+    }
+
+    pub fn has_fees(&self) -> bool {
+        self.fee_vault_account_pubkey != Pubkey::default()
+    }
+
+    /// Protocol and creator fees on `amount`, both rounded up but never more than `amount`
+    pub fn fees(&self, amount: u64) -> (u64, u64) {
+        let protocol_fee = fee_amount(amount, self.protocol_fee_bps).min(amount);
+        let creator_fee = fee_amount(amount, self.creator_fee_bps).min(amount - protocol_fee);
+        (protocol_fee, creator_fee)
+    }
+
+    /// Records fees that were moved into the fee vault
+    pub fn accrue_fees(&mut self, (protocol_fee, creator_fee): (u64, u64)) -> ProgramResult {
+        self.protocol_fees_accrued = self
+            .protocol_fees_accrued
+            .checked_add(protocol_fee)
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        self.creator_fees_accrued = self
+            .creator_fees_accrued
+            .checked_add(creator_fee)
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn is_expired(&self, unix_timestamp: i64) -> bool {
+        self.expiry_unix_timestamp != 0 && unix_timestamp >= self.expiry_unix_timestamp
+    }
+
+    /// Latest publish time of a price that can settle an oracle pool. `ConfigureOracle` checks
+    /// that it doesn't overflow.
+    pub fn oracle_publish_deadline(&self) -> i64 {
+        self.expiry_unix_timestamp
+            .saturating_add(self.oracle_max_publish_delay)
+    }
+
+    /// Collateral owed to a holder of `long_amount` long and `short_amount` short tokens once
+    /// the pool is final, along with the decrease in circulation. `long_supply` and
+    /// `short_supply` are the outstanding supplies of the two mints before the collection.
+    ///
+    /// Winning tokens are paid a proportional stake of the escrow, rounded down. Tokens of a
+    /// voided pool or a scalar market are paid at a fixed price per side, which keeps the escrow
+    /// exactly covered (both sides have `circulation` tokens), so circulation only drops to 0 once
+    /// both sides are collected.
+    ///
+    /// Whoever collects the last outstanding tokens gets the remainder of the escrow. Rounding
+    /// down only ever leaves collateral behind for later collectors, so nobody is paid more than
+    /// one base unit less than their exact share, and no dust is left in the escrow.
+    pub fn payout(
+        &self,
+        long_amount: u64,
+        short_amount: u64,
+        long_supply: u64,
+        short_supply: u64,
+        escrow_amount: u64,
+    ) -> Result<(u64, u64), ProgramError> {
+        let unit = u64::pow(10, self.decimals as u32);
+        // Scalar and voided markets split the collateral of every complete set between the two
+        // sides at a fixed price
+        let split = |long_price: u64| {
+            if long_amount == long_supply && short_amount == short_supply {
+                return Ok((escrow_amount, self.circulation));
+            }
+            let amount = notional(long_amount, long_price)?
+                .checked_add(notional(short_amount, unit - long_price)?)
+                .ok_or(BinaryOptionError::MathOverflow)?;
+            Ok((amount, 0))
+        };
+        match self.status {
+            PoolStatus::Settled if self.market_type == MarketType::Scalar => {
+                split(self.scalar_long_price())
+            }
+            PoolStatus::Settled => {
+                let reward = if self.winning_side_pubkey == self.long_mint_account_pubkey {
+                    long_amount
+                } else if self.winning_side_pubkey == self.short_mint_account_pubkey {
+                    short_amount
+                } else {
+                    return Err(BinaryOptionError::TokenNotFoundInPool.into());
+                };
+                if reward == 0 {
+                    return Ok((0, 0));
+                }
+                if reward == self.circulation {
+                    return Ok((escrow_amount, reward));
+                }
+                let amount = pro_rata(
+                    escrow_amount as u128,
+                    reward as u128,
+                    self.circulation as u128,
+                )?;
+                Ok((amount, reward))
+            }
+            PoolStatus::Voided => split(self.void_price),
+            _ => Err(BinaryOptionError::BetNotSettled.into()),
+        }
+    }
+
+    pub fn increment_supply(&mut self, n: u64) -> ProgramResult {
+        self.circulation = self
+            .circulation
+            .checked_add(n)
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn decrement_supply(&mut self, n: u64) -> ProgramResult {
+        if self.circulation < n {
+            return Err(BinaryOptionError::InvalidSupply.into());
+        }
+        self.circulation -= n;
+        Ok(())
+    }
+
+    pub fn apply_supply_delta(&mut self, delta: i128) -> ProgramResult {
+        let n = u64::try_from(delta.unsigned_abs()).map_err(|_| BinaryOptionError::MathOverflow)?;
+        if delta >= 0 {
+            self.increment_supply(n)
+        } else {
+            self.decrement_supply(n)
+        }
+    }
+}
+
+/// Net change in circulation when `n` contracts trade between a buyer holding `n_b` short tokens
+/// and a seller holding `n_s` long tokens. The buyer's short tokens are burned before any long
+/// tokens are minted to them, and likewise for the seller, so both mints change by
+/// `(n - min(n, n_b)) - min(n, n_s)`. This covers all four cases of a trade.
+pub fn trade_supply_delta(n: u64, n_b: u64, n_s: u64) -> i128 {
+    n as i128 - n.min(n_b) as i128 - n.min(n_s) as i128
+}
+
+/// Token and collateral movements of a trade, along with the positions of both traders once it
+/// has settled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeEffects {
+    /// Short tokens burned from the buyer
+    pub buyer_burn: u64,
+    /// Long tokens burned from the seller
+    pub seller_burn: u64,
+    /// Long tokens minted to the buyer
+    pub buyer_mint: u64,
+    /// Short tokens minted to the seller
+    pub seller_mint: u64,
+    /// Collateral the buyer pays into the escrow for the minted long tokens
+    pub buyer_deposit: u64,
+    /// Collateral the seller pays into the escrow for the minted short tokens
+    pub seller_deposit: u64,
+    /// Collateral the escrow releases to the buyer for the burned short tokens
+    pub buyer_withdrawal: u64,
+    /// Collateral the escrow releases to the seller for the burned long tokens
+    pub seller_withdrawal: u64,
+    pub supply_delta: i128,
+    pub buyer_long: u64,
+    pub buyer_short: u64,
+    pub seller_long: u64,
+    pub seller_short: u64,
+}
+
+/// Computes a trade of `n` contracts between a buyer holding `b_l` long and `n_b` short tokens
+/// and a seller holding `n_s` long and `s_s` short tokens. Both traders close as much of their
+/// existing inventory as the trade allows, getting back the collateral locked up for it, and open
+/// a new position with the rest. This covers all four cases of a trade: the buyer's position
+/// changes by exactly `+n`, the seller's by `-n`, and the escrow by `supply_delta` complete sets.
+pub fn trade_effects(
+    n: u64,
+    n_b: u64,
+    n_s: u64,
+    b_l: u64,
+    s_s: u64,
+    buy_price: u64,
+    sell_price: u64,
+) -> Result<TradeEffects, ProgramError> {
+    let buyer_burn = n.min(n_b);
+    let seller_burn = n.min(n_s);
+    let buyer_mint = n - buyer_burn;
+    let seller_mint = n - seller_burn;
+    Ok(TradeEffects {
+        buyer_burn,
+        seller_burn,
+        buyer_mint,
+        seller_mint,
+        buyer_deposit: notional(buyer_mint, buy_price)?,
+        seller_deposit: notional(seller_mint, sell_price)?,
+        buyer_withdrawal: notional(buyer_burn, sell_price)?,
+        seller_withdrawal: notional(seller_burn, buy_price)?,
+        supply_delta: trade_supply_delta(n, n_b, n_s),
+        buyer_long: b_l
+            .checked_add(buyer_mint)
+            .ok_or(BinaryOptionError::MathOverflow)?,
+        buyer_short: n_b - buyer_burn,
+        seller_long: n_s - seller_burn,
+        seller_short: s_s
+            .checked_add(seller_mint)
+            .ok_or(BinaryOptionError::MathOverflow)?,
+    })
+}
+
+/// Lifecycle of a pool, `Open` until it is settled or voided
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum PoolStatus {
+    Open,
+    Settled,
+    /// Trading is frozen while the outcome is being determined
+    Halted,
+    Voided,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum Outcome {
+    Long,
+    Short,
+    Void,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum MarketType {
+    /// The winning side takes the whole escrow
+    Binary,
+    /// Long and short split the escrow linearly in the resolved value
+    Scalar,
+}
+
+/// Who is allowed to pick the winning side of a pool
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum ResolutionSource {
+    /// The pool owner calls `Settle`
+    Owner,
+    /// Anyone can call `SettleFromOracle` after expiry
+    Oracle,
+    /// Bonded proposals with a dispute window, see `OptimisticResolver`
+    Optimistic,
+    /// M-of-N voting, see `ResolverCommittee`
+    Committee,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum Comparison {
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+}
+
+impl Comparison {
+    pub fn holds(&self, price: i64, strike_price: i64) -> bool {
+        match self {
+            Comparison::GreaterThan => price > strike_price,
+            Comparison::GreaterThanOrEqual => price >= strike_price,
+            Comparison::LessThan => price < strike_price,
+            Comparison::LessThanOrEqual => price <= strike_price,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum PositionSide {
+    Long,
+    Short,
+}
+
+pub const MAX_ORDERS: usize = 32;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Default, Clone, Copy)]
+pub enum OrderSide {
+    /// Buy long tokens (go long)
+    #[default]
+    Bid,
+    /// Sell long tokens (go short)
+    Ask,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, Copy)]
+pub struct Order {
+    pub order_id: u64,
+    pub side: OrderSide,
+    pub owner: Pubkey,
+    pub collateral_account_pubkey: Pubkey,
+    pub long_token_account_pubkey: Pubkey,
+    pub short_token_account_pubkey: Pubkey,
+    /// Price of one long token denominated in the escrow mint
+    pub price: u64,
+    /// Remaining number of contracts, a slot with size 0 is empty
+    pub size: u64,
+    /// Pool fees escrowed in the order book vault for each remaining contract, 0 if the pool had
+    /// no fees when the order was placed
+    pub fee_per_contract: u64,
+}
+
+impl Order {
+    /// Collateral escrowed in the order book vault for each remaining contract
+    pub fn collateral_per_contract(&self, decimals: u8) -> u64 {
+        match self.side {
+            OrderSide::Bid => self.price,
+            OrderSide::Ask => u64::pow(10, decimals as u32) - self.price,
+        }
+    }
+
+    /// Collateral and fees escrowed in the order book vault for each remaining contract
+    pub fn escrow_per_contract(&self, decimals: u8) -> Result<u64, ProgramError> {
+        self.collateral_per_contract(decimals)
+            .checked_add(self.fee_per_contract)
+            .ok_or_else(|| BinaryOptionError::MathOverflow.into())
+    }
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct OrderBook {
+    pub binary_option_account_pubkey: Pubkey,
+    pub vault_account_pubkey: Pubkey,
+    pub next_order_id: u64,
+    pub orders: [Order; MAX_ORDERS],
+}
+
+impl OrderBook {
+    pub const LEN: usize = 5224;
+
+    pub fn from_account_info(a: &AccountInfo) -> Result<OrderBook, ProgramError> {
+        let order_book = OrderBook::try_from_slice(&a.data.borrow_mut())?;
+        Ok(order_book)
+    }
+
+    pub fn find_order(&self, order_id: u64) -> Result<usize, ProgramError> {
+        self.orders
+            .iter()
+            .position(|o| o.size > 0 && o.order_id == order_id)
+            .ok_or_else(|| BinaryOptionError::OrderNotFound.into())
+    }
+}
+
+/// A constant product market maker between long and short tokens, funded by liquidity providers
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct CpmmPool {
+    pub binary_option_account_pubkey: Pubkey,
+    pub lp_mint_pubkey: Pubkey,
+    /// Collateral that has not been turned into complete sets yet, including accrued fees
+    pub vault_account_pubkey: Pubkey,
+    pub long_token_account_pubkey: Pubkey,
+    pub short_token_account_pubkey: Pubkey,
+    pub fee_bps: u16,
+    /// Lifetime trading fees paid into the pool, they accrue to the liquidity providers
+    pub total_fees_accrued: u64,
+}
+
+impl CpmmPool {
+    pub const LEN: usize = 170;
+
+    pub fn from_account_info(a: &AccountInfo) -> Result<CpmmPool, ProgramError> {
+        let cpmm_pool = CpmmPool::try_from_slice(&a.data.borrow_mut())?;
+        Ok(cpmm_pool)
+    }
+}
+
+/// Settles a pool without a trusted owner: anyone can propose an outcome by posting a bond, which
+/// becomes final unless it is disputed (with a matching bond) before the dispute window ends.
+/// Disputes are resolved by the arbiter and both bonds go to the side that was right.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct OptimisticResolver {
+    pub binary_option_account_pubkey: Pubkey,
+    /// Holds the bonds, owned by the escrow PDA
+    pub bond_vault_account_pubkey: Pubkey,
+    pub arbiter: Pubkey,
+    pub bond: u64,
+    /// Length of the dispute window in seconds
+    pub dispute_window: i64,
+    /// Escrow mint account of the proposer, `Pubkey::default()` if there is no proposal
+    pub proposer_account_pubkey: Pubkey,
+    pub proposed_outcome: Outcome,
+    pub proposed_at: i64,
+    /// Escrow mint account of the disputer, `Pubkey::default()` if there is no dispute
+    pub disputer_account_pubkey: Pubkey,
+}
+
+impl OptimisticResolver {
+    pub const LEN: usize = 185;
+
+    pub fn from_account_info(a: &AccountInfo) -> Result<OptimisticResolver, ProgramError> {
+        let resolver = OptimisticResolver::try_from_slice(&a.data.borrow_mut())?;
+        Ok(resolver)
+    }
+
+    pub fn has_proposal(&self) -> bool {
+        self.proposer_account_pubkey != Pubkey::default()
+    }
+
+    pub fn is_disputed(&self) -> bool {
+        self.disputer_account_pubkey != Pubkey::default()
+    }
+
+    /// Unix timestamp at which the proposal can no longer be disputed
+    pub fn dispute_deadline(&self) -> Result<i64, ProgramError> {
+        self.proposed_at
+            .checked_add(self.dispute_window)
+            .ok_or_else(|| BinaryOptionError::MathOverflow.into())
+    }
+}
+
+pub const MAX_VOTERS: usize = 16;
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub enum Vote {
+    #[default]
+    None,
+    Long,
+    Short,
+    Void,
+}
+
+impl From<Outcome> for Vote {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Long => Vote::Long,
+            Outcome::Short => Vote::Short,
+            Outcome::Void => Vote::Void,
+        }
+    }
+}
+
+/// Settles a pool once `threshold` of its voters agree on an outcome
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ResolverCommittee {
+    pub binary_option_account_pubkey: Pubkey,
+    pub threshold: u8,
+    pub voter_count: u8,
+    pub voters: [Pubkey; MAX_VOTERS],
+    /// `votes[i]` is the current vote of `voters[i]`
+    pub votes: [Vote; MAX_VOTERS],
+}
+
+impl ResolverCommittee {
+    pub const LEN: usize = 562;
+
+    pub fn from_account_info(a: &AccountInfo) -> Result<ResolverCommittee, ProgramError> {
+        let committee = ResolverCommittee::try_from_slice(&a.data.borrow_mut())?;
+        Ok(committee)
+    }
+
+    pub fn find_voter(&self, voter: &Pubkey) -> Result<usize, ProgramError> {
+        self.voters[..self.voter_count as usize]
+            .iter()
+            .position(|v| v == voter)
+            .ok_or_else(|| BinaryOptionError::NotAVoter.into())
+    }
+
+    pub fn tally(&self, vote: Vote) -> usize {
+        self.votes[..self.voter_count as usize]
+            .iter()
+            .filter(|v| **v == vote)
+            .count()
+    }
+}
+
+pub const MAX_OUTCOMES: usize = 16;
+
+/// A market with up to `MAX_OUTCOMES` mutually exclusive outcomes. A complete set (one token of
+/// every outcome) is backed by 10^decimals of the escrow mint, and holders of the winning outcome
+/// share the escrow after settlement. The mints and the escrow are owned by the PDA derived from
+/// the market account.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct CategoricalMarket {
+    pub decimals: u8,
+    pub circulation: u64,
+    pub status: PoolStatus,
+    pub escrow_mint_account_pubkey: Pubkey,
+    pub escrow_account_pubkey: Pubkey,
+    pub owner: Pubkey,
+    pub outcome_count: u8,
+    pub outcome_mints: [Pubkey; MAX_OUTCOMES],
+    pub winning_outcome: u8,
+    /// Trading stops at this unix timestamp, 0 if the market never expires
+    pub expiry_unix_timestamp: i64,
+}
+
+impl CategoricalMarket {
+    pub const LEN: usize = 628;
+
+    pub fn from_account_info(a: &AccountInfo) -> Result<CategoricalMarket, ProgramError> {
+        let market = CategoricalMarket::try_from_slice(&a.data.borrow_mut())?;
+        Ok(market)
+    }
+
+    pub fn outcome_mints(&self) -> &[Pubkey] {
+        &self.outcome_mints[..self.outcome_count as usize]
+    }
+
+    pub fn is_expired(&self, unix_timestamp: i64) -> bool {
+        self.expiry_unix_timestamp != 0 && unix_timestamp >= self.expiry_unix_timestamp
+    }
+
+    /// Escrow tokens paid out for `reward` winning tokens. Like `BinaryOption::payout`, whoever
+    /// collects the last outstanding winning tokens gets the remainder of the escrow.
+    pub fn payout(&self, reward: u64, escrow_amount: u64) -> Result<u64, ProgramError> {
+        if reward == self.circulation {
+            return Ok(escrow_amount);
+        }
+        pro_rata(
+            escrow_amount as u128,
+            reward as u128,
+            self.circulation as u128,
+        )
+    }
+
+    pub fn increment_supply(&mut self, n: u64) -> ProgramResult {
+        self.circulation = self
+            .circulation
+            .checked_add(n)
+            .ok_or(BinaryOptionError::MathOverflow)?;
+        Ok(())
     }
 
     pub fn decrement_supply(&mut self, n: u64) -> ProgramResult {
@@ -40,3 +733,328 @@ impl BinaryOption {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const DECIMALS: u8 = 2;
+    const UNIT: u64 = 100;
+
+    fn pool(status: PoolStatus, circulation: u64) -> BinaryOption {
+        let mut pool = BinaryOption::try_from_slice(&[0; BinaryOption::LEN]).unwrap();
+        pool.decimals = DECIMALS;
+        pool.circulation = circulation;
+        pool.status = status;
+        pool.long_mint_account_pubkey = Pubkey::new_unique();
+        pool.short_mint_account_pubkey = Pubkey::new_unique();
+        pool.winning_side_pubkey = pool.long_mint_account_pubkey;
+        pool
+    }
+
+    /// xorshift, so the cases are reproducible without a dependency
+    fn next(seed: &mut u64) -> u64 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        *seed
+    }
+
+    /// Splits `total` tokens between `holders` holders, some of whom may hold nothing
+    fn holdings(seed: &mut u64, holders: usize, total: u64) -> Vec<u64> {
+        let mut amounts = vec![0; holders];
+        for _ in 0..total {
+            amounts[(next(seed) % holders as u64) as usize] += 1;
+        }
+        amounts
+    }
+
+    /// Collects every holding in order, checking each payout against its exact share of the
+    /// escrow. Returns what is left in the escrow.
+    fn collect_all(pool: &mut BinaryOption, longs: &[u64], shorts: &[u64], escrow: u64) -> u64 {
+        let mut long_supply: u64 = longs.iter().sum();
+        let mut short_supply: u64 = shorts.iter().sum();
+        let circulation = pool.circulation;
+        let mut remaining = escrow;
+        for (&long_amount, &short_amount) in longs.iter().zip(shorts) {
+            let (amount, decrement) = pool
+                .payout(
+                    long_amount,
+                    short_amount,
+                    long_supply,
+                    short_supply,
+                    remaining,
+                )
+                .unwrap();
+            // The exact share as a fraction
+            let (numerator, denominator) = match pool.status {
+                PoolStatus::Voided => (
+                    long_amount * pool.void_price + short_amount * (UNIT - pool.void_price),
+                    1,
+                ),
+                _ => (long_amount * escrow, circulation),
+            };
+            assert!(
+                (amount + 1) * denominator > numerator,
+                "paid {} of an exact share of {}/{}",
+                amount,
+                numerator,
+                denominator
+            );
+            remaining -= amount;
+            long_supply -= long_amount;
+            short_supply -= short_amount;
+            pool.decrement_supply(decrement).unwrap();
+        }
+        remaining
+    }
+
+    #[test]
+    fn test_settled_collection_leaves_no_dust() {
+        let mut seed = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..500 {
+            let circulation = 1 + next(&mut seed) % 200;
+            let holders = 1 + (next(&mut seed) % 10) as usize;
+            let longs = holdings(&mut seed, holders, circulation);
+            let shorts = holdings(&mut seed, holders, circulation);
+            // Collateral sent straight to the escrow makes the shares fractional
+            let escrow = circulation * UNIT + next(&mut seed) % (2 * circulation);
+            let mut pool = pool(PoolStatus::Settled, circulation);
+            assert_eq!(collect_all(&mut pool, &longs, &shorts, escrow), 0);
+            assert_eq!(pool.circulation, 0);
+        }
+    }
+
+    #[test]
+    fn test_voided_collection_leaves_no_dust() {
+        let mut seed = 0x9e37_79b9_7f4a_7c15;
+        for _ in 0..500 {
+            let circulation = 1 + next(&mut seed) % 200;
+            let holders = 1 + (next(&mut seed) % 10) as usize;
+            let longs = holdings(&mut seed, holders, circulation);
+            let shorts = holdings(&mut seed, holders, circulation);
+            let mut pool = pool(PoolStatus::Voided, circulation);
+            pool.void_price = next(&mut seed) % (UNIT + 1);
+            assert_eq!(
+                collect_all(&mut pool, &longs, &shorts, circulation * UNIT),
+                0
+            );
+            assert_eq!(pool.circulation, 0);
+        }
+    }
+
+    #[test]
+    fn test_losing_tokens_pay_nothing() {
+        let pool = pool(PoolStatus::Settled, 10);
+        assert_eq!(pool.payout(0, 10, 10, 10, 10 * UNIT).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn test_last_collector_gets_remainder() {
+        // 3 winners share 10 base units: 3, 3 and then the remaining 4
+        let mut pool = pool(PoolStatus::Settled, 3);
+        assert_eq!(pool.payout(1, 0, 3, 0, 10).unwrap(), (3, 1));
+        pool.decrement_supply(1).unwrap();
+        assert_eq!(pool.payout(1, 0, 2, 0, 7).unwrap(), (3, 1));
+        pool.decrement_supply(1).unwrap();
+        assert_eq!(pool.payout(1, 0, 1, 0, 4).unwrap(), (4, 1));
+    }
+
+    #[test]
+    fn test_categorical_last_collector_gets_remainder() {
+        // 3 winning tokens share 10 base units: 3, 3 and then the remaining 4
+        let mut market = CategoricalMarket::try_from_slice(&[0; CategoricalMarket::LEN]).unwrap();
+        market.circulation = 3;
+        assert_eq!(market.payout(1, 10).unwrap(), 3);
+        market.decrement_supply(1).unwrap();
+        assert_eq!(market.payout(1, 7).unwrap(), 3);
+        market.decrement_supply(1).unwrap();
+        assert_eq!(market.payout(1, 4).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_dispute_deadline_overflow() {
+        let mut resolver =
+            OptimisticResolver::try_from_slice(&[0; OptimisticResolver::LEN]).unwrap();
+        resolver.proposed_at = 1_000;
+        resolver.dispute_window = 3_600;
+        assert_eq!(resolver.dispute_deadline().unwrap(), 4_600);
+        resolver.dispute_window = i64::MAX;
+        assert_eq!(
+            resolver.dispute_deadline(),
+            Err(BinaryOptionError::MathOverflow.into())
+        );
+    }
+
+    /// Token balances of the two traders and the rest of the market
+    #[derive(Clone, Copy, Debug)]
+    struct Positions {
+        buyer_long: u64,
+        buyer_short: u64,
+        seller_long: u64,
+        seller_short: u64,
+        others: u64,
+    }
+
+    impl Positions {
+        fn long_supply(&self) -> u64 {
+            self.buyer_long + self.seller_long + self.others
+        }
+
+        fn short_supply(&self) -> u64 {
+            self.buyer_short + self.seller_short + self.others
+        }
+    }
+
+    /// Burns and mints like the four cases of a trade, written out separately from `trade_effects`
+    fn trade(p: &mut Positions, n: u64) {
+        let (n_b, n_s) = (p.buyer_short, p.seller_long);
+        match [n_b >= n, n_s >= n] {
+            [true, true] => {
+                p.buyer_short -= n;
+                p.seller_long -= n;
+            }
+            [false, false] => {
+                p.buyer_short -= n_b;
+                p.seller_long -= n_s;
+                p.buyer_long += n - n_b;
+                p.seller_short += n - n_s;
+            }
+            [true, false] => {
+                p.buyer_short -= n;
+                p.seller_long -= n_s;
+                p.seller_short += n - n_s;
+            }
+            [false, true] => {
+                p.buyer_short -= n_b;
+                p.seller_long -= n;
+                p.buyer_long += n - n_b;
+            }
+        }
+    }
+
+    #[test]
+    fn test_trade_supply_delta_matches_mint_supply() {
+        // Every ordering of n, n_b and n_s, including equalities and zeros
+        for n in 0..=6 {
+            for n_b in 0..=6 {
+                for n_s in 0..=6 {
+                    for (buyer_long, seller_short, others) in [(0, 0, 0), (2, 3, 1), (5, 0, 4)] {
+                        let mut p = Positions {
+                            buyer_long,
+                            buyer_short: n_b,
+                            seller_long: n_s,
+                            seller_short,
+                            others,
+                        };
+                        // Balance the two mints, so both match the circulation
+                        let (long_supply, short_supply) = (p.long_supply(), p.short_supply());
+                        p.buyer_long += short_supply.saturating_sub(long_supply);
+                        p.seller_short += long_supply.saturating_sub(short_supply);
+
+                        let mut pool = pool(PoolStatus::Open, p.long_supply());
+                        let before = p;
+                        trade(&mut p, n);
+                        pool.apply_supply_delta(trade_supply_delta(n, n_b, n_s))
+                            .unwrap();
+
+                        assert_eq!(pool.circulation, p.long_supply(), "{:?}", before);
+                        assert_eq!(pool.circulation, p.short_supply(), "{:?}", before);
+                        // The buyer gains exactly n contracts and the seller gives up n
+                        assert_eq!(
+                            p.buyer_long as i64 - p.buyer_short as i64,
+                            before.buyer_long as i64 - before.buyer_short as i64 + n as i64
+                        );
+                        assert_eq!(
+                            p.seller_long as i64 - p.seller_short as i64,
+                            before.seller_long as i64 - before.seller_short as i64 - n as i64
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_trade_supply_delta_nets_out_positions() {
+        // Case 2 with n < n_b + n_s used to underflow
+        assert_eq!(trade_supply_delta(5, 3, 4), -2);
+        assert_eq!(trade_supply_delta(5, 3, 2), 0);
+        assert_eq!(trade_supply_delta(5, 1, 1), 3);
+        assert_eq!(trade_supply_delta(5, 5, 5), -5);
+        assert_eq!(trade_supply_delta(5, 9, 2), -2);
+        assert_eq!(trade_supply_delta(5, 2, 9), -2);
+        assert_eq!(trade_supply_delta(u64::MAX, 0, 0), u64::MAX as i128);
+    }
+
+    proptest! {
+        #[test]
+        fn test_trade_effects_conserve_positions_and_collateral(
+            decimals in 0u8..=9,
+            price in any::<u64>(),
+            n in 0u64..1_000_000,
+            b_l in 0u64..1_000_000,
+            n_b in 0u64..1_000_000,
+            n_s in 0u64..1_000_000,
+            s_s in 0u64..1_000_000,
+        ) {
+            let unit = 10u64.pow(decimals as u32);
+            let buy_price = price % (unit + 1);
+            let sell_price = unit - buy_price;
+            // The rest of the market holds enough tokens to balance both mints
+            let circulation = (b_l + n_s).max(n_b + s_s);
+            let other_long = circulation - b_l - n_s;
+            let other_short = circulation - n_b - s_s;
+            let mut pool = pool(PoolStatus::Open, circulation);
+
+            let effects = trade_effects(n, n_b, n_s, b_l, s_s, buy_price, sell_price).unwrap();
+            pool.apply_supply_delta(effects.supply_delta).unwrap();
+
+            // The buyer gains exactly n contracts and the seller gives up n
+            prop_assert_eq!(
+                effects.buyer_long as i128 - effects.buyer_short as i128,
+                b_l as i128 - n_b as i128 + n as i128
+            );
+            prop_assert_eq!(
+                effects.seller_long as i128 - effects.seller_short as i128,
+                n_s as i128 - s_s as i128 - n as i128
+            );
+            // Burns and mints account for the new positions
+            prop_assert_eq!(effects.buyer_long, b_l + effects.buyer_mint);
+            prop_assert_eq!(effects.buyer_short, n_b - effects.buyer_burn);
+            prop_assert_eq!(effects.seller_long, n_s - effects.seller_burn);
+            prop_assert_eq!(effects.seller_short, s_s + effects.seller_mint);
+            prop_assert_eq!(
+                other_long + effects.buyer_long + effects.seller_long,
+                pool.circulation
+            );
+            prop_assert_eq!(
+                other_short + effects.buyer_short + effects.seller_short,
+                pool.circulation
+            );
+            // Deposits are made before withdrawals, and the escrow stays fully collateralized
+            let escrow = (circulation * unit + effects.buyer_deposit + effects.seller_deposit)
+                .checked_sub(effects.buyer_withdrawal + effects.seller_withdrawal)
+                .unwrap();
+            prop_assert_eq!(escrow, pool.circulation * unit);
+        }
+
+        #[test]
+        fn test_trade_effects_never_panic(
+            n in any::<u64>(),
+            n_b in any::<u64>(),
+            n_s in any::<u64>(),
+            b_l in any::<u64>(),
+            s_s in any::<u64>(),
+            buy_price in any::<u64>(),
+            sell_price in any::<u64>(),
+        ) {
+            // Overflows are errors
+            if let Ok(effects) = trade_effects(n, n_b, n_s, b_l, s_s, buy_price, sell_price) {
+                prop_assert_eq!(effects.buyer_burn + effects.buyer_mint, n);
+                prop_assert_eq!(effects.seller_burn + effects.seller_mint, n);
+            }
+        }
+    }
+}
@@ -4,6 +4,7 @@ use {
         entrypoint::ProgramResult,
         msg,
         program::invoke,
+        program_error::ProgramError,
         pubkey::Pubkey,
         system_instruction,
         sysvar::{rent::Rent, Sysvar},
@@ -97,3 +98,20 @@ pub fn create_or_allocate_account_raw<'a>(
 
     Ok(())
 }
+
+/// Closes an account owned by this program, moving all of its lamports to `destination_info`
+#[inline(always)]
+pub fn close_account<'a>(
+    account_info: &AccountInfo<'a>,
+    destination_info: &AccountInfo<'a>,
+) -> ProgramResult {
+    let lamports = account_info.lamports();
+    msg!("Transfer {} lamports from the closed account", lamports);
+    **destination_info.lamports.borrow_mut() = destination_info
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::InvalidArgument)?;
+    **account_info.lamports.borrow_mut() = 0;
+    account_info.data.borrow_mut().fill(0);
+    Ok(())
+}
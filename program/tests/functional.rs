@@ -0,0 +1,4278 @@
+use {
+    binary_option::{
+        error::BinaryOptionError,
+        id,
+        instruction::{self, CollectAccounts},
+        oracle::{
+            OraclePrice, PYTH_ACCOUNT_TYPE_PRICE, PYTH_MAGIC, PYTH_PROGRAM_ID, PYTH_STATUS_TRADING,
+            PYTH_VERSION_2,
+        },
+        processor::Processor,
+        state::{
+            BinaryOption, Comparison, CpmmPool, OrderBook, OrderSide, Outcome, PoolStatus,
+            PositionSide, MAX_FEE_BPS, ORACLE_VOID_GRACE_PERIOD,
+        },
+    },
+    borsh::BorshDeserialize,
+    solana_program::{
+        clock::Clock,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_instruction,
+    },
+    solana_program_test::{
+        processor, BanksClient, ProgramTest, ProgramTestBanksClientExt, ProgramTestContext,
+    },
+    solana_sdk::{
+        account::Account as SolanaAccount,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_associated_token_account::{
+        get_associated_token_address, instruction::create_associated_token_account,
+    },
+    spl_token::state::{Account, Mint},
+};
+
+const DECIMALS: u8 = 2;
+const UNIT: u64 = 100;
+
+fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new("binary_option", id(), processor!(Processor::process));
+    // `cargo test --features test-bpf` runs the suite against the compiled program instead
+    program_test.prefer_bpf(cfg!(feature = "test-bpf"));
+    program_test
+}
+
+async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) {
+    try_process(context, instructions, signers).await.unwrap();
+}
+
+async fn try_process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), TransactionError> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend(signers);
+    let blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+    context.last_blockhash = blockhash;
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|error| error.unwrap())
+}
+
+async fn create_account(
+    context: &mut ProgramTestContext,
+    account: &Keypair,
+    space: usize,
+    initialize: Instruction,
+) {
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let create = system_instruction::create_account(
+        &context.payer.pubkey(),
+        &account.pubkey(),
+        rent.minimum_balance(space),
+        space as u64,
+        &spl_token::id(),
+    );
+    process(context, &[create, initialize], &[account]).await;
+}
+
+async fn create_mint(context: &mut ProgramTestContext, mint: &Keypair, authority: &Pubkey) {
+    let initialize = spl_token::instruction::initialize_mint(
+        &spl_token::id(),
+        &mint.pubkey(),
+        authority,
+        None,
+        0,
+    )
+    .unwrap();
+    create_account(context, mint, Mint::LEN, initialize).await;
+}
+
+async fn create_token_account(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    let account = Keypair::new();
+    let initialize = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &account.pubkey(),
+        mint,
+        owner,
+    )
+    .unwrap();
+    create_account(context, &account, Account::LEN, initialize).await;
+    account.pubkey()
+}
+
+async fn token_balance(banks_client: &mut BanksClient, address: &Pubkey) -> u64 {
+    let account = banks_client.get_account(*address).await.unwrap().unwrap();
+    Account::unpack(&account.data).unwrap().amount
+}
+
+/// Number of tokens the delegate of a token account is allowed to move
+async fn allowance(banks_client: &mut BanksClient, address: &Pubkey) -> u64 {
+    let account = banks_client.get_account(*address).await.unwrap().unwrap();
+    Account::unpack(&account.data).unwrap().delegated_amount
+}
+
+async fn mint_supply(banks_client: &mut BanksClient, address: &Pubkey) -> u64 {
+    let account = banks_client.get_account(*address).await.unwrap().unwrap();
+    Mint::unpack(&account.data).unwrap().supply
+}
+
+/// A pool denominated in a fresh collateral mint
+struct Pool {
+    address: Pubkey,
+    owner: Keypair,
+    escrow_mint: Pubkey,
+    escrow_mint_authority: Keypair,
+    escrow: Pubkey,
+    long_mint: Pubkey,
+    short_mint: Pubkey,
+    escrow_authority: Pubkey,
+    fee_vault: Option<Pubkey>,
+}
+
+/// A wallet with a collateral account (its associated token account) and token accounts for both sides of a pool
+struct Trader {
+    wallet: Keypair,
+    collateral: Pubkey,
+    long: Pubkey,
+    short: Pubkey,
+}
+
+/// Balances of a trader after a step, in collateral and contracts
+#[derive(Debug, PartialEq)]
+struct Position {
+    collateral: u64,
+    long: u64,
+    short: u64,
+}
+
+impl Pool {
+    async fn initialize(context: &mut ProgramTestContext) -> Self {
+        Self::initialize_with_expiry(context, 0).await
+    }
+
+    async fn initialize_with_expiry(context: &mut ProgramTestContext, expiry: i64) -> Self {
+        let owner = Keypair::new();
+        let escrow_mint = Keypair::new();
+        let escrow_mint_authority = Keypair::new();
+        create_mint(context, &escrow_mint, &escrow_mint_authority.pubkey()).await;
+        process(
+            context,
+            &[system_instruction::transfer(
+                &context.payer.pubkey(),
+                &owner.pubkey(),
+                1_000_000_000,
+            )],
+            &[],
+        )
+        .await;
+
+        let pool = Keypair::new();
+        let escrow = Keypair::new();
+        let long_mint = Keypair::new();
+        let short_mint = Keypair::new();
+        process(
+            context,
+            &[instruction::initialize_binary_option(
+                id(),
+                pool.pubkey(),
+                escrow_mint.pubkey(),
+                escrow.pubkey(),
+                long_mint.pubkey(),
+                short_mint.pubkey(),
+                owner.pubkey(),
+                owner.pubkey(),
+                DECIMALS,
+                expiry,
+            )],
+            &[&pool, &escrow, &long_mint, &short_mint, &owner],
+        )
+        .await;
+
+        let (escrow_authority, _) = Pubkey::find_program_address(
+            &[
+                long_mint.pubkey().as_ref(),
+                short_mint.pubkey().as_ref(),
+                spl_token::id().as_ref(),
+                id().as_ref(),
+            ],
+            &id(),
+        );
+        Self {
+            address: pool.pubkey(),
+            owner,
+            escrow_mint: escrow_mint.pubkey(),
+            escrow_mint_authority,
+            escrow: escrow.pubkey(),
+            long_mint: long_mint.pubkey(),
+            short_mint: short_mint.pubkey(),
+            escrow_authority,
+            fee_vault: None,
+        }
+    }
+
+    /// Charges fees on trades and collections, both paid to the owner
+    async fn configure_fees(&mut self, context: &mut ProgramTestContext) {
+        let fee_vault = Keypair::new();
+        let owner = self.owner.pubkey();
+        process(
+            context,
+            &[self.configure_fees_instruction(fee_vault.pubkey(), owner, owner, 100, 100)],
+            &[&fee_vault, &self.owner],
+        )
+        .await;
+        self.fee_vault = Some(fee_vault.pubkey());
+    }
+
+    /// Fees of a pool whose creator fees go to its owner
+    fn configure_fees_instruction(
+        &self,
+        fee_vault: Pubkey,
+        pool_owner: Pubkey,
+        protocol_fee_recipient: Pubkey,
+        protocol_fee_bps: u16,
+        creator_fee_bps: u16,
+    ) -> Instruction {
+        instruction::configure_fees(
+            id(),
+            self.address,
+            self.escrow_mint,
+            fee_vault,
+            self.long_mint,
+            self.short_mint,
+            pool_owner,
+            protocol_fee_recipient,
+            self.owner.pubkey(),
+            self.escrow_authority,
+            protocol_fee_bps,
+            creator_fee_bps,
+        )
+    }
+
+    fn withdraw_fees(&self, recipient: Pubkey, recipient_account: Pubkey) -> Instruction {
+        instruction::withdraw_fees(
+            id(),
+            self.address,
+            self.fee_vault.unwrap_or_default(),
+            self.long_mint,
+            self.short_mint,
+            recipient,
+            recipient_account,
+            self.escrow_authority,
+        )
+    }
+
+    async fn state(&self, banks_client: &mut BanksClient) -> BinaryOption {
+        let account = banks_client
+            .get_account(self.address)
+            .await
+            .unwrap()
+            .unwrap();
+        BinaryOption::try_from_slice(&account.data).unwrap()
+    }
+
+    /// Creates a trader funded with `collateral`
+    async fn trader(&self, context: &mut ProgramTestContext, collateral: u64) -> Trader {
+        let wallet = Keypair::new();
+        let owner = wallet.pubkey();
+        let associated_collateral = get_associated_token_address(&owner, &self.escrow_mint);
+        process(
+            context,
+            &[create_associated_token_account(
+                &context.payer.pubkey(),
+                &owner,
+                &self.escrow_mint,
+                &spl_token::id(),
+            )],
+            &[],
+        )
+        .await;
+        self.mint_collateral(context, &associated_collateral, collateral)
+            .await;
+        Trader {
+            collateral: associated_collateral,
+            long: create_token_account(context, &self.long_mint, &owner).await,
+            short: create_token_account(context, &self.short_mint, &owner).await,
+            wallet,
+        }
+    }
+
+    /// Creates a collateral account of `owner` holding `amount`
+    async fn collateral_account(
+        &self,
+        context: &mut ProgramTestContext,
+        owner: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        let account = create_token_account(context, &self.escrow_mint, owner).await;
+        self.mint_collateral(context, &account, amount).await;
+        account
+    }
+
+    async fn mint_collateral(
+        &self,
+        context: &mut ProgramTestContext,
+        account: &Pubkey,
+        amount: u64,
+    ) {
+        if amount > 0 {
+            process(
+                context,
+                &[spl_token::instruction::mint_to(
+                    &spl_token::id(),
+                    &self.escrow_mint,
+                    account,
+                    &self.escrow_mint_authority.pubkey(),
+                    &[],
+                    amount,
+                )
+                .unwrap()],
+                &[&self.escrow_mint_authority],
+            )
+            .await;
+        }
+    }
+
+    async fn trade(
+        &self,
+        context: &mut ProgramTestContext,
+        buyer: &Trader,
+        seller: &Trader,
+        size: u64,
+        buy_price: u64,
+    ) {
+        process(
+            context,
+            &[instruction::trade(
+                id(),
+                self.address,
+                self.escrow,
+                self.long_mint,
+                self.short_mint,
+                buyer.wallet.pubkey(),
+                seller.wallet.pubkey(),
+                buyer.collateral,
+                seller.collateral,
+                buyer.long,
+                buyer.short,
+                seller.long,
+                seller.short,
+                self.escrow_authority,
+                self.fee_vault,
+                size,
+                buy_price,
+                UNIT - buy_price,
+            )],
+            &[&buyer.wallet, &seller.wallet],
+        )
+        .await;
+    }
+
+    fn mint_set(&self, trader: &Trader, size: u64) -> Instruction {
+        instruction::mint_set(
+            id(),
+            self.address,
+            self.escrow,
+            self.long_mint,
+            self.short_mint,
+            trader.wallet.pubkey(),
+            trader.collateral,
+            trader.long,
+            trader.short,
+            self.escrow_authority,
+            size,
+        )
+    }
+
+    fn redeem_set(&self, trader: &Trader, size: u64) -> Instruction {
+        instruction::redeem_set(
+            id(),
+            self.address,
+            self.escrow,
+            self.long_mint,
+            self.short_mint,
+            trader.wallet.pubkey(),
+            trader.collateral,
+            trader.long,
+            trader.short,
+            self.escrow_authority,
+            size,
+        )
+    }
+
+    async fn settle(&self, context: &mut ProgramTestContext, winning_mint: Pubkey) {
+        process(
+            context,
+            &[instruction::settle(
+                id(),
+                self.address,
+                winning_mint,
+                self.owner.pubkey(),
+            )],
+            &[&self.owner],
+        )
+        .await;
+    }
+
+    async fn collect(&self, context: &mut ProgramTestContext, collector: &Trader) {
+        process(
+            context,
+            &[instruction::collect(
+                id(),
+                self.address,
+                collector.wallet.pubkey(),
+                collector.long,
+                collector.short,
+                collector.collateral,
+                self.long_mint,
+                self.short_mint,
+                self.escrow,
+                self.escrow_authority,
+                self.fee_vault,
+            )],
+            &[],
+        )
+        .await;
+    }
+
+    async fn position(&self, banks_client: &mut BanksClient, trader: &Trader) -> Position {
+        Position {
+            collateral: token_balance(banks_client, &trader.collateral).await,
+            long: token_balance(banks_client, &trader.long).await,
+            short: token_balance(banks_client, &trader.short).await,
+        }
+    }
+
+    /// Checks the circulation of the pool, and that it is backed by the outstanding tokens and
+    /// the escrow
+    async fn assert_circulation(&self, banks_client: &mut BanksClient, circulation: u64) {
+        let state = self.state(banks_client).await;
+        assert_eq!(state.circulation, circulation);
+        if state.is_settled() {
+            // Losing tokens are burned without changing the circulation
+            assert_eq!(
+                mint_supply(banks_client, &state.winning_side_pubkey).await,
+                circulation
+            );
+        } else {
+            assert_eq!(
+                mint_supply(banks_client, &self.long_mint).await,
+                circulation
+            );
+            assert_eq!(
+                mint_supply(banks_client, &self.short_mint).await,
+                circulation
+            );
+        }
+        assert_eq!(
+            token_balance(banks_client, &self.escrow).await,
+            circulation * UNIT
+        );
+    }
+}
+
+/// An order book of a pool and the vault that holds the collateral of its orders
+struct OrderBookAccounts {
+    address: Pubkey,
+    vault: Pubkey,
+}
+
+impl OrderBookAccounts {
+    async fn orders(&self, banks_client: &mut BanksClient) -> OrderBook {
+        let account = banks_client
+            .get_account(self.address)
+            .await
+            .unwrap()
+            .unwrap();
+        OrderBook::try_from_slice(&account.data).unwrap()
+    }
+}
+
+impl Pool {
+    async fn initialize_order_book(&self, context: &mut ProgramTestContext) -> OrderBookAccounts {
+        let order_book = Keypair::new();
+        let vault = Keypair::new();
+        process(
+            context,
+            &[instruction::initialize_order_book(
+                id(),
+                self.address,
+                order_book.pubkey(),
+                vault.pubkey(),
+                self.escrow_mint,
+                self.escrow_authority,
+                self.owner.pubkey(),
+            )],
+            &[&order_book, &vault, &self.owner],
+        )
+        .await;
+        OrderBookAccounts {
+            address: order_book.pubkey(),
+            vault: vault.pubkey(),
+        }
+    }
+
+    fn place_order(
+        &self,
+        book: &OrderBookAccounts,
+        trader: &Trader,
+        side: OrderSide,
+        price: u64,
+        size: u64,
+    ) -> Instruction {
+        instruction::place_order(
+            id(),
+            self.address,
+            book.address,
+            book.vault,
+            self.long_mint,
+            self.short_mint,
+            trader.wallet.pubkey(),
+            trader.collateral,
+            trader.long,
+            trader.short,
+            self.escrow_authority,
+            side,
+            price,
+            size,
+        )
+    }
+
+    fn cancel_order(
+        &self,
+        book: &OrderBookAccounts,
+        trader: &Trader,
+        order_id: u64,
+    ) -> Instruction {
+        instruction::cancel_order(
+            id(),
+            self.address,
+            book.address,
+            book.vault,
+            self.long_mint,
+            self.short_mint,
+            trader.wallet.pubkey(),
+            trader.collateral,
+            trader.long,
+            trader.short,
+            self.escrow_authority,
+            order_id,
+        )
+    }
+
+    fn match_orders(
+        &self,
+        book: &OrderBookAccounts,
+        buyer: &Trader,
+        seller: &Trader,
+        bid_order_id: u64,
+        ask_order_id: u64,
+    ) -> Instruction {
+        instruction::match_orders(
+            id(),
+            self.address,
+            book.address,
+            book.vault,
+            self.escrow,
+            self.long_mint,
+            self.short_mint,
+            buyer.collateral,
+            buyer.long,
+            buyer.short,
+            seller.collateral,
+            seller.long,
+            seller.short,
+            self.escrow_authority,
+            bid_order_id,
+            ask_order_id,
+            self.fee_vault,
+        )
+    }
+}
+
+/// The collateral vault and the inventory of a pool's LMSR market maker
+struct AmmAccounts {
+    vault: Keypair,
+    long: Keypair,
+    short: Keypair,
+}
+
+impl AmmAccounts {
+    fn new() -> Self {
+        Self {
+            vault: Keypair::new(),
+            long: Keypair::new(),
+            short: Keypair::new(),
+        }
+    }
+}
+
+impl Pool {
+    fn initialize_amm(
+        &self,
+        amm: &AmmAccounts,
+        owner: Pubkey,
+        owner_account: Pubkey,
+        liquidity: u64,
+        subsidy: u64,
+    ) -> Instruction {
+        instruction::initialize_amm(
+            id(),
+            self.address,
+            self.escrow_mint,
+            amm.vault.pubkey(),
+            amm.long.pubkey(),
+            amm.short.pubkey(),
+            self.long_mint,
+            self.short_mint,
+            owner,
+            owner_account,
+            self.escrow_authority,
+            liquidity,
+            subsidy,
+        )
+    }
+
+    /// Buys or sells `size` tokens of `side` at a cost of at most, or for proceeds of at least,
+    /// `collateral_limit`
+    fn amm_trade(
+        &self,
+        amm: &AmmAccounts,
+        buy: bool,
+        trader: &Trader,
+        side: PositionSide,
+        size: u64,
+        collateral_limit: u64,
+    ) -> Instruction {
+        let build = if buy {
+            instruction::amm_buy
+        } else {
+            instruction::amm_sell
+        };
+        build(
+            id(),
+            self.address,
+            self.escrow,
+            self.long_mint,
+            self.short_mint,
+            amm.vault.pubkey(),
+            amm.long.pubkey(),
+            amm.short.pubkey(),
+            trader.wallet.pubkey(),
+            trader.collateral,
+            match side {
+                PositionSide::Long => trader.long,
+                PositionSide::Short => trader.short,
+            },
+            self.escrow_authority,
+            self.fee_vault,
+            side,
+            size,
+            collateral_limit,
+        )
+    }
+
+    fn amm_withdraw(&self, amm: &AmmAccounts, owner: Pubkey, owner_account: Pubkey) -> Instruction {
+        instruction::amm_withdraw(
+            id(),
+            self.address,
+            self.escrow,
+            self.long_mint,
+            self.short_mint,
+            amm.vault.pubkey(),
+            amm.long.pubkey(),
+            amm.short.pubkey(),
+            owner,
+            owner_account,
+            self.escrow_authority,
+        )
+    }
+}
+
+/// A constant product market maker of a pool, along with its LP mint, collateral vault and
+/// inventory
+struct CpmmAccounts {
+    pool: Keypair,
+    lp_mint: Keypair,
+    vault: Keypair,
+    long: Keypair,
+    short: Keypair,
+}
+
+impl CpmmAccounts {
+    fn new() -> Self {
+        Self {
+            pool: Keypair::new(),
+            lp_mint: Keypair::new(),
+            vault: Keypair::new(),
+            long: Keypair::new(),
+            short: Keypair::new(),
+        }
+    }
+
+    fn signers(&self) -> [&Keypair; 5] {
+        [
+            &self.pool,
+            &self.lp_mint,
+            &self.vault,
+            &self.long,
+            &self.short,
+        ]
+    }
+
+    async fn state(&self, banks_client: &mut BanksClient) -> CpmmPool {
+        let account = banks_client
+            .get_account(self.pool.pubkey())
+            .await
+            .unwrap()
+            .unwrap();
+        CpmmPool::try_from_slice(&account.data).unwrap()
+    }
+}
+
+impl Pool {
+    fn initialize_cpmm(
+        &self,
+        cpmm: &CpmmAccounts,
+        pool_owner: Pubkey,
+        fee_bps: u16,
+    ) -> Instruction {
+        instruction::initialize_cpmm(
+            id(),
+            self.address,
+            cpmm.pool.pubkey(),
+            cpmm.lp_mint.pubkey(),
+            cpmm.vault.pubkey(),
+            cpmm.long.pubkey(),
+            cpmm.short.pubkey(),
+            self.escrow_mint,
+            self.long_mint,
+            self.short_mint,
+            self.escrow_authority,
+            pool_owner,
+            fee_bps,
+        )
+    }
+
+    /// Adds `amount` of collateral, or removes `amount` shares, on behalf of `provider`
+    fn cpmm_liquidity(
+        &self,
+        cpmm: &CpmmAccounts,
+        add: bool,
+        provider: &Trader,
+        provider_lp: Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        let build = if add {
+            instruction::cpmm_add_liquidity
+        } else {
+            instruction::cpmm_remove_liquidity
+        };
+        build(
+            id(),
+            self.address,
+            cpmm.pool.pubkey(),
+            cpmm.lp_mint.pubkey(),
+            cpmm.vault.pubkey(),
+            cpmm.long.pubkey(),
+            cpmm.short.pubkey(),
+            self.escrow,
+            self.long_mint,
+            self.short_mint,
+            provider.wallet.pubkey(),
+            provider.collateral,
+            provider.long,
+            provider.short,
+            provider_lp,
+            self.escrow_authority,
+            amount,
+        )
+    }
+
+    /// Buys or sells `size` tokens of `side` at a cost of at most, or for proceeds of at least,
+    /// `collateral_limit`
+    fn cpmm_trade(
+        &self,
+        cpmm: &CpmmAccounts,
+        buy: bool,
+        trader: &Trader,
+        side: PositionSide,
+        size: u64,
+        collateral_limit: u64,
+    ) -> Instruction {
+        let build = if buy {
+            instruction::cpmm_buy
+        } else {
+            instruction::cpmm_sell
+        };
+        build(
+            id(),
+            self.address,
+            cpmm.pool.pubkey(),
+            self.escrow,
+            self.long_mint,
+            self.short_mint,
+            cpmm.vault.pubkey(),
+            cpmm.long.pubkey(),
+            cpmm.short.pubkey(),
+            trader.wallet.pubkey(),
+            trader.collateral,
+            match side {
+                PositionSide::Long => trader.long,
+                PositionSide::Short => trader.short,
+            },
+            self.escrow_authority,
+            self.fee_vault,
+            side,
+            size,
+            collateral_limit,
+        )
+    }
+}
+
+/// A categorical market using the collateral of a pool, owned by the owner of the pool
+struct Categorical {
+    address: Pubkey,
+    escrow: Pubkey,
+    escrow_authority: Pubkey,
+    mints: Vec<Pubkey>,
+}
+
+/// Wallet of a categorical market trader, with one token account per outcome
+struct CategoricalTrader {
+    wallet: Keypair,
+    collateral: Pubkey,
+    outcomes: Vec<Pubkey>,
+}
+
+impl Categorical {
+    fn initialize_instruction(
+        pool: &Pool,
+        market: &Keypair,
+        escrow: &Keypair,
+        mints: &[Keypair],
+        expiry: i64,
+    ) -> Instruction {
+        let mint_keys: Vec<Pubkey> = mints.iter().map(|mint| mint.pubkey()).collect();
+        instruction::initialize_categorical(
+            id(),
+            market.pubkey(),
+            pool.escrow_mint,
+            escrow.pubkey(),
+            Self::escrow_authority(&market.pubkey()),
+            pool.owner.pubkey(),
+            &mint_keys,
+            DECIMALS,
+            expiry,
+        )
+    }
+
+    async fn initialize(
+        context: &mut ProgramTestContext,
+        pool: &Pool,
+        outcomes: usize,
+        expiry: i64,
+    ) -> Self {
+        let market = Keypair::new();
+        let escrow = Keypair::new();
+        let mints: Vec<Keypair> = (0..outcomes).map(|_| Keypair::new()).collect();
+        let mut signers = vec![&market, &escrow, &pool.owner];
+        signers.extend(mints.iter());
+        process(
+            context,
+            &[Self::initialize_instruction(
+                pool, &market, &escrow, &mints, expiry,
+            )],
+            &signers,
+        )
+        .await;
+        Self {
+            address: market.pubkey(),
+            escrow: escrow.pubkey(),
+            escrow_authority: Self::escrow_authority(&market.pubkey()),
+            mints: mints.iter().map(|mint| mint.pubkey()).collect(),
+        }
+    }
+
+    fn escrow_authority(market: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[market.as_ref(), spl_token::id().as_ref(), id().as_ref()],
+            &id(),
+        )
+        .0
+    }
+
+    async fn trader(
+        &self,
+        context: &mut ProgramTestContext,
+        pool: &Pool,
+        collateral: u64,
+    ) -> CategoricalTrader {
+        let wallet = Keypair::new();
+        let collateral = pool
+            .collateral_account(context, &wallet.pubkey(), collateral)
+            .await;
+        let mut outcomes = vec![];
+        for mint in self.mints.iter() {
+            outcomes.push(create_token_account(context, mint, &wallet.pubkey()).await);
+        }
+        CategoricalTrader {
+            wallet,
+            collateral,
+            outcomes,
+        }
+    }
+
+    fn mint_set(&self, trader: &CategoricalTrader, size: u64) -> Instruction {
+        instruction::categorical_mint_set(
+            id(),
+            self.address,
+            self.escrow,
+            trader.wallet.pubkey(),
+            trader.collateral,
+            self.escrow_authority,
+            &self.mints,
+            &trader.outcomes,
+            size,
+        )
+    }
+
+    fn redeem_set(&self, trader: &CategoricalTrader, size: u64) -> Instruction {
+        instruction::categorical_redeem_set(
+            id(),
+            self.address,
+            self.escrow,
+            trader.wallet.pubkey(),
+            trader.collateral,
+            self.escrow_authority,
+            &self.mints,
+            &trader.outcomes,
+            size,
+        )
+    }
+
+    /// `seller` sells `size` tokens of `outcome` to `buyer` for `price` each
+    fn trade(
+        &self,
+        buyer: &CategoricalTrader,
+        seller: &CategoricalTrader,
+        outcome: u8,
+        size: u64,
+        price: u64,
+    ) -> Instruction {
+        // Out of range outcomes are passed with the accounts of the last one
+        let i = (outcome as usize).min(self.mints.len() - 1);
+        instruction::categorical_trade(
+            id(),
+            self.address,
+            self.mints[i],
+            buyer.wallet.pubkey(),
+            seller.wallet.pubkey(),
+            buyer.collateral,
+            seller.collateral,
+            buyer.outcomes[i],
+            seller.outcomes[i],
+            outcome,
+            size,
+            price,
+        )
+    }
+
+    fn settle(&self, owner: Pubkey, outcome: u8) -> Instruction {
+        instruction::categorical_settle(id(), self.address, owner, outcome)
+    }
+
+    fn collect(&self, trader: &CategoricalTrader, outcome: usize) -> Instruction {
+        instruction::categorical_collect(
+            id(),
+            self.address,
+            self.escrow,
+            self.mints[outcome],
+            trader.wallet.pubkey(),
+            trader.collateral,
+            trader.outcomes[outcome],
+            self.escrow_authority,
+        )
+    }
+}
+
+fn position(collateral: u64, long: u64, short: u64) -> Position {
+    Position {
+        collateral,
+        long,
+        short,
+    }
+}
+
+/// The result of a transaction whose first instruction fails with `error`
+fn program_error(error: BinaryOptionError) -> Result<(), TransactionError> {
+    Err(TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(error as u32),
+    ))
+}
+
+#[tokio::test]
+async fn test_initialize() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.decimals, DECIMALS);
+    assert_eq!(state.status, PoolStatus::Open);
+    assert_eq!(state.owner, pool.owner.pubkey());
+    assert_eq!(state.escrow_mint_account_pubkey, pool.escrow_mint);
+    assert_eq!(state.escrow_account_pubkey, pool.escrow);
+    assert_eq!(state.long_mint_account_pubkey, pool.long_mint);
+    assert_eq!(state.short_mint_account_pubkey, pool.short_mint);
+    pool.assert_circulation(&mut context.banks_client, 0).await;
+
+    // The escrow and both mints are controlled by the program
+    let escrow = context
+        .banks_client
+        .get_account(pool.escrow)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Account::unpack(&escrow.data).unwrap().owner,
+        pool.escrow_authority
+    );
+    for mint in [pool.long_mint, pool.short_mint].iter() {
+        let mint = context
+            .banks_client
+            .get_account(*mint)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Mint::unpack(&mint.data).unwrap().mint_authority,
+            Some(pool.escrow_authority).into()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_trade_opens_positions() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+
+    // n_b < n && n_s < n: neither trader holds the opposite side
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+    let banks_client = &mut context.banks_client;
+    assert_eq!(
+        pool.position(banks_client, &alice).await,
+        position(9_700, 10, 0)
+    );
+    assert_eq!(
+        pool.position(banks_client, &bob).await,
+        position(9_300, 0, 10)
+    );
+    pool.assert_circulation(banks_client, 10).await;
+}
+
+#[tokio::test]
+async fn test_trade_closes_positions() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+
+    // n_b >= n && n_s >= n: Bob buys back 4 of his shorts from Alice, both reduce inventory
+    pool.trade(&mut context, &bob, &alice, 4, 40).await;
+    let banks_client = &mut context.banks_client;
+    assert_eq!(
+        pool.position(banks_client, &alice).await,
+        position(9_700 + 4 * 40, 6, 0)
+    );
+    assert_eq!(
+        pool.position(banks_client, &bob).await,
+        position(9_300 + 4 * 60, 0, 6)
+    );
+    pool.assert_circulation(banks_client, 6).await;
+}
+
+#[tokio::test]
+async fn test_trade_buyer_closes_seller_opens() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    let carol = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+
+    // n_b >= n && n_s < n: Bob closes 4 shorts, Carol opens 4 shorts
+    pool.trade(&mut context, &bob, &carol, 4, 45).await;
+    let banks_client = &mut context.banks_client;
+    assert_eq!(
+        pool.position(banks_client, &bob).await,
+        position(9_300 + 4 * 55, 0, 6)
+    );
+    assert_eq!(
+        pool.position(banks_client, &carol).await,
+        position(10_000 - 4 * 55, 0, 4)
+    );
+    pool.assert_circulation(banks_client, 10).await;
+}
+
+#[tokio::test]
+async fn test_trade_buyer_opens_seller_closes() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    let carol = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+
+    // n_b < n && n_s >= n: Carol opens 3 longs, Alice closes 3 longs
+    pool.trade(&mut context, &carol, &alice, 3, 35).await;
+    let banks_client = &mut context.banks_client;
+    assert_eq!(
+        pool.position(banks_client, &carol).await,
+        position(10_000 - 3 * 35, 3, 0)
+    );
+    assert_eq!(
+        pool.position(banks_client, &alice).await,
+        position(9_700 + 3 * 35, 7, 0)
+    );
+    pool.assert_circulation(banks_client, 10).await;
+}
+
+#[tokio::test]
+async fn test_trade_swaps_positions() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+
+    // n_b < n && n_s < n with existing inventory: Bob goes from 10 short to 5 long and Alice
+    // from 10 long to 5 short
+    pool.trade(&mut context, &bob, &alice, 15, 50).await;
+    let banks_client = &mut context.banks_client;
+    assert_eq!(
+        pool.position(banks_client, &bob).await,
+        position(9_300 + 10 * 50 - 5 * 50, 5, 0)
+    );
+    assert_eq!(
+        pool.position(banks_client, &alice).await,
+        position(9_700 + 10 * 50 - 5 * 50, 0, 5)
+    );
+    pool.assert_circulation(banks_client, 5).await;
+}
+
+#[tokio::test]
+async fn test_settle_and_collect() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    let carol = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+    pool.trade(&mut context, &carol, &alice, 4, 60).await;
+    pool.assert_circulation(&mut context.banks_client, 10).await;
+
+    pool.settle(&mut context, pool.long_mint).await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.status, PoolStatus::Settled);
+    assert_eq!(state.winning_side_pubkey, pool.long_mint);
+
+    // Alice holds 6 longs and Carol 4, Bob's 10 shorts are worthless
+    pool.collect(&mut context, &alice).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(9_700 + 4 * 60 + 6 * UNIT, 0, 0)
+    );
+    pool.assert_circulation(&mut context.banks_client, 4).await;
+
+    pool.collect(&mut context, &bob).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &bob).await,
+        position(9_300, 0, 0)
+    );
+    pool.assert_circulation(&mut context.banks_client, 4).await;
+
+    pool.collect(&mut context, &carol).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &carol).await,
+        position(10_000 - 4 * 60 + 4 * UNIT, 0, 0)
+    );
+    pool.assert_circulation(&mut context.banks_client, 0).await;
+}
+
+#[tokio::test]
+async fn test_collect_destination() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+    pool.settle(&mut context, pool.long_mint).await;
+
+    let collect = |collector: &Trader, collateral_account: Pubkey| {
+        instruction::collect(
+            id(),
+            pool.address,
+            collector.wallet.pubkey(),
+            collector.long,
+            collector.short,
+            collateral_account,
+            pool.long_mint,
+            pool.short_mint,
+            pool.escrow,
+            pool.escrow_authority,
+            pool.fee_vault,
+        )
+    };
+    let payout_error = Err(TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(BinaryOptionError::InvalidPayoutDestination as u32),
+    ));
+
+    // Without Alice's signature her payout can only go to her associated token account, even if
+    // she owns the other account
+    let alice_account = pool
+        .collateral_account(&mut context, &alice.wallet.pubkey(), 0)
+        .await;
+    let mallory = Keypair::new();
+    let mallory_account = pool
+        .collateral_account(&mut context, &mallory.pubkey(), 0)
+        .await;
+    assert_eq!(
+        try_process(&mut context, &[collect(&alice, alice_account)], &[]).await,
+        payout_error
+    );
+    assert!(
+        try_process(&mut context, &[collect(&alice, mallory_account)], &[])
+            .await
+            .is_err()
+    );
+
+    // Signed by Alice, it can go anywhere
+    let signed = instruction::collect_signed(
+        id(),
+        pool.address,
+        alice.wallet.pubkey(),
+        alice.long,
+        alice.short,
+        mallory_account,
+        pool.long_mint,
+        pool.short_mint,
+        pool.escrow,
+        pool.escrow_authority,
+        pool.fee_vault,
+    );
+    process(&mut context, &[signed], &[&alice.wallet]).await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &mallory_account).await,
+        10 * UNIT
+    );
+
+    // Anyone can crank Bob's collection into his associated token account
+    process(&mut context, &[collect(&bob, bob.collateral)], &[]).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &bob).await,
+        position(9_300, 0, 0)
+    );
+    pool.assert_circulation(&mut context.banks_client, 0).await;
+}
+
+#[tokio::test]
+async fn test_mint_and_redeem_sets() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+
+    // A set costs one unit of collateral and can be redeemed for it at any time
+    process(&mut context, &[pool.mint_set(&alice, 5)], &[&alice.wallet]).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(9_500, 5, 5)
+    );
+    pool.assert_circulation(&mut context.banks_client, 5).await;
+    process(
+        &mut context,
+        &[pool.redeem_set(&alice, 2)],
+        &[&alice.wallet],
+    )
+    .await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(9_700, 3, 3)
+    );
+    pool.assert_circulation(&mut context.banks_client, 3).await;
+
+    // Only the sets a user holds can be redeemed
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.redeem_set(&alice, 4)],
+            &[&alice.wallet]
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidSupply)
+    );
+    process(
+        &mut context,
+        &[pool.redeem_set(&alice, 3)],
+        &[&alice.wallet],
+    )
+    .await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(10_000, 0, 0)
+    );
+    pool.assert_circulation(&mut context.banks_client, 0).await;
+
+    // Sets can neither be minted nor redeemed once the pool is settled
+    let bob = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 2, 30).await;
+    pool.settle(&mut context, pool.long_mint).await;
+    assert_eq!(
+        try_process(&mut context, &[pool.mint_set(&alice, 1)], &[&alice.wallet]).await,
+        program_error(BinaryOptionError::AlreadySettled)
+    );
+    assert_eq!(
+        try_process(&mut context, &[pool.redeem_set(&bob, 1)], &[&bob.wallet]).await,
+        program_error(BinaryOptionError::AlreadySettled)
+    );
+}
+
+#[tokio::test]
+async fn test_trading_closes_at_expiry() {
+    let mut context = program_test().start_with_context().await;
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let expiry = clock.unix_timestamp + 60;
+    let pool = Pool::initialize_with_expiry(&mut context, expiry).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+
+    clock.unix_timestamp = expiry;
+    context.set_sysvar(&clock);
+    let trade = instruction::trade(
+        id(),
+        pool.address,
+        pool.escrow,
+        pool.long_mint,
+        pool.short_mint,
+        alice.wallet.pubkey(),
+        bob.wallet.pubkey(),
+        alice.collateral,
+        bob.collateral,
+        alice.long,
+        alice.short,
+        bob.long,
+        bob.short,
+        pool.escrow_authority,
+        None,
+        1,
+        30,
+        UNIT - 30,
+    );
+    assert_eq!(
+        try_process(&mut context, &[trade], &[&alice.wallet, &bob.wallet]).await,
+        program_error(BinaryOptionError::TradingClosed)
+    );
+    assert_eq!(
+        try_process(&mut context, &[pool.mint_set(&alice, 1)], &[&alice.wallet]).await,
+        program_error(BinaryOptionError::TradingClosed)
+    );
+
+    // The owner still settles the expired pool and positions are paid out as usual
+    pool.settle(&mut context, pool.long_mint).await;
+    pool.collect(&mut context, &alice).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(9_700 + 10 * UNIT, 0, 0)
+    );
+}
+
+#[tokio::test]
+async fn test_halt_and_resume() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let owner = pool.owner.pubkey();
+    let halt = || instruction::halt(id(), pool.address, owner);
+    let resume = || instruction::resume(id(), pool.address, owner, None);
+
+    // Only the owner halts the pool
+    let mallory = Keypair::new();
+    assert!(try_process(
+        &mut context,
+        &[instruction::halt(id(), pool.address, mallory.pubkey())],
+        &[&mallory],
+    )
+    .await
+    .is_err());
+    assert_eq!(
+        try_process(&mut context, &[resume()], &[&pool.owner]).await,
+        program_error(BinaryOptionError::InvalidPoolStatus)
+    );
+
+    process(&mut context, &[halt()], &[&pool.owner]).await;
+    assert_eq!(
+        pool.state(&mut context.banks_client).await.status,
+        PoolStatus::Halted
+    );
+    assert_eq!(
+        try_process(&mut context, &[pool.mint_set(&alice, 1)], &[&alice.wallet]).await,
+        program_error(BinaryOptionError::TradingClosed)
+    );
+    assert_eq!(
+        try_process(&mut context, &[halt()], &[&pool.owner]).await,
+        program_error(BinaryOptionError::InvalidPoolStatus)
+    );
+
+    process(&mut context, &[resume()], &[&pool.owner]).await;
+    process(&mut context, &[pool.mint_set(&alice, 1)], &[&alice.wallet]).await;
+
+    // A halted pool can still be settled, after which it stays final
+    process(&mut context, &[halt()], &[&pool.owner]).await;
+    pool.settle(&mut context, pool.long_mint).await;
+    assert_eq!(
+        try_process(&mut context, &[halt()], &[&pool.owner]).await,
+        program_error(BinaryOptionError::AlreadySettled)
+    );
+}
+
+#[tokio::test]
+async fn test_void() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+
+    // The owner picks the payout of a pool it settles itself
+    let mallory = Keypair::new();
+    assert!(try_process(
+        &mut context,
+        &[instruction::void(id(), pool.address, mallory.pubkey(), 40)],
+        &[&mallory],
+    )
+    .await
+    .is_err());
+    process(
+        &mut context,
+        &[instruction::void(
+            id(),
+            pool.address,
+            pool.owner.pubkey(),
+            40,
+        )],
+        &[&pool.owner],
+    )
+    .await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.status, PoolStatus::Voided);
+    assert_eq!(state.void_price, 40);
+    pool.collect(&mut context, &alice).await;
+    pool.collect(&mut context, &bob).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(9_700 + 10 * 40, 0, 0)
+    );
+    assert_eq!(
+        pool.position(&mut context.banks_client, &bob).await,
+        position(9_300 + 10 * 60, 0, 0)
+    );
+
+    // A pool settled by a committee can't be voided by its owner
+    let pool = Pool::initialize(&mut context).await;
+    let committee = Keypair::new();
+    process(
+        &mut context,
+        &[instruction::initialize_committee(
+            id(),
+            pool.address,
+            committee.pubkey(),
+            pool.owner.pubkey(),
+            &[Pubkey::new_unique()],
+            1,
+            30,
+        )],
+        &[&committee, &pool.owner],
+    )
+    .await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[instruction::void(
+                id(),
+                pool.address,
+                pool.owner.pubkey(),
+                40
+            )],
+            &[&pool.owner],
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidResolutionSource)
+    );
+
+    // An oracle pool is only voided once no price can settle it anymore, at the preset price
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let expiry = clock.unix_timestamp + 60;
+    let max_publish_delay = 30;
+    let pool = Pool::initialize_with_expiry(&mut context, expiry).await;
+    let oracle_feed = Pubkey::new_unique();
+    set_oracle_price(
+        &mut context,
+        oracle_feed,
+        PYTH_PROGRAM_ID,
+        200,
+        clock.slot,
+        expiry - 1,
+    );
+    process(
+        &mut context,
+        &[instruction::configure_oracle(
+            id(),
+            pool.address,
+            oracle_feed,
+            pool.owner.pubkey(),
+            100,
+            Comparison::GreaterThan,
+            1_000,
+            100,
+            max_publish_delay,
+            PYTH_PROGRAM_ID,
+            UNIT / 2,
+        )],
+        &[&pool.owner],
+    )
+    .await;
+    let void = || instruction::void(id(), pool.address, pool.owner.pubkey(), 0);
+    clock.unix_timestamp = expiry + max_publish_delay + ORACLE_VOID_GRACE_PERIOD - 1;
+    context.set_sysvar(&clock);
+    assert_eq!(
+        try_process(&mut context, &[void()], &[&pool.owner]).await,
+        program_error(BinaryOptionError::MarketNotExpired)
+    );
+    clock.unix_timestamp += 1;
+    context.set_sysvar(&clock);
+    process(&mut context, &[void()], &[&pool.owner]).await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.status, PoolStatus::Voided);
+    assert_eq!(state.void_price, UNIT / 2);
+}
+
+#[tokio::test]
+async fn test_scalar_settlement() {
+    let mut context = program_test().start_with_context().await;
+    let configure_scalar = |pool: &Pool, lower_bound, upper_bound| {
+        instruction::configure_scalar(
+            id(),
+            pool.address,
+            pool.owner.pubkey(),
+            lower_bound,
+            upper_bound,
+        )
+    };
+    let settle_scalar = |pool: &Pool, value| {
+        instruction::settle_scalar(id(), pool.address, pool.owner.pubkey(), value)
+    };
+
+    let pool = Pool::initialize(&mut context).await;
+    process(
+        &mut context,
+        &[configure_scalar(&pool, 0, 1_000)],
+        &[&pool.owner],
+    )
+    .await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+    // The bounds are fixed once positions exist, and a scalar market has no winning mint
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[configure_scalar(&pool, 0, 2_000)],
+            &[&pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidSupply)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[instruction::settle(
+                id(),
+                pool.address,
+                pool.long_mint,
+                pool.owner.pubkey()
+            )],
+            &[&pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidMarketType)
+    );
+
+    // A long token pays out a quarter of a complete set
+    process(&mut context, &[settle_scalar(&pool, 250)], &[&pool.owner]).await;
+    pool.collect(&mut context, &alice).await;
+    pool.collect(&mut context, &bob).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(9_700 + 10 * 25, 0, 0)
+    );
+    assert_eq!(
+        pool.position(&mut context.banks_client, &bob).await,
+        position(9_300 + 10 * 75, 0, 0)
+    );
+
+    // Values beyond the bounds pay out everything to one side
+    let pool = Pool::initialize(&mut context).await;
+    process(
+        &mut context,
+        &[configure_scalar(&pool, -500, 500)],
+        &[&pool.owner],
+    )
+    .await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+    process(
+        &mut context,
+        &[settle_scalar(&pool, -2_000)],
+        &[&pool.owner],
+    )
+    .await;
+    pool.collect(&mut context, &alice).await;
+    pool.collect(&mut context, &bob).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(9_700, 0, 0)
+    );
+    assert_eq!(
+        pool.position(&mut context.banks_client, &bob).await,
+        position(9_300 + 10 * UNIT, 0, 0)
+    );
+
+    // A binary pool has no resolution value
+    let pool = Pool::initialize(&mut context).await;
+    assert_eq!(
+        try_process(&mut context, &[settle_scalar(&pool, 250)], &[&pool.owner]).await,
+        program_error(BinaryOptionError::InvalidMarketType)
+    );
+}
+
+#[tokio::test]
+async fn test_owner_transfer() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let old_owner = pool.owner.pubkey();
+    let new_owner = Keypair::new();
+    let mallory = Keypair::new();
+    let propose = |owner: Pubkey, new_owner: Pubkey| {
+        instruction::propose_owner(id(), pool.address, owner, new_owner)
+    };
+    let accept =
+        |new_owner: &Keypair| instruction::accept_owner(id(), pool.address, new_owner.pubkey());
+    let mismatch = program_error(BinaryOptionError::PublicKeyMismatch);
+
+    // Only the owner proposes, and only the proposed owner accepts
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[propose(mallory.pubkey(), mallory.pubkey())],
+            &[&mallory]
+        )
+        .await,
+        mismatch
+    );
+    process(
+        &mut context,
+        &[propose(old_owner, new_owner.pubkey())],
+        &[&pool.owner],
+    )
+    .await;
+    assert_eq!(
+        try_process(&mut context, &[accept(&mallory)], &[&mallory]).await,
+        mismatch
+    );
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.owner, old_owner);
+    assert_eq!(state.pending_owner, new_owner.pubkey());
+
+    // Proposing the default key cancels the transfer
+    process(
+        &mut context,
+        &[propose(old_owner, Pubkey::default())],
+        &[&pool.owner],
+    )
+    .await;
+    assert_eq!(
+        try_process(&mut context, &[accept(&new_owner)], &[&new_owner]).await,
+        mismatch
+    );
+
+    process(
+        &mut context,
+        &[propose(old_owner, new_owner.pubkey())],
+        &[&pool.owner],
+    )
+    .await;
+    process(&mut context, &[accept(&new_owner)], &[&new_owner]).await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.owner, new_owner.pubkey());
+    assert_eq!(state.pending_owner, Pubkey::default());
+
+    // Settlement rights moved with the ownership
+    let settle = |owner: Pubkey| instruction::settle(id(), pool.address, pool.long_mint, owner);
+    assert_eq!(
+        try_process(&mut context, &[settle(old_owner)], &[&pool.owner]).await,
+        mismatch
+    );
+    process(&mut context, &[settle(new_owner.pubkey())], &[&new_owner]).await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.winning_side_pubkey, pool.long_mint);
+}
+
+#[tokio::test]
+async fn test_order_book() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    let book = pool.initialize_order_book(&mut context).await;
+
+    // Orders 0 and 2 are bids of Alice, order 1 is an ask of Bob
+    let bid = pool.place_order(&book, &alice, OrderSide::Bid, 40, 5);
+    let ask = pool.place_order(&book, &bob, OrderSide::Ask, 40, 5);
+    let cancelled_bid = pool.place_order(&book, &alice, OrderSide::Bid, 10, 5);
+    process(&mut context, &[bid], &[&alice.wallet]).await;
+    process(&mut context, &[ask], &[&bob.wallet]).await;
+    process(
+        &mut context,
+        &[cancelled_bid, pool.cancel_order(&book, &alice, 2)],
+        &[&alice.wallet],
+    )
+    .await;
+    process(
+        &mut context,
+        &[pool.match_orders(&book, &alice, &bob, 0, 1)],
+        &[],
+    )
+    .await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(10_000 - 5 * 40, 5, 0)
+    );
+    assert_eq!(
+        pool.position(&mut context.banks_client, &bob).await,
+        position(10_000 - 5 * 60, 0, 5)
+    );
+    pool.assert_circulation(&mut context.banks_client, 5).await;
+}
+
+#[tokio::test]
+async fn test_match_orders_partial_fill() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    let book = pool.initialize_order_book(&mut context).await;
+
+    // Bob's ask rests first, so Alice's bid is filled at his price and gets the difference back
+    let ask = pool.place_order(&book, &bob, OrderSide::Ask, 30, 4);
+    let bid = pool.place_order(&book, &alice, OrderSide::Bid, 40, 10);
+    process(&mut context, &[ask], &[&bob.wallet]).await;
+    process(&mut context, &[bid], &[&alice.wallet]).await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &book.vault).await,
+        4 * 70 + 10 * 40
+    );
+    assert_eq!(allowance(&mut context.banks_client, &alice.long).await, 10);
+
+    process(
+        &mut context,
+        &[pool.match_orders(&book, &alice, &bob, 1, 0)],
+        &[],
+    )
+    .await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(10_000 - 10 * 40 + 4 * 10, 4, 0)
+    );
+    assert_eq!(
+        pool.position(&mut context.banks_client, &bob).await,
+        position(10_000 - 4 * 70, 0, 4)
+    );
+    pool.assert_circulation(&mut context.banks_client, 4).await;
+    // The rest of the bid stays on the book, along with its collateral and allowance
+    let orders = book.orders(&mut context.banks_client).await;
+    assert_eq!(
+        orders.find_order(0),
+        Err(BinaryOptionError::OrderNotFound.into())
+    );
+    assert_eq!(orders.orders[orders.find_order(1).unwrap()].size, 6);
+    assert_eq!(
+        token_balance(&mut context.banks_client, &book.vault).await,
+        6 * 40
+    );
+    assert_eq!(
+        allowance(&mut context.banks_client, &alice.long).await,
+        4 + 6
+    );
+    assert_eq!(allowance(&mut context.banks_client, &bob.short).await, 4);
+
+    // Cancelling returns the collateral and the allowance of the unfilled contracts
+    process(
+        &mut context,
+        &[pool.cancel_order(&book, &alice, 1)],
+        &[&alice.wallet],
+    )
+    .await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(10_000 - 4 * 30, 4, 0)
+    );
+    assert_eq!(
+        token_balance(&mut context.banks_client, &book.vault).await,
+        0
+    );
+    assert_eq!(allowance(&mut context.banks_client, &alice.long).await, 4);
+}
+
+#[tokio::test]
+async fn test_match_orders_nets_positions() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    let book = pool.initialize_order_book(&mut context).await;
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+
+    // Bob buys back 4 of his shorts and Alice sells 4 of her longs, so both close positions
+    let bid = pool.place_order(&book, &bob, OrderSide::Bid, 50, 4);
+    let ask = pool.place_order(&book, &alice, OrderSide::Ask, 50, 4);
+    process(&mut context, &[bid], &[&bob.wallet]).await;
+    process(&mut context, &[ask], &[&alice.wallet]).await;
+    process(
+        &mut context,
+        &[pool.match_orders(&book, &bob, &alice, 0, 1)],
+        &[],
+    )
+    .await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(9_700 + 4 * 50, 6, 0)
+    );
+    assert_eq!(
+        pool.position(&mut context.banks_client, &bob).await,
+        position(9_300 + 4 * 50, 0, 6)
+    );
+    pool.assert_circulation(&mut context.banks_client, 6).await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &book.vault).await,
+        0
+    );
+    // Once the orders are filled, the PDA can burn exactly the remaining positions
+    for (account, amount) in [
+        (alice.long, 6),
+        (alice.short, 0),
+        (bob.long, 0),
+        (bob.short, 6),
+    ]
+    .iter()
+    {
+        assert_eq!(allowance(&mut context.banks_client, account).await, *amount);
+    }
+}
+
+#[tokio::test]
+async fn test_order_book_rejections() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+
+    // Only the owner can attach a book, and a pool has a single one, whose vault it checks before
+    // closing
+    let (other_book, other_vault) = (Keypair::new(), Keypair::new());
+    let initialize_other = |pool_owner: Pubkey| {
+        instruction::initialize_order_book(
+            id(),
+            pool.address,
+            other_book.pubkey(),
+            other_vault.pubkey(),
+            pool.escrow_mint,
+            pool.escrow_authority,
+            pool_owner,
+        )
+    };
+    let mallory = Keypair::new();
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[initialize_other(mallory.pubkey())],
+            &[&other_book, &other_vault, &mallory],
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+    let book = pool.initialize_order_book(&mut context).await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[initialize_other(pool.owner.pubkey())],
+            &[&other_book, &other_vault, &pool.owner],
+        )
+        .await,
+        program_error(BinaryOptionError::OrderBookAlreadyInitialized)
+    );
+
+    // Orders have a size and a price strictly between 0 and a complete set
+    for (price, size) in [(0, 5), (UNIT, 5), (40, 0)].iter() {
+        assert_eq!(
+            try_process(
+                &mut context,
+                &[pool.place_order(&book, &alice, OrderSide::Bid, *price, *size)],
+                &[&alice.wallet],
+            )
+            .await,
+            program_error(BinaryOptionError::InvalidOrder)
+        );
+    }
+
+    // A bid below the ask is never filled, and the orders have to be passed as bid and ask
+    let bid = pool.place_order(&book, &alice, OrderSide::Bid, 40, 5);
+    let ask = pool.place_order(&book, &bob, OrderSide::Ask, 50, 5);
+    process(&mut context, &[bid], &[&alice.wallet]).await;
+    process(&mut context, &[ask], &[&bob.wallet]).await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.match_orders(&book, &alice, &bob, 0, 1)],
+            &[]
+        )
+        .await,
+        program_error(BinaryOptionError::OrdersDoNotCross)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.match_orders(&book, &bob, &alice, 1, 0)],
+            &[]
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidOrder)
+    );
+
+    // Only the owner of an order cancels it
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.cancel_order(&book, &bob, 0)],
+            &[&bob.wallet]
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+
+    // Nothing is placed or matched while the pool is halted, but orders can still be cancelled,
+    // and only once
+    process(
+        &mut context,
+        &[instruction::halt(id(), pool.address, pool.owner.pubkey())],
+        &[&pool.owner],
+    )
+    .await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.place_order(&book, &alice, OrderSide::Bid, 60, 5)],
+            &[&alice.wallet],
+        )
+        .await,
+        program_error(BinaryOptionError::TradingClosed)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.match_orders(&book, &alice, &bob, 0, 1)],
+            &[]
+        )
+        .await,
+        program_error(BinaryOptionError::TradingClosed)
+    );
+    process(
+        &mut context,
+        &[pool.cancel_order(&book, &alice, 0)],
+        &[&alice.wallet],
+    )
+    .await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.cancel_order(&book, &alice, 0)],
+            &[&alice.wallet]
+        )
+        .await,
+        program_error(BinaryOptionError::OrderNotFound)
+    );
+    assert_eq!(
+        token_balance(&mut context.banks_client, &book.vault).await,
+        5 * 50
+    );
+
+    // Once the pool is settled, resting orders are only cancelled, which empties the vault
+    pool.settle(&mut context, pool.long_mint).await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.place_order(&book, &alice, OrderSide::Bid, 60, 5)],
+            &[&alice.wallet],
+        )
+        .await,
+        program_error(BinaryOptionError::AlreadySettled)
+    );
+    process(
+        &mut context,
+        &[pool.cancel_order(&book, &bob, 1)],
+        &[&bob.wallet],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &book.vault).await,
+        0
+    );
+    for trader in [&alice, &bob].iter() {
+        assert_eq!(
+            pool.position(&mut context.banks_client, trader).await,
+            position(10_000, 0, 0)
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_amm() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let owner = pool.owner.pubkey();
+    let owner_account = pool.collateral_account(&mut context, &owner, 10_000).await;
+    let amm = AmmAccounts::new();
+    process(
+        &mut context,
+        &[pool.initialize_amm(&amm, owner, owner_account, 10, 10 * UNIT)],
+        &[&amm.vault, &amm.long, &amm.short, &pool.owner],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &amm.vault.pubkey()).await,
+        10 * UNIT
+    );
+
+    // Buying pushes the price of a long token above the initial half of a set
+    process(
+        &mut context,
+        &[pool.amm_trade(&amm, true, &alice, PositionSide::Long, 5, 5 * UNIT)],
+        &[&alice.wallet],
+    )
+    .await;
+    let cost = 10_000 - token_balance(&mut context.banks_client, &alice.collateral).await;
+    assert!(5 * UNIT / 2 < cost && cost < 5 * UNIT, "cost {}", cost);
+    // The market maker mints complete sets and keeps the short side
+    pool.assert_circulation(&mut context.banks_client, 5).await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &amm.short.pubkey()).await,
+        5
+    );
+
+    // Selling back pays out the price of a long token, which is still above half a set, and
+    // redeems the complete sets the market maker now holds
+    process(
+        &mut context,
+        &[pool.amm_trade(&amm, false, &alice, PositionSide::Long, 2, 0)],
+        &[&alice.wallet],
+    )
+    .await;
+    let collateral = token_balance(&mut context.banks_client, &alice.collateral).await;
+    let proceeds = collateral - (10_000 - cost);
+    assert!(
+        UNIT < proceeds && proceeds < 2 * UNIT,
+        "proceeds {}",
+        proceeds
+    );
+    assert_eq!(
+        token_balance(&mut context.banks_client, &alice.long).await,
+        3
+    );
+    pool.assert_circulation(&mut context.banks_client, 3).await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(
+        (state.amm_long_outstanding, state.amm_short_outstanding),
+        (3, 0)
+    );
+
+    // Short wins, so the owner gets back the subsidy along with Alice's losses
+    pool.settle(&mut context, pool.short_mint).await;
+    process(
+        &mut context,
+        &[pool.amm_withdraw(&amm, owner, owner_account)],
+        &[&pool.owner],
+    )
+    .await;
+    pool.collect(&mut context, &alice).await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &owner_account).await,
+        10_000 + 10_000 - collateral
+    );
+    for account in [amm.vault.pubkey(), pool.escrow].iter() {
+        assert_eq!(token_balance(&mut context.banks_client, account).await, 0);
+    }
+    pool.assert_circulation(&mut context.banks_client, 0).await;
+}
+
+#[tokio::test]
+async fn test_amm_rejections() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let owner = pool.owner.pubkey();
+    let owner_account = pool.collateral_account(&mut context, &owner, 10_000).await;
+    let mallory = pool.trader(&mut context, 10_000).await;
+    let amm = AmmAccounts::new();
+
+    // Only the owner sets up the market maker, with a subsidy that covers its worst case loss,
+    // b * ln(2) complete sets
+    let other = AmmAccounts::new();
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.initialize_amm(
+                &other,
+                mallory.wallet.pubkey(),
+                mallory.collateral,
+                10,
+                10 * UNIT
+            )],
+            &[&other.vault, &other.long, &other.short, &mallory.wallet],
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.initialize_amm(&other, owner, owner_account, 10, 693)],
+            &[&other.vault, &other.long, &other.short, &pool.owner],
+        )
+        .await,
+        program_error(BinaryOptionError::InsufficientSubsidy)
+    );
+    process(
+        &mut context,
+        &[pool.initialize_amm(&amm, owner, owner_account, 10, 694)],
+        &[&amm.vault, &amm.long, &amm.short, &pool.owner],
+    )
+    .await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.initialize_amm(&other, owner, owner_account, 10, 10 * UNIT)],
+            &[&other.vault, &other.long, &other.short, &pool.owner],
+        )
+        .await,
+        program_error(BinaryOptionError::AmmAlreadyInitialized)
+    );
+
+    // Trades that would cost more, or pay out less, than the limit fail
+    let buy =
+        |trader, size, limit| pool.amm_trade(&amm, true, trader, PositionSide::Long, size, limit);
+    let sell =
+        |trader, size, limit| pool.amm_trade(&amm, false, trader, PositionSide::Long, size, limit);
+    let slippage = program_error(BinaryOptionError::SlippageExceeded);
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[buy(&alice, 5, 5 * UNIT / 2)],
+            &[&alice.wallet]
+        )
+        .await,
+        slippage
+    );
+    process(&mut context, &[buy(&alice, 5, 5 * UNIT)], &[&alice.wallet]).await;
+    assert_eq!(
+        try_process(&mut context, &[sell(&alice, 5, 5 * UNIT)], &[&alice.wallet]).await,
+        slippage
+    );
+    // Nobody can sell back more than the market maker sold
+    process(
+        &mut context,
+        &[pool.mint_set(&mallory, 1)],
+        &[&mallory.wallet],
+    )
+    .await;
+    assert_eq!(
+        try_process(&mut context, &[sell(&mallory, 6, 0)], &[&mallory.wallet]).await,
+        program_error(BinaryOptionError::InvalidSupply)
+    );
+
+    // The market maker stops trading with the pool, and only pays out once it is settled
+    process(
+        &mut context,
+        &[instruction::halt(id(), pool.address, owner)],
+        &[&pool.owner],
+    )
+    .await;
+    assert_eq!(
+        try_process(&mut context, &[buy(&alice, 1, UNIT)], &[&alice.wallet]).await,
+        program_error(BinaryOptionError::TradingClosed)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.amm_withdraw(&amm, owner, owner_account)],
+            &[&pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::BetNotSettled)
+    );
+    pool.settle(&mut context, pool.long_mint).await;
+    assert_eq!(
+        try_process(&mut context, &[sell(&alice, 1, 0)], &[&alice.wallet]).await,
+        program_error(BinaryOptionError::AlreadySettled)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.amm_withdraw(&amm, mallory.wallet.pubkey(), mallory.collateral)],
+            &[&mallory.wallet]
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+
+    // Withdrawing empties the vault, after which there is nothing left to withdraw
+    process(
+        &mut context,
+        &[pool.amm_withdraw(&amm, owner, owner_account)],
+        &[&pool.owner],
+    )
+    .await;
+    let withdrawn = token_balance(&mut context.banks_client, &owner_account).await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &amm.vault.pubkey()).await,
+        0
+    );
+    process(
+        &mut context,
+        &[pool.amm_withdraw(&amm, owner, owner_account)],
+        &[&pool.owner],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &owner_account).await,
+        withdrawn
+    );
+}
+
+#[tokio::test]
+async fn test_cpmm() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    let cpmm = CpmmAccounts::new();
+    let mut signers = cpmm.signers().to_vec();
+    signers.push(&pool.owner);
+    process(
+        &mut context,
+        &[pool.initialize_cpmm(&cpmm, pool.owner.pubkey(), 30)],
+        &signers,
+    )
+    .await;
+    let alice_lp =
+        create_token_account(&mut context, &cpmm.lp_mint.pubkey(), &alice.wallet.pubkey()).await;
+
+    // The first provider gets one share per unit of collateral
+    process(
+        &mut context,
+        &[pool.cpmm_liquidity(&cpmm, true, &alice, alice_lp, 20 * UNIT)],
+        &[&alice.wallet],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &alice_lp).await,
+        20 * UNIT
+    );
+
+    // Bob buys shorts, which the pool mints as complete sets, keeping the longs
+    process(
+        &mut context,
+        &[
+            pool.cpmm_trade(&cpmm, true, &bob, PositionSide::Short, 5, 5 * UNIT),
+            pool.cpmm_trade(&cpmm, false, &bob, PositionSide::Short, 2, 0),
+        ],
+        &[&bob.wallet],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &bob.short).await,
+        3
+    );
+    pool.assert_circulation(&mut context.banks_client, 3).await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &cpmm.long.pubkey()).await,
+        3
+    );
+    // Both trades paid the pool's fee, which stays with the liquidity providers
+    assert!(
+        cpmm.state(&mut context.banks_client)
+            .await
+            .total_fees_accrued
+            > 0
+    );
+
+    // Removing every share empties the pool, and no collateral was created or lost
+    process(
+        &mut context,
+        &[pool.cpmm_liquidity(&cpmm, false, &alice, alice_lp, 20 * UNIT)],
+        &[&alice.wallet],
+    )
+    .await;
+    assert_eq!(
+        mint_supply(&mut context.banks_client, &cpmm.lp_mint.pubkey()).await,
+        0
+    );
+    for account in [cpmm.vault.pubkey(), cpmm.long.pubkey(), cpmm.short.pubkey()].iter() {
+        assert_eq!(token_balance(&mut context.banks_client, account).await, 0);
+    }
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await.long,
+        3
+    );
+    let mut collateral = token_balance(&mut context.banks_client, &pool.escrow).await;
+    for trader in [&alice, &bob].iter() {
+        collateral += token_balance(&mut context.banks_client, &trader.collateral).await;
+    }
+    assert_eq!(collateral, 20_000);
+}
+
+#[tokio::test]
+async fn test_cpmm_rejections() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    let owner = pool.owner.pubkey();
+    let cpmm = CpmmAccounts::new();
+    let other = CpmmAccounts::new();
+    let mut cpmm_signers = cpmm.signers().to_vec();
+    cpmm_signers.push(&pool.owner);
+    let mut other_signers = other.signers().to_vec();
+    other_signers.push(&pool.owner);
+
+    // Only the owner can create the market maker, whose fee is capped like the pool's, and a pool
+    // has one market maker whose vault it checks before closing
+    let mallory = Keypair::new();
+    let mut mallory_signers = other.signers().to_vec();
+    mallory_signers.push(&mallory);
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.initialize_cpmm(&other, mallory.pubkey(), 30)],
+            &mallory_signers,
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.initialize_cpmm(&other, owner, MAX_FEE_BPS + 1)],
+            &other_signers,
+        )
+        .await,
+        program_error(BinaryOptionError::FeeTooHigh)
+    );
+    process(
+        &mut context,
+        &[pool.initialize_cpmm(&cpmm, owner, 30)],
+        &cpmm_signers,
+    )
+    .await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.initialize_cpmm(&other, owner, 30)],
+            &other_signers,
+        )
+        .await,
+        program_error(BinaryOptionError::CpmmAlreadyInitialized)
+    );
+
+    // Nothing trades against an empty pool
+    let buy = |size, limit| pool.cpmm_trade(&cpmm, true, &bob, PositionSide::Long, size, limit);
+    let sell = |size, limit| pool.cpmm_trade(&cpmm, false, &bob, PositionSide::Long, size, limit);
+    assert_eq!(
+        try_process(&mut context, &[buy(1, UNIT)], &[&bob.wallet]).await,
+        program_error(BinaryOptionError::InsufficientLiquidity)
+    );
+    let alice_lp =
+        create_token_account(&mut context, &cpmm.lp_mint.pubkey(), &alice.wallet.pubkey()).await;
+    let liquidity = |add, provider: &Trader, amount| {
+        pool.cpmm_liquidity(&cpmm, add, provider, alice_lp, amount)
+    };
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[liquidity(true, &alice, 0)],
+            &[&alice.wallet]
+        )
+        .await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::InvalidArgument
+        ))
+    );
+    process(
+        &mut context,
+        &[liquidity(true, &alice, 20 * UNIT)],
+        &[&alice.wallet],
+    )
+    .await;
+
+    // Trades that would cost more, or pay out less, than the limit fail
+    let slippage = program_error(BinaryOptionError::SlippageExceeded);
+    assert_eq!(
+        try_process(&mut context, &[buy(5, 5 * UNIT / 2)], &[&bob.wallet]).await,
+        slippage
+    );
+    process(&mut context, &[buy(5, 5 * UNIT)], &[&bob.wallet]).await;
+    assert_eq!(
+        try_process(&mut context, &[sell(5, 5 * UNIT)], &[&bob.wallet]).await,
+        slippage
+    );
+
+    // Shares are only burned by their holder, and only as many as exist
+    assert_eq!(
+        try_process(&mut context, &[liquidity(false, &bob, 1)], &[&bob.wallet]).await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[liquidity(false, &alice, 20 * UNIT + 1)],
+            &[&alice.wallet]
+        )
+        .await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::InvalidArgument
+        ))
+    );
+
+    // Trading stops with the pool, and no liquidity is added once it is settled, but providers
+    // can still withdraw
+    process(
+        &mut context,
+        &[instruction::halt(id(), pool.address, owner)],
+        &[&pool.owner],
+    )
+    .await;
+    assert_eq!(
+        try_process(&mut context, &[sell(1, 0)], &[&bob.wallet]).await,
+        program_error(BinaryOptionError::TradingClosed)
+    );
+    pool.settle(&mut context, pool.long_mint).await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[liquidity(true, &alice, UNIT)],
+            &[&alice.wallet]
+        )
+        .await,
+        program_error(BinaryOptionError::AlreadySettled)
+    );
+    assert_eq!(
+        try_process(&mut context, &[buy(1, UNIT)], &[&bob.wallet]).await,
+        program_error(BinaryOptionError::AlreadySettled)
+    );
+    process(
+        &mut context,
+        &[liquidity(false, &alice, 20 * UNIT)],
+        &[&alice.wallet],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &cpmm.vault.pubkey()).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_oracle_settlement() {
+    let mut context = program_test().start_with_context().await;
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let expiry = clock.unix_timestamp + 60;
+    let max_publish_delay = 30;
+    let pool = Pool::initialize_with_expiry(&mut context, expiry).await;
+    let mallory = Keypair::new();
+    let oracle_feed = Pubkey::new_unique();
+    let configure = |owner: Pubkey, oracle_program| {
+        instruction::configure_oracle(
+            id(),
+            pool.address,
+            oracle_feed,
+            owner,
+            100,
+            Comparison::GreaterThan,
+            1_000,
+            100,
+            max_publish_delay,
+            oracle_program,
+            UNIT / 2,
+        )
+    };
+
+    // Only feeds of the Pyth program can settle a pool, even if they belong to the program passed
+    let unsupported_program = Pubkey::new_unique();
+    set_oracle_price(
+        &mut context,
+        oracle_feed,
+        unsupported_program,
+        200,
+        clock.slot,
+        expiry - 1,
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[configure(pool.owner.pubkey(), unsupported_program)],
+            &[&pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::UnsupportedOracleProgram)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[configure(pool.owner.pubkey(), PYTH_PROGRAM_ID)],
+            &[&pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::IncorrectOwner)
+    );
+    set_oracle_price(
+        &mut context,
+        oracle_feed,
+        PYTH_PROGRAM_ID,
+        200,
+        clock.slot,
+        expiry - 1,
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[configure(mallory.pubkey(), PYTH_PROGRAM_ID)],
+            &[&mallory]
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+    process(
+        &mut context,
+        &[configure(pool.owner.pubkey(), PYTH_PROGRAM_ID)],
+        &[&pool.owner],
+    )
+    .await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.oracle_program_id, PYTH_PROGRAM_ID);
+    assert_eq!(state.oracle_max_publish_delay, max_publish_delay);
+
+    // The owner can't settle the pool anymore, and the feed only settles it after expiry
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[instruction::settle(
+                id(),
+                pool.address,
+                pool.short_mint,
+                pool.owner.pubkey()
+            )],
+            &[&pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidResolutionSource)
+    );
+    let settle = |oracle_feed| instruction::settle_from_oracle(id(), pool.address, oracle_feed);
+    assert_eq!(
+        try_process(&mut context, &[settle(oracle_feed)], &[]).await,
+        program_error(BinaryOptionError::MarketNotExpired)
+    );
+
+    clock.unix_timestamp += 90;
+    context.set_sysvar(&clock);
+    // Only the configured feed is read
+    let other_feed = Pubkey::new_unique();
+    set_oracle_price(
+        &mut context,
+        other_feed,
+        PYTH_PROGRAM_ID,
+        200,
+        clock.slot,
+        expiry,
+    );
+    assert_eq!(
+        try_process(&mut context, &[settle(other_feed)], &[]).await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+
+    let not_at_expiry = program_error(BinaryOptionError::OraclePriceNotAtExpiry);
+    // The price was published before expiry
+    assert_eq!(
+        try_process(&mut context, &[settle(oracle_feed)], &[]).await,
+        not_at_expiry
+    );
+
+    // The price was published too long after expiry
+    set_oracle_price(
+        &mut context,
+        oracle_feed,
+        PYTH_PROGRAM_ID,
+        200,
+        clock.slot,
+        expiry + max_publish_delay + 1,
+    );
+    assert_eq!(
+        try_process(&mut context, &[settle(oracle_feed)], &[]).await,
+        not_at_expiry
+    );
+
+    // The price was published in time, but too many slots ago
+    set_oracle_price(
+        &mut context,
+        oracle_feed,
+        PYTH_PROGRAM_ID,
+        200,
+        clock.slot,
+        expiry + max_publish_delay,
+    );
+    let mut later = clock.clone();
+    later.slot += 1_001;
+    context.set_sysvar(&later);
+    assert_eq!(
+        try_process(&mut context, &[settle(oracle_feed)], &[]).await,
+        program_error(BinaryOptionError::StaleOraclePrice)
+    );
+
+    // Any recent price published within the delay settles the pool, once
+    context.set_sysvar(&clock);
+    process(&mut context, &[settle(oracle_feed)], &[]).await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.winning_side_pubkey, pool.long_mint);
+    assert_eq!(
+        try_process(&mut context, &[settle(oracle_feed)], &[]).await,
+        program_error(BinaryOptionError::AlreadySettled)
+    );
+}
+
+#[tokio::test]
+async fn test_optimistic_resolution() {
+    let mut context = program_test().start_with_context().await;
+    let arbiter = Keypair::new();
+    let mallory = Keypair::new();
+    let dispute_window = 3_600;
+    let initialize = |pool: &Pool, resolver: &Keypair, bond_vault: &Keypair| {
+        instruction::initialize_resolver(
+            id(),
+            pool.address,
+            resolver.pubkey(),
+            bond_vault.pubkey(),
+            pool.escrow_mint,
+            arbiter.pubkey(),
+            pool.escrow_authority,
+            pool.owner.pubkey(),
+            UNIT,
+            dispute_window,
+            UNIT / 2,
+        )
+    };
+    let propose = |pool: &Pool, resolver: &Keypair, bond_vault: &Keypair, proposer: &Trader| {
+        instruction::propose_outcome(
+            id(),
+            pool.address,
+            resolver.pubkey(),
+            bond_vault.pubkey(),
+            proposer.wallet.pubkey(),
+            proposer.collateral,
+            Outcome::Long,
+        )
+    };
+    let dispute = |pool: &Pool, resolver: &Keypair, bond_vault: &Keypair, disputer: &Trader| {
+        instruction::dispute_outcome(
+            id(),
+            pool.address,
+            resolver.pubkey(),
+            bond_vault.pubkey(),
+            disputer.wallet.pubkey(),
+            disputer.collateral,
+        )
+    };
+    let finalize = |pool: &Pool, resolver: &Keypair, bond_vault: &Keypair, proposer: &Trader| {
+        instruction::finalize_outcome(
+            id(),
+            pool.address,
+            resolver.pubkey(),
+            bond_vault.pubkey(),
+            proposer.collateral,
+            pool.escrow_authority,
+        )
+    };
+    let halt = |pool: &Pool| instruction::halt(id(), pool.address, pool.owner.pubkey());
+
+    // The arbiter settles a disputed pool, and the side that was right takes both bonds
+    let pool = Pool::initialize(&mut context).await;
+    let resolver = Keypair::new();
+    let bond_vault = Keypair::new();
+    process(
+        &mut context,
+        &[initialize(&pool, &resolver, &bond_vault), halt(&pool)],
+        &[&resolver, &bond_vault, &pool.owner],
+    )
+    .await;
+    let proposer = pool.trader(&mut context, UNIT).await;
+    let disputer = pool.trader(&mut context, UNIT).await;
+    process(
+        &mut context,
+        &[
+            propose(&pool, &resolver, &bond_vault, &proposer),
+            dispute(&pool, &resolver, &bond_vault, &disputer),
+        ],
+        &[&proposer.wallet, &disputer.wallet],
+    )
+    .await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[finalize(&pool, &resolver, &bond_vault, &proposer)],
+            &[]
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidProposalState)
+    );
+    let resolve_dispute = |arbiter: Pubkey| {
+        instruction::resolve_dispute(
+            id(),
+            pool.address,
+            resolver.pubkey(),
+            bond_vault.pubkey(),
+            proposer.collateral,
+            disputer.collateral,
+            arbiter,
+            pool.escrow_authority,
+            Outcome::Short,
+        )
+    };
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[resolve_dispute(mallory.pubkey())],
+            &[&mallory]
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+    process(
+        &mut context,
+        &[resolve_dispute(arbiter.pubkey())],
+        &[&arbiter],
+    )
+    .await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.winning_side_pubkey, pool.short_mint);
+    assert_eq!(
+        token_balance(&mut context.banks_client, &disputer.collateral).await,
+        2 * UNIT
+    );
+
+    // An undisputed proposal settles the pool once the dispute window is over
+    let pool = Pool::initialize(&mut context).await;
+    let resolver = Keypair::new();
+    let bond_vault = Keypair::new();
+    process(
+        &mut context,
+        &[initialize(&pool, &resolver, &bond_vault)],
+        &[&resolver, &bond_vault, &pool.owner],
+    )
+    .await;
+    let (other_resolver, other_bond_vault) = (Keypair::new(), Keypair::new());
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[initialize(&pool, &other_resolver, &other_bond_vault)],
+            &[&other_resolver, &other_bond_vault, &pool.owner],
+        )
+        .await,
+        program_error(BinaryOptionError::ResolverAlreadyInitialized)
+    );
+
+    // Outcomes are only proposed and finalized while trading is stopped
+    let proposer = pool.trader(&mut context, UNIT).await;
+    let not_expired = program_error(BinaryOptionError::MarketNotExpired);
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[propose(&pool, &resolver, &bond_vault, &proposer)],
+            &[&proposer.wallet]
+        )
+        .await,
+        not_expired
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[finalize(&pool, &resolver, &bond_vault, &proposer)],
+            &[]
+        )
+        .await,
+        not_expired
+    );
+
+    // The owner can resume trading until an outcome is proposed, which the resolver tells
+    let resume = |resolver_account| {
+        instruction::resume(id(), pool.address, pool.owner.pubkey(), resolver_account)
+    };
+    process(&mut context, &[halt(&pool)], &[&pool.owner]).await;
+    assert_eq!(
+        try_process(&mut context, &[resume(None)], &[&pool.owner]).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::NotEnoughAccountKeys
+        ))
+    );
+    process(
+        &mut context,
+        &[resume(Some(resolver.pubkey())), halt(&pool)],
+        &[&pool.owner],
+    )
+    .await;
+    process(
+        &mut context,
+        &[propose(&pool, &resolver, &bond_vault, &proposer)],
+        &[&proposer.wallet],
+    )
+    .await;
+    let proposal_state = program_error(BinaryOptionError::InvalidProposalState);
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[resume(Some(resolver.pubkey()))],
+            &[&pool.owner]
+        )
+        .await,
+        proposal_state
+    );
+    let other_proposer = pool.trader(&mut context, UNIT).await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[propose(&pool, &resolver, &bond_vault, &other_proposer)],
+            &[&other_proposer.wallet]
+        )
+        .await,
+        proposal_state
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[finalize(&pool, &resolver, &bond_vault, &proposer)],
+            &[]
+        )
+        .await,
+        program_error(BinaryOptionError::DisputeWindowOpen)
+    );
+
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += dispute_window;
+    context.set_sysvar(&clock);
+    let disputer = pool.trader(&mut context, UNIT).await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[dispute(&pool, &resolver, &bond_vault, &disputer)],
+            &[&disputer.wallet]
+        )
+        .await,
+        program_error(BinaryOptionError::DisputeWindowClosed)
+    );
+    process(
+        &mut context,
+        &[finalize(&pool, &resolver, &bond_vault, &proposer)],
+        &[],
+    )
+    .await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.winning_side_pubkey, pool.long_mint);
+    assert_eq!(
+        token_balance(&mut context.banks_client, &proposer.collateral).await,
+        UNIT
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[finalize(&pool, &resolver, &bond_vault, &proposer)],
+            &[]
+        )
+        .await,
+        program_error(BinaryOptionError::AlreadySettled)
+    );
+}
+
+#[tokio::test]
+async fn test_committee_resolution() {
+    let mut context = program_test().start_with_context().await;
+    // Each outcome is first outvoted, until a voter changes their vote
+    let outcomes = [
+        (Outcome::Long, Outcome::Short, 9_700 + 10 * UNIT, 9_300),
+        (Outcome::Short, Outcome::Void, 9_700, 9_300 + 10 * UNIT),
+        (
+            Outcome::Void,
+            Outcome::Long,
+            9_700 + 10 * 30,
+            9_300 + 10 * 70,
+        ),
+    ];
+    for (outcome, other, alice_collateral, bob_collateral) in outcomes.iter() {
+        let pool = Pool::initialize(&mut context).await;
+        let committee = Keypair::new();
+        let voters = [Keypair::new(), Keypair::new(), Keypair::new()];
+        let voter_keys: Vec<Pubkey> = voters.iter().map(|voter| voter.pubkey()).collect();
+        process(
+            &mut context,
+            &[instruction::initialize_committee(
+                id(),
+                pool.address,
+                committee.pubkey(),
+                pool.owner.pubkey(),
+                &voter_keys,
+                2,
+                30,
+            )],
+            &[&committee, &pool.owner],
+        )
+        .await;
+        let state = pool.state(&mut context.banks_client).await;
+        assert_eq!(state.committee_account_pubkey, committee.pubkey());
+        let alice = pool.trader(&mut context, 10_000).await;
+        let bob = pool.trader(&mut context, 10_000).await;
+        pool.trade(&mut context, &alice, &bob, 10, 30).await;
+        process(
+            &mut context,
+            &[instruction::halt(id(), pool.address, pool.owner.pubkey())],
+            &[&pool.owner],
+        )
+        .await;
+
+        let vote = |voter: &Keypair, outcome| {
+            instruction::vote(
+                id(),
+                pool.address,
+                committee.pubkey(),
+                voter.pubkey(),
+                outcome,
+            )
+        };
+        process(
+            &mut context,
+            &[vote(&voters[0], *other), vote(&voters[1], *outcome)],
+            &[&voters[0], &voters[1]],
+        )
+        .await;
+        // No outcome has reached the threshold yet
+        let state = pool.state(&mut context.banks_client).await;
+        assert_eq!(state.status, PoolStatus::Halted);
+        process(&mut context, &[vote(&voters[0], *outcome)], &[&voters[0]]).await;
+        let state = pool.state(&mut context.banks_client).await;
+        match outcome {
+            Outcome::Long => assert_eq!(state.winning_side_pubkey, pool.long_mint),
+            Outcome::Short => assert_eq!(state.winning_side_pubkey, pool.short_mint),
+            // A void outcome pays out the price the committee was set up with
+            Outcome::Void => assert_eq!(state.status, PoolStatus::Voided),
+        }
+        pool.collect(&mut context, &alice).await;
+        pool.collect(&mut context, &bob).await;
+        assert_eq!(
+            pool.position(&mut context.banks_client, &alice).await,
+            position(*alice_collateral, 0, 0)
+        );
+        assert_eq!(
+            pool.position(&mut context.banks_client, &bob).await,
+            position(*bob_collateral, 0, 0)
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_committee_rejections() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let owner = pool.owner.pubkey();
+    let committee = Keypair::new();
+    let voters = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let voter_keys: Vec<Pubkey> = voters.iter().map(|voter| voter.pubkey()).collect();
+    let initialize = |committee: &Keypair, voters: &[Pubkey]| {
+        instruction::initialize_committee(
+            id(),
+            pool.address,
+            committee.pubkey(),
+            owner,
+            voters,
+            2,
+            UNIT / 2,
+        )
+    };
+
+    // A voter can't count twice, and a pool only has one committee
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[initialize(
+                &committee,
+                &[voter_keys[0], voter_keys[1], voter_keys[0]]
+            )],
+            &[&committee, &pool.owner],
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeysShouldBeUnique)
+    );
+    process(
+        &mut context,
+        &[initialize(&committee, &voter_keys)],
+        &[&committee, &pool.owner],
+    )
+    .await;
+    let other_committee = Keypair::new();
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[initialize(&other_committee, &voter_keys)],
+            &[&other_committee, &pool.owner],
+        )
+        .await,
+        program_error(BinaryOptionError::CommitteeAlreadyInitialized)
+    );
+
+    // Votes are only cast once trading has stopped, and the owner can't settle the pool
+    let vote = |committee: Pubkey, voter: &Keypair| {
+        instruction::vote(id(), pool.address, committee, voter.pubkey(), Outcome::Long)
+    };
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[vote(committee.pubkey(), &voters[0])],
+            &[&voters[0]]
+        )
+        .await,
+        program_error(BinaryOptionError::MarketNotExpired)
+    );
+    process(
+        &mut context,
+        &[instruction::halt(id(), pool.address, owner)],
+        &[&pool.owner],
+    )
+    .await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[instruction::settle(
+                id(),
+                pool.address,
+                pool.long_mint,
+                owner
+            )],
+            &[&pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidResolutionSource)
+    );
+
+    // Only voters of the committee the pool records can vote
+    let mallory = Keypair::new();
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[vote(committee.pubkey(), &mallory)],
+            &[&mallory]
+        )
+        .await,
+        program_error(BinaryOptionError::NotAVoter)
+    );
+    let stale_committee = Pubkey::new_unique();
+    let account = context
+        .banks_client
+        .get_account(committee.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    context.set_account(&stale_committee, &account.into());
+    for voter in voters.iter().take(2) {
+        assert_eq!(
+            try_process(&mut context, &[vote(stale_committee, voter)], &[voter]).await,
+            program_error(BinaryOptionError::PublicKeyMismatch)
+        );
+    }
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.status, PoolStatus::Halted);
+
+    // No vote counts once the pool is settled
+    for voter in voters.iter().take(2) {
+        process(&mut context, &[vote(committee.pubkey(), voter)], &[voter]).await;
+    }
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.winning_side_pubkey, pool.long_mint);
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[vote(committee.pubkey(), &voters[2])],
+            &[&voters[2]]
+        )
+        .await,
+        program_error(BinaryOptionError::AlreadySettled)
+    );
+}
+
+#[tokio::test]
+async fn test_categorical_market() {
+    let mut context = program_test().start_with_context().await;
+    // Only the collateral mint and the owner of the pool are used
+    let pool = Pool::initialize(&mut context).await;
+    let market = Categorical::initialize(&mut context, &pool, 3, 0).await;
+    let alice = market.trader(&mut context, &pool, 10_000).await;
+    let bob = market.trader(&mut context, &pool, 10_000).await;
+    let carol = market.trader(&mut context, &pool, 10_000).await;
+
+    // Alice sells the outcomes she doesn't believe in out of complete sets
+    process(
+        &mut context,
+        &[market.mint_set(&alice, 10), market.redeem_set(&alice, 1)],
+        &[&alice.wallet],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &market.escrow).await,
+        9 * UNIT
+    );
+    process(
+        &mut context,
+        &[
+            market.trade(&bob, &alice, 0, 4, 40),
+            market.trade(&carol, &alice, 1, 3, 30),
+        ],
+        &[&alice.wallet, &bob.wallet, &carol.wallet],
+    )
+    .await;
+
+    // Only the tokens of the winning outcome pay out, the last collector empties the escrow
+    process(
+        &mut context,
+        &[market.settle(pool.owner.pubkey(), 1)],
+        &[&pool.owner],
+    )
+    .await;
+    assert_eq!(
+        try_process(&mut context, &[market.collect(&bob, 0)], &[&bob.wallet]).await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+    for trader in [&alice, &bob, &carol].iter() {
+        process(
+            &mut context,
+            &[market.collect(trader, 1)],
+            &[&trader.wallet],
+        )
+        .await;
+    }
+    let collateral = [
+        10_000 - 9 * UNIT + 4 * 40 + 3 * 30 + 6 * UNIT,
+        10_000 - 4 * 40,
+        10_000 - 3 * 30 + 3 * UNIT,
+    ];
+    for (trader, collateral) in [&alice, &bob, &carol].iter().zip(collateral.iter()) {
+        assert_eq!(
+            token_balance(&mut context.banks_client, &trader.collateral).await,
+            *collateral
+        );
+        assert_eq!(
+            token_balance(&mut context.banks_client, &trader.outcomes[1]).await,
+            0
+        );
+    }
+    assert_eq!(
+        token_balance(&mut context.banks_client, &market.escrow).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_categorical_rejections() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let owner = pool.owner.pubkey();
+
+    // A market has at least two outcomes
+    let (market, escrow, mints) = (Keypair::new(), Keypair::new(), [Keypair::new()]);
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[Categorical::initialize_instruction(
+                &pool, &market, &escrow, &mints, 0
+            )],
+            &[&market, &escrow, &pool.owner, &mints[0]],
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidOutcome)
+    );
+
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let expiry = clock.unix_timestamp + 60;
+    let market = Categorical::initialize(&mut context, &pool, 3, expiry).await;
+    let alice = market.trader(&mut context, &pool, 10_000).await;
+    let bob = market.trader(&mut context, &pool, 10_000).await;
+    process(
+        &mut context,
+        &[market.mint_set(&alice, 5)],
+        &[&alice.wallet],
+    )
+    .await;
+
+    // Prices are above 0 and at most a complete set, and both sides are different traders
+    let both = [&alice.wallet, &bob.wallet];
+    let prices = program_error(BinaryOptionError::TradePricesIncorrect);
+    assert_eq!(
+        try_process(&mut context, &[market.trade(&bob, &alice, 0, 1, 0)], &both).await,
+        prices
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[market.trade(&bob, &alice, 0, 1, UNIT + 1)],
+            &both
+        )
+        .await,
+        prices
+    );
+    assert_eq!(
+        try_process(&mut context, &[market.trade(&bob, &alice, 3, 1, 40)], &both).await,
+        program_error(BinaryOptionError::InvalidOutcome)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[market.trade(&alice, &alice, 0, 1, 40)],
+            &[&alice.wallet]
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeysShouldBeUnique)
+    );
+
+    // Only the owner settles, with one of the outcomes, and nothing is collected before
+    let mallory = Keypair::new();
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[market.settle(mallory.pubkey(), 0)],
+            &[&mallory]
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+    assert_eq!(
+        try_process(&mut context, &[market.settle(owner, 3)], &[&pool.owner]).await,
+        program_error(BinaryOptionError::InvalidWinner)
+    );
+    assert_eq!(
+        try_process(&mut context, &[market.collect(&alice, 0)], &[&alice.wallet]).await,
+        program_error(BinaryOptionError::BetNotSettled)
+    );
+
+    // Trading and minting stop at expiry, but sets can still be redeemed until the market is
+    // settled, after which sets are neither minted nor traded
+    clock.unix_timestamp = expiry;
+    context.set_sysvar(&clock);
+    let closed = program_error(BinaryOptionError::TradingClosed);
+    assert_eq!(
+        try_process(&mut context, &[market.trade(&bob, &alice, 0, 1, 40)], &both).await,
+        closed
+    );
+    assert_eq!(
+        try_process(&mut context, &[market.mint_set(&bob, 1)], &[&bob.wallet]).await,
+        closed
+    );
+    process(
+        &mut context,
+        &[market.redeem_set(&alice, 1)],
+        &[&alice.wallet],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &alice.collateral).await,
+        10_000 - 4 * UNIT
+    );
+    process(&mut context, &[market.settle(owner, 0)], &[&pool.owner]).await;
+    let settled = program_error(BinaryOptionError::AlreadySettled);
+    assert_eq!(
+        try_process(&mut context, &[market.trade(&bob, &alice, 0, 1, 40)], &both).await,
+        settled
+    );
+    assert_eq!(
+        try_process(&mut context, &[market.mint_set(&bob, 1)], &[&bob.wallet]).await,
+        settled
+    );
+    assert_eq!(
+        try_process(&mut context, &[market.settle(owner, 1)], &[&pool.owner]).await,
+        settled
+    );
+    process(&mut context, &[market.collect(&alice, 0)], &[&alice.wallet]).await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &alice.collateral).await,
+        10_000
+    );
+}
+
+#[tokio::test]
+async fn test_fees() {
+    let mut context = program_test().start_with_context().await;
+    let mut pool = Pool::initialize(&mut context).await;
+    let owner = pool.owner.pubkey();
+    let protocol = Keypair::new();
+    let fee_vault = Keypair::new();
+    process(
+        &mut context,
+        &[pool.configure_fees_instruction(fee_vault.pubkey(), owner, protocol.pubkey(), 100, 50)],
+        &[&fee_vault, &pool.owner],
+    )
+    .await;
+    pool.fee_vault = Some(fee_vault.pubkey());
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    let carol = pool.trader(&mut context, 10_000).await;
+
+    // Both sides of a trade pay the fees on their notional, rounded up
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(10_000 - 300 - 3 - 2, 10, 0)
+    );
+    assert_eq!(
+        pool.position(&mut context.banks_client, &bob).await,
+        position(10_000 - 700 - 7 - 4, 0, 10)
+    );
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(
+        (state.protocol_fees_accrued, state.creator_fees_accrued),
+        (10, 6)
+    );
+
+    // The market maker charges them on top of the cost
+    let owner_account = pool.collateral_account(&mut context, &owner, 10_000).await;
+    let amm = AmmAccounts::new();
+    process(
+        &mut context,
+        &[
+            pool.initialize_amm(&amm, owner, owner_account, 10, 10 * UNIT),
+            pool.amm_trade(&amm, true, &carol, PositionSide::Long, 1, UNIT),
+        ],
+        &[
+            &amm.vault,
+            &amm.long,
+            &amm.short,
+            &pool.owner,
+            &carol.wallet,
+        ],
+    )
+    .await;
+    let paid = 10_000 - token_balance(&mut context.banks_client, &carol.collateral).await;
+    let amm_state = pool.state(&mut context.banks_client).await;
+    let fees = (
+        amm_state.protocol_fees_accrued - 10,
+        amm_state.creator_fees_accrued - 6,
+    );
+    assert!(fees.0 > 0 && fees.1 > 0);
+    assert_eq!(fees, state.fees(paid - fees.0 - fees.1));
+
+    // Orders escrow the fees of a contract at their limit price, 2 for both of these orders
+    let book = pool.initialize_order_book(&mut context).await;
+    let dave = pool.trader(&mut context, 10_000).await;
+    let erin = pool.trader(&mut context, 10_000).await;
+    process(
+        &mut context,
+        &[
+            pool.place_order(&book, &erin, OrderSide::Ask, 30, 4),
+            pool.place_order(&book, &dave, OrderSide::Bid, 40, 10),
+        ],
+        &[&erin.wallet, &dave.wallet],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &book.vault).await,
+        4 * (70 + 2) + 10 * (40 + 2)
+    );
+
+    // A match charges both sides the fees on the notional they trade at, like a trade, and
+    // refunds the rest of their escrow
+    process(
+        &mut context,
+        &[pool.match_orders(&book, &dave, &erin, 1, 0)],
+        &[],
+    )
+    .await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &dave).await,
+        position(10_000 - 120 - 2 - 1 - 6 * (40 + 2), 4, 0)
+    );
+    assert_eq!(
+        pool.position(&mut context.banks_client, &erin).await,
+        position(10_000 - 280 - 3 - 2, 0, 4)
+    );
+    let book_state = pool.state(&mut context.banks_client).await;
+    assert_eq!(
+        (
+            book_state.protocol_fees_accrued - amm_state.protocol_fees_accrued,
+            book_state.creator_fees_accrued - amm_state.creator_fees_accrued,
+        ),
+        (2 + 3, 1 + 2)
+    );
+    process(
+        &mut context,
+        &[pool.cancel_order(&book, &dave, 1)],
+        &[&dave.wallet],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &dave.collateral).await,
+        10_000 - 120 - 2 - 1
+    );
+    assert_eq!(
+        token_balance(&mut context.banks_client, &book.vault).await,
+        0
+    );
+
+    // Collecting pays them on the payout
+    pool.settle(&mut context, pool.long_mint).await;
+    pool.collect(&mut context, &alice).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(10_000 - 305 + 10 * UNIT - 10 - 5, 0, 0)
+    );
+
+    // Each recipient withdraws their share of the vault
+    let state = pool.state(&mut context.banks_client).await;
+    let (protocol_fees, creator_fees) = (state.protocol_fees_accrued, state.creator_fees_accrued);
+    assert_eq!(
+        token_balance(&mut context.banks_client, &fee_vault.pubkey()).await,
+        protocol_fees + creator_fees
+    );
+    let protocol_account = pool
+        .collateral_account(&mut context, &protocol.pubkey(), 0)
+        .await;
+    process(
+        &mut context,
+        &[pool.withdraw_fees(protocol.pubkey(), protocol_account)],
+        &[&protocol],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &protocol_account).await,
+        protocol_fees
+    );
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(
+        (state.protocol_fees_accrued, state.creator_fees_accrued),
+        (0, creator_fees)
+    );
+    let owner_collateral = token_balance(&mut context.banks_client, &owner_account).await;
+    process(
+        &mut context,
+        &[pool.withdraw_fees(owner, owner_account)],
+        &[&pool.owner],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &owner_account).await,
+        owner_collateral + creator_fees
+    );
+    assert_eq!(
+        token_balance(&mut context.banks_client, &fee_vault.pubkey()).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_fees_rejections() {
+    let mut context = program_test().start_with_context().await;
+    let mut pool = Pool::initialize(&mut context).await;
+    let owner = pool.owner.pubkey();
+    let mallory = Keypair::new();
+    let fee_vault = Keypair::new();
+    let configure = |pool: &Pool, pool_owner, protocol_fee_bps| {
+        pool.configure_fees_instruction(
+            fee_vault.pubkey(),
+            pool_owner,
+            owner,
+            protocol_fee_bps,
+            500,
+        )
+    };
+    let owner_account = pool.collateral_account(&mut context, &owner, 0).await;
+    let mallory_account = pool
+        .collateral_account(&mut context, &mallory.pubkey(), 0)
+        .await;
+    let book = pool.initialize_order_book(&mut context).await;
+    let carol = pool.trader(&mut context, 10_000).await;
+    let dave = pool.trader(&mut context, 10_000).await;
+    process(
+        &mut context,
+        &[
+            pool.place_order(&book, &carol, OrderSide::Bid, 40, 5),
+            pool.place_order(&book, &dave, OrderSide::Ask, 40, 5),
+        ],
+        &[&carol.wallet, &dave.wallet],
+    )
+    .await;
+
+    // Only the owner sets up fees, once, and they are capped
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.withdraw_fees(owner, owner_account)],
+            &[&pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::NotAFeeRecipient)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[configure(&pool, mallory.pubkey(), 100)],
+            &[&fee_vault, &mallory]
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[configure(&pool, owner, MAX_FEE_BPS - 499)],
+            &[&fee_vault, &pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::FeeTooHigh)
+    );
+    process(
+        &mut context,
+        &[configure(&pool, owner, MAX_FEE_BPS - 500)],
+        &[&fee_vault, &pool.owner],
+    )
+    .await;
+    pool.fee_vault = Some(fee_vault.pubkey());
+    let other_vault = Keypair::new();
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.configure_fees_instruction(other_vault.pubkey(), owner, owner, 0, 0)],
+            &[&other_vault, &pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::FeesAlreadyConfigured)
+    );
+
+    // Orders placed before the fees escrowed none, so they can only be cancelled
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.match_orders(&book, &carol, &dave, 0, 1)],
+            &[]
+        )
+        .await,
+        program_error(BinaryOptionError::FeesNotSupported)
+    );
+    process(
+        &mut context,
+        &[
+            pool.cancel_order(&book, &carol, 0),
+            pool.cancel_order(&book, &dave, 1),
+        ],
+        &[&carol.wallet, &dave.wallet],
+    )
+    .await;
+    for trader in [&carol, &dave].iter() {
+        assert_eq!(
+            token_balance(&mut context.banks_client, &trader.collateral).await,
+            10_000
+        );
+    }
+
+    // Traders know the fees before taking a position
+    let other_pool = Pool::initialize(&mut context).await;
+    let alice = other_pool.trader(&mut context, 10_000).await;
+    let bob = other_pool.trader(&mut context, 10_000).await;
+    other_pool.trade(&mut context, &alice, &bob, 1, 50).await;
+    let other_owner = other_pool.owner.pubkey();
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[other_pool.configure_fees_instruction(
+                other_vault.pubkey(),
+                other_owner,
+                owner,
+                0,
+                0
+            )],
+            &[&other_vault, &other_pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidPoolStatus)
+    );
+
+    // Only recipients withdraw, into their own account, and an empty vault pays out nothing
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.withdraw_fees(mallory.pubkey(), mallory_account)],
+            &[&mallory]
+        )
+        .await,
+        program_error(BinaryOptionError::NotAFeeRecipient)
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[pool.withdraw_fees(owner, mallory_account)],
+            &[&pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+    process(
+        &mut context,
+        &[pool.withdraw_fees(owner, owner_account)],
+        &[&pool.owner],
+    )
+    .await;
+    assert_eq!(
+        token_balance(&mut context.banks_client, &owner_account).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_close_pool_with_liquidity() {
+    let mut context = program_test().start_with_context().await;
+    let pool = Pool::initialize(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let owner = pool.owner.pubkey();
+    let owner_account = pool
+        .collateral_account(&mut context, &owner, 10 * UNIT)
+        .await;
+
+    // Only the owner pays for the venues, so that their rent can go back to the owner on close.
+    // Someone else funding them is turned away without being charged
+    let sponsor = Keypair::new();
+    let payer = context.payer.pubkey();
+    process(
+        &mut context,
+        &[system_instruction::transfer(
+            &payer,
+            &sponsor.pubkey(),
+            1_000_000_000,
+        )],
+        &[],
+    )
+    .await;
+    let cpmm = CpmmAccounts::new();
+    let (book_account, book_vault) = (Keypair::new(), Keypair::new());
+    let mut cpmm_signers = cpmm.signers().to_vec();
+    cpmm_signers.push(&sponsor);
+    let book_signers = vec![&book_account, &book_vault, &sponsor];
+    for (instruction, signers) in [
+        (
+            pool.initialize_cpmm(&cpmm, sponsor.pubkey(), 30),
+            cpmm_signers,
+        ),
+        (
+            instruction::initialize_order_book(
+                id(),
+                pool.address,
+                book_account.pubkey(),
+                book_vault.pubkey(),
+                pool.escrow_mint,
+                pool.escrow_authority,
+                sponsor.pubkey(),
+            ),
+            book_signers,
+        ),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            try_process(&mut context, std::slice::from_ref(instruction), signers).await,
+            program_error(BinaryOptionError::PublicKeyMismatch)
+        );
+    }
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(sponsor.pubkey())
+            .await
+            .unwrap(),
+        1_000_000_000
+    );
+
+    // The pool has every kind of venue, and a committee to settle it
+    let committee = Keypair::new();
+    let voter = Keypair::new();
+    let amm = AmmAccounts::new();
+    let mut signers = cpmm.signers().to_vec();
+    signers.extend([&committee, &amm.vault, &amm.long, &amm.short, &pool.owner].iter());
+    process(
+        &mut context,
+        &[
+            instruction::initialize_committee(
+                id(),
+                pool.address,
+                committee.pubkey(),
+                owner,
+                &[voter.pubkey()],
+                1,
+                UNIT / 2,
+            ),
+            pool.initialize_amm(&amm, owner, owner_account, 10, 10 * UNIT),
+            pool.initialize_cpmm(&cpmm, owner, 30),
+        ],
+        &signers,
+    )
+    .await;
+    let book = pool.initialize_order_book(&mut context).await;
+    let alice_lp =
+        create_token_account(&mut context, &cpmm.lp_mint.pubkey(), &alice.wallet.pubkey()).await;
+    process(
+        &mut context,
+        &[pool.cpmm_liquidity(&cpmm, true, &alice, alice_lp, 20 * UNIT)],
+        &[&alice.wallet],
+    )
+    .await;
+
+    // Nobody trades, so nothing is in circulation, but the LP's collateral is still in the vault
+    process(
+        &mut context,
+        &[
+            instruction::halt(id(), pool.address, owner),
+            instruction::vote(
+                id(),
+                pool.address,
+                committee.pubkey(),
+                voter.pubkey(),
+                Outcome::Long,
+            ),
+        ],
+        &[&pool.owner, &voter],
+    )
+    .await;
+    pool.assert_circulation(&mut context.banks_client, 0).await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(
+        state.venue_accounts(),
+        vec![
+            amm.vault.pubkey(),
+            amm.long.pubkey(),
+            amm.short.pubkey(),
+            book.vault,
+            cpmm.vault.pubkey(),
+            cpmm.long.pubkey(),
+            cpmm.short.pubkey(),
+            book.address,
+            cpmm.pool.pubkey(),
+            committee.pubkey(),
+            cpmm.lp_mint.pubkey(),
+        ]
+    );
+    let close_pool = |venue_accounts: &[Pubkey]| {
+        instruction::close_pool(
+            id(),
+            pool.address,
+            pool.escrow,
+            pool.long_mint,
+            pool.short_mint,
+            owner,
+            owner_account,
+            owner,
+            pool.escrow_authority,
+            None,
+            venue_accounts,
+        )
+    };
+    assert_eq!(
+        try_process(&mut context, &[close_pool(&[])], &[&pool.owner]).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::NotEnoughAccountKeys
+        ))
+    );
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[close_pool(&state.venue_accounts())],
+            &[&pool.owner]
+        )
+        .await,
+        program_error(BinaryOptionError::PoolNotEmpty)
+    );
+
+    // Once the LP and the market maker have withdrawn, the pool can be closed
+    process(
+        &mut context,
+        &[
+            pool.cpmm_liquidity(&cpmm, false, &alice, alice_lp, 20 * UNIT),
+            pool.amm_withdraw(&amm, owner, owner_account),
+        ],
+        &[&alice.wallet, &pool.owner],
+    )
+    .await;
+    pool.collect(&mut context, &alice).await;
+    assert_eq!(
+        pool.position(&mut context.banks_client, &alice).await,
+        position(10_000, 0, 0)
+    );
+    let mut closed = state.venue_accounts();
+    closed.retain(|account| *account != cpmm.lp_mint.pubkey());
+    closed.extend([pool.address, pool.escrow].iter());
+    let mut rent = 0;
+    for account in closed.iter() {
+        rent += context.banks_client.get_balance(*account).await.unwrap();
+    }
+    let owner_lamports = context.banks_client.get_balance(owner).await.unwrap();
+    process(
+        &mut context,
+        &[close_pool(&state.venue_accounts())],
+        &[&pool.owner],
+    )
+    .await;
+
+    // The rent of every account comes back, only the mints stay behind
+    assert_eq!(
+        context.banks_client.get_balance(owner).await.unwrap(),
+        owner_lamports + rent
+    );
+    for account in closed.iter() {
+        assert!(context
+            .banks_client
+            .get_account(*account)
+            .await
+            .unwrap()
+            .is_none());
+    }
+    assert_eq!(
+        token_balance(&mut context.banks_client, &owner_account).await,
+        10 * UNIT
+    );
+}
+
+#[tokio::test]
+async fn test_close_pool() {
+    let mut context = program_test().start_with_context().await;
+    let mut pool = Pool::initialize(&mut context).await;
+    pool.configure_fees(&mut context).await;
+    let alice = pool.trader(&mut context, 10_000).await;
+    let bob = pool.trader(&mut context, 10_000).await;
+    let owner = pool.owner.pubkey();
+    let owner_account = pool.collateral_account(&mut context, &owner, 0).await;
+    let close_pool = |pool_owner: Pubkey| {
+        instruction::close_pool(
+            id(),
+            pool.address,
+            pool.escrow,
+            pool.long_mint,
+            pool.short_mint,
+            pool_owner,
+            owner_account,
+            owner,
+            pool.escrow_authority,
+            pool.fee_vault.map(|fee_vault| (fee_vault, owner)),
+            &[],
+        )
+    };
+
+    // The pool stays until it is settled, every token is collected and the fees are withdrawn
+    pool.trade(&mut context, &alice, &bob, 10, 30).await;
+    assert_eq!(
+        try_process(&mut context, &[close_pool(owner)], &[&pool.owner]).await,
+        program_error(BinaryOptionError::BetNotSettled)
+    );
+    pool.settle(&mut context, pool.long_mint).await;
+    let not_empty = program_error(BinaryOptionError::PoolNotEmpty);
+    assert_eq!(
+        try_process(&mut context, &[close_pool(owner)], &[&pool.owner]).await,
+        not_empty
+    );
+    pool.collect(&mut context, &alice).await;
+    pool.collect(&mut context, &bob).await;
+    let state = pool.state(&mut context.banks_client).await;
+    assert_eq!(state.circulation, 0);
+    assert!(state.protocol_fees_accrued > 0);
+    assert_eq!(
+        try_process(&mut context, &[close_pool(owner)], &[&pool.owner]).await,
+        not_empty
+    );
+    process(
+        &mut context,
+        &[
+            pool.withdraw_fees(owner, owner_account),
+            instruction::sweep_dust(
+                id(),
+                pool.address,
+                pool.escrow,
+                pool.long_mint,
+                pool.short_mint,
+                owner,
+                owner_account,
+                pool.escrow_authority,
+            ),
+        ],
+        &[&pool.owner],
+    )
+    .await;
+    let mallory = Keypair::new();
+    assert_eq!(
+        try_process(&mut context, &[close_pool(mallory.pubkey())], &[&mallory]).await,
+        program_error(BinaryOptionError::PublicKeyMismatch)
+    );
+
+    // The owner paid for the pool, the escrow and the fee vault
+    assert_eq!(state.payer, owner);
+    assert_eq!(state.fee_vault_payer, owner);
+    let mut rent = 0;
+    for address in [pool.address, pool.escrow, pool.fee_vault.unwrap()] {
+        rent += context.banks_client.get_balance(address).await.unwrap();
+    }
+    let owner_lamports = context.banks_client.get_balance(owner).await.unwrap();
+    process(&mut context, &[close_pool(owner)], &[&pool.owner]).await;
+    assert!(context
+        .banks_client
+        .get_account(pool.address)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        token_balance(&mut context.banks_client, &owner_account).await,
+        state.protocol_fees_accrued + state.creator_fees_accrued
+    );
+    assert_eq!(
+        context.banks_client.get_balance(owner).await.unwrap(),
+        owner_lamports + rent
+    );
+}
+
+#[tokio::test]
+async fn test_refund_bonds() {
+    let mut context = program_test().start_with_context().await;
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let expiry = clock.unix_timestamp + 60;
+    let pool = Pool::initialize_with_expiry(&mut context, expiry).await;
+    let resolver = Keypair::new();
+    let bond_vault = Keypair::new();
+    let owner = pool.owner.pubkey();
+    process(
+        &mut context,
+        &[instruction::initialize_resolver(
+            id(),
+            pool.address,
+            resolver.pubkey(),
+            bond_vault.pubkey(),
+            pool.escrow_mint,
+            Pubkey::new_unique(),
+            pool.escrow_authority,
+            owner,
+            UNIT,
+            3_600,
+            UNIT / 2,
+        )],
+        &[&resolver, &bond_vault, &pool.owner],
+    )
+    .await;
+    // Outcomes can be proposed once the pool has expired, without halting it
+    clock.unix_timestamp = expiry;
+    context.set_sysvar(&clock);
+    let proposer = pool.trader(&mut context, UNIT).await;
+    let disputer = pool.trader(&mut context, UNIT).await;
+    process(
+        &mut context,
+        &[
+            instruction::propose_outcome(
+                id(),
+                pool.address,
+                resolver.pubkey(),
+                bond_vault.pubkey(),
+                proposer.wallet.pubkey(),
+                proposer.collateral,
+                Outcome::Long,
+            ),
+            instruction::dispute_outcome(
+                id(),
+                pool.address,
+                resolver.pubkey(),
+                bond_vault.pubkey(),
+                disputer.wallet.pubkey(),
+                disputer.collateral,
+            ),
+        ],
+        &[&proposer.wallet, &disputer.wallet],
+    )
+    .await;
+    let refund = || {
+        instruction::refund_bonds(
+            id(),
+            pool.address,
+            resolver.pubkey(),
+            bond_vault.pubkey(),
+            proposer.collateral,
+            Some(disputer.collateral),
+            pool.escrow_authority,
+        )
+    };
+    // The bonds stay in the vault while the resolver can still settle the pool
+    assert_eq!(
+        try_process(&mut context, &[refund()], &[]).await,
+        program_error(BinaryOptionError::InvalidResolutionSource)
+    );
+
+    // Nothing was minted, so the owner can hand the pool to a committee instead
+    let committee = Keypair::new();
+    process(
+        &mut context,
+        &[instruction::initialize_committee(
+            id(),
+            pool.address,
+            committee.pubkey(),
+            owner,
+            &[Pubkey::new_unique()],
+            1,
+            UNIT / 2,
+        )],
+        &[&committee, &pool.owner],
+    )
+    .await;
+    let bonder = pool.trader(&mut context, UNIT).await;
+    assert_eq!(
+        try_process(
+            &mut context,
+            &[instruction::dispute_outcome(
+                id(),
+                pool.address,
+                resolver.pubkey(),
+                bond_vault.pubkey(),
+                bonder.wallet.pubkey(),
+                bonder.collateral,
+            )],
+            &[&bonder.wallet],
+        )
+        .await,
+        program_error(BinaryOptionError::InvalidResolutionSource)
+    );
+
+    // Anyone can then return both bonds, once
+    process(&mut context, &[refund()], &[]).await;
+    for bonder in [&proposer, &disputer].iter() {
+        assert_eq!(
+            token_balance(&mut context.banks_client, &bonder.collateral).await,
+            UNIT
+        );
+    }
+    assert_eq!(
+        token_balance(&mut context.banks_client, &bond_vault.pubkey()).await,
+        0
+    );
+    assert_eq!(
+        try_process(&mut context, &[refund()], &[]).await,
+        program_error(BinaryOptionError::InvalidProposalState)
+    );
+}
+
+/// Writes a price account owned by `oracle_program` that is trading at `price`, in the mock
+/// layout when the program is built with the `mock-oracle` feature and as a Pyth v2 price
+/// account otherwise
+fn set_oracle_price(
+    context: &mut ProgramTestContext,
+    address: Pubkey,
+    oracle_program: Pubkey,
+    price: i64,
+    publish_slot: u64,
+    publish_time: i64,
+) {
+    let data = if cfg!(feature = "mock-oracle") {
+        OraclePrice {
+            price,
+            conf: 0,
+            expo: 0,
+            publish_slot,
+            publish_time,
+        }
+        .to_mock_bytes()
+        .to_vec()
+    } else {
+        let mut data = vec![0; 240];
+        data[0..4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&PYTH_VERSION_2.to_le_bytes());
+        data[8..12].copy_from_slice(&PYTH_ACCOUNT_TYPE_PRICE.to_le_bytes());
+        data[96..104].copy_from_slice(&publish_time.to_le_bytes());
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        data[224..228].copy_from_slice(&PYTH_STATUS_TRADING.to_le_bytes());
+        data[232..240].copy_from_slice(&publish_slot.to_le_bytes());
+        data
+    };
+    context.set_account(
+        &address,
+        &SolanaAccount {
+            lamports: 1_000_000_000,
+            data,
+            owner: oracle_program,
+            ..SolanaAccount::default()
+        }
+        .into(),
+    );
+}
+
+// Every instruction builder goes through the processor in at least one test above, but
+// `sweep_dust`, `propose_owner`, `accept_owner`, `settle_from_oracle`, `cpmm_remove_liquidity` and
+// `categorical_collect` only do so on their success path. Only Collect shares its account layout
+// with the processor, through `CollectAccounts`. The tests below only check the account lists the
+// builders produce
+
+#[test]
+fn test_builders_collect_layout() {
+    let keys: Vec<Pubkey> = (0..11).map(|_| Pubkey::new_unique()).collect();
+    let build = |signed: bool, fee_vault: Option<Pubkey>| {
+        let builder = if signed {
+            instruction::collect_signed
+        } else {
+            instruction::collect
+        };
+        builder(
+            id(),
+            keys[0],
+            keys[1],
+            keys[2],
+            keys[3],
+            keys[4],
+            keys[5],
+            keys[6],
+            keys[7],
+            keys[8],
+            fee_vault,
+        )
+    };
+    let expected = |collector_is_signer: bool| {
+        CollectAccounts {
+            pool: keys[0],
+            collector: keys[1],
+            collector_long_token_account: keys[2],
+            collector_short_token_account: keys[3],
+            collector_collateral_account: keys[4],
+            long_token_mint: keys[5],
+            short_token_mint: keys[6],
+            escrow_account: keys[7],
+            escrow_authority: keys[8],
+            token_program: spl_token::id(),
+        }
+        .to_account_metas(collector_is_signer)
+    };
+
+    // Only the collector's signer flag differs, and nothing follows the token program
+    assert_eq!(build(false, None).accounts, expected(false));
+    assert_eq!(build(true, None).accounts, expected(true));
+    for (index, meta) in build(true, None).accounts.iter().enumerate() {
+        assert_eq!(meta.is_signer, index == 1);
+        assert_eq!(meta.is_writable, ![1, 8, 9].contains(&index));
+    }
+
+    // The fee vault is appended last, writable
+    let mut with_fees = expected(false);
+    with_fees.push(AccountMeta::new(keys[9], false));
+    assert_eq!(build(false, Some(keys[9])).accounts, with_fees);
+}
+
+#[test]
+fn test_builders_optional_accounts() {
+    let pool = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let resolver = Pubkey::new_unique();
+    let header = vec![
+        AccountMeta::new(pool, false),
+        AccountMeta::new_readonly(owner, true),
+    ];
+    assert_eq!(
+        instruction::resume(id(), pool, owner, None).accounts,
+        header
+    );
+    let mut with_resolver = header;
+    with_resolver.push(AccountMeta::new_readonly(resolver, false));
+    assert_eq!(
+        instruction::resume(id(), pool, owner, Some(resolver)).accounts,
+        with_resolver
+    );
+
+    // ClosePool takes the fee vault and its payer, then every venue account, all writable
+    let keys: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+    let fee_vault = (Pubkey::new_unique(), Pubkey::new_unique());
+    let venue_accounts: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let close = |fee_vault: Option<(Pubkey, Pubkey)>, venue_accounts: &[Pubkey]| {
+        instruction::close_pool(
+            id(),
+            keys[0],
+            keys[1],
+            keys[2],
+            keys[3],
+            keys[4],
+            keys[5],
+            keys[6],
+            keys[7],
+            fee_vault,
+            venue_accounts,
+        )
+        .accounts
+    };
+    let bare = close(None, &[]);
+    assert_eq!(bare.len(), 9);
+    assert_eq!(bare[8], AccountMeta::new_readonly(spl_token::id(), false));
+    let full = close(Some(fee_vault), &venue_accounts);
+    assert_eq!(full[..9], bare[..]);
+    assert_eq!(full[9], AccountMeta::new(fee_vault.0, false));
+    assert_eq!(full[10], AccountMeta::new(fee_vault.1, false));
+    let venue_metas: Vec<AccountMeta> = venue_accounts
+        .iter()
+        .map(|venue_account| AccountMeta::new(*venue_account, false))
+        .collect();
+    assert_eq!(full[11..], venue_metas[..]);
+    assert_eq!(close(None, &venue_accounts)[9..], venue_metas[..]);
+}
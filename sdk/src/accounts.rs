@@ -0,0 +1,82 @@
+use {
+    crate::error::ClientResult, binary_option::state::BinaryOption, borsh::BorshDeserialize,
+    solana_sdk::pubkey::Pubkey, spl_associated_token_account::get_associated_token_address,
+};
+
+/// Program derived address that owns the escrow and the mint authority of both token mints
+pub fn escrow_authority(
+    program_id: &Pubkey,
+    long_mint: &Pubkey,
+    short_mint: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            long_mint.as_ref(),
+            short_mint.as_ref(),
+            spl_token::id().as_ref(),
+            program_id.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+/// Decodes the data of a pool account
+pub fn decode_binary_option(data: &[u8]) -> ClientResult<BinaryOption> {
+    Ok(BinaryOption::try_from_slice(data)?)
+}
+
+/// A decoded pool along with the addresses needed to build its instructions
+#[derive(Debug, Clone)]
+pub struct Pool {
+    pub program_id: Pubkey,
+    pub address: Pubkey,
+    pub state: BinaryOption,
+}
+
+impl Pool {
+    pub fn new(program_id: Pubkey, address: Pubkey, state: BinaryOption) -> Self {
+        Self {
+            program_id,
+            address,
+            state,
+        }
+    }
+
+    pub fn escrow_authority(&self) -> Pubkey {
+        escrow_authority(
+            &self.program_id,
+            &self.state.long_mint_account_pubkey,
+            &self.state.short_mint_account_pubkey,
+        )
+        .0
+    }
+
+    /// Fee vault that has to be passed to trades and collections, if the pool charges fees
+    pub fn fee_vault(&self) -> Option<Pubkey> {
+        if self.state.has_fees() {
+            Some(self.state.fee_vault_account_pubkey)
+        } else {
+            None
+        }
+    }
+
+    /// Associated token accounts of `wallet` for this pool
+    pub fn position_accounts(&self, wallet: &Pubkey) -> PositionAccounts {
+        PositionAccounts {
+            collateral: get_associated_token_address(
+                wallet,
+                &self.state.escrow_mint_account_pubkey,
+            ),
+            long: get_associated_token_address(wallet, &self.state.long_mint_account_pubkey),
+            short: get_associated_token_address(wallet, &self.state.short_mint_account_pubkey),
+        }
+    }
+}
+
+/// Token accounts that hold the collateral and the positions of a wallet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionAccounts {
+    pub collateral: Pubkey,
+    pub long: Pubkey,
+    pub short: Pubkey,
+}
@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("RPC request failed: {0}")]
+    Rpc(Box<solana_client::client_error::ClientError>),
+    #[error("Invalid pool account: {0}")]
+    InvalidPoolAccount(#[from] std::io::Error),
+    #[error("Pool account is owned by {0}, not by the program")]
+    IncorrectPoolOwner(solana_sdk::pubkey::Pubkey),
+}
+
+impl From<solana_client::client_error::ClientError> for ClientError {
+    fn from(e: solana_client::client_error::ClientError) -> Self {
+        ClientError::Rpc(Box::new(e))
+    }
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
@@ -0,0 +1,124 @@
+use {
+    crate::accounts::Pool,
+    binary_option::instruction,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey},
+    spl_associated_token_account::instruction::create_associated_token_account_idempotent,
+};
+
+/// Creates an InitializeBinaryOption instruction. The pool, escrow and both mints are new
+/// accounts that have to sign, `authority` pays for them and becomes the owner of the pool.
+#[allow(clippy::too_many_arguments)]
+pub fn initialize(
+    program_id: Pubkey,
+    pool: Pubkey,
+    escrow_mint: Pubkey,
+    escrow: Pubkey,
+    long_mint: Pubkey,
+    short_mint: Pubkey,
+    authority: Pubkey,
+    decimals: u8,
+    expiry_unix_timestamp: i64,
+) -> Instruction {
+    instruction::initialize_binary_option(
+        program_id,
+        pool,
+        escrow_mint,
+        escrow,
+        long_mint,
+        short_mint,
+        authority,
+        authority,
+        decimals,
+        expiry_unix_timestamp,
+    )
+}
+
+impl Pool {
+    /// Creates the long and short token accounts of `wallet` unless they already exist
+    pub fn create_position_accounts(&self, funder: &Pubkey, wallet: &Pubkey) -> Vec<Instruction> {
+        [
+            self.state.long_mint_account_pubkey,
+            self.state.short_mint_account_pubkey,
+        ]
+        .iter()
+        .map(|mint| {
+            create_associated_token_account_idempotent(funder, wallet, mint, &spl_token::id())
+        })
+        .collect()
+    }
+
+    /// Creates a Trade instruction between the associated token accounts of `buyer` and `seller`
+    pub fn trade(
+        &self,
+        buyer: &Pubkey,
+        seller: &Pubkey,
+        size: u64,
+        buy_price: u64,
+        sell_price: u64,
+    ) -> Instruction {
+        let buyer_accounts = self.position_accounts(buyer);
+        let seller_accounts = self.position_accounts(seller);
+        instruction::trade(
+            self.program_id,
+            self.address,
+            self.state.escrow_account_pubkey,
+            self.state.long_mint_account_pubkey,
+            self.state.short_mint_account_pubkey,
+            *buyer,
+            *seller,
+            buyer_accounts.collateral,
+            seller_accounts.collateral,
+            buyer_accounts.long,
+            buyer_accounts.short,
+            seller_accounts.long,
+            seller_accounts.short,
+            self.escrow_authority(),
+            self.fee_vault(),
+            size,
+            buy_price,
+            sell_price,
+        )
+    }
+
+    /// Creates a Settle instruction, signed by the owner of the pool
+    pub fn settle(&self, winning_mint: &Pubkey, owner: &Pubkey) -> Instruction {
+        instruction::settle(self.program_id, self.address, *winning_mint, *owner)
+    }
+
+    /// Creates a Collect instruction that pays out to the associated token account of `collector`.
+    /// It doesn't need the collector's signature, so anyone can send it.
+    pub fn collect(&self, collector: &Pubkey) -> Instruction {
+        let collector_accounts = self.position_accounts(collector);
+        instruction::collect(
+            self.program_id,
+            self.address,
+            *collector,
+            collector_accounts.long,
+            collector_accounts.short,
+            collector_accounts.collateral,
+            self.state.long_mint_account_pubkey,
+            self.state.short_mint_account_pubkey,
+            self.state.escrow_account_pubkey,
+            self.escrow_authority(),
+            self.fee_vault(),
+        )
+    }
+
+    /// Creates a Collect instruction, signed by `collector`, that pays out to `destination`
+    pub fn collect_to(&self, collector: &Pubkey, destination: &Pubkey) -> Instruction {
+        let collector_accounts = self.position_accounts(collector);
+        instruction::collect_signed(
+            self.program_id,
+            self.address,
+            *collector,
+            collector_accounts.long,
+            collector_accounts.short,
+            *destination,
+            self.state.long_mint_account_pubkey,
+            self.state.short_mint_account_pubkey,
+            self.state.escrow_account_pubkey,
+            self.escrow_authority(),
+            self.fee_vault(),
+        )
+    }
+}
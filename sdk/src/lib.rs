@@ -0,0 +1,9 @@
+//! Client library for the binary option program: account derivation, decoding of pool accounts,
+//! instruction builders that fill in the associated token accounts of traders, and an RPC client.
+
+pub mod accounts;
+pub mod error;
+pub mod instructions;
+pub mod rpc;
+
+pub use binary_option::{id, state::BinaryOption};
@@ -0,0 +1,147 @@
+use {
+    crate::{
+        accounts::{decode_binary_option, Pool},
+        error::{ClientError, ClientResult},
+        instructions,
+    },
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        instruction::Instruction,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature, Signer},
+        transaction::Transaction,
+    },
+};
+
+/// Sends binary option transactions through an RPC node. The payer pays for fees and rent, and
+/// owns the pools it creates.
+pub struct BinaryOptionClient {
+    pub rpc: RpcClient,
+    pub program_id: Pubkey,
+    pub payer: Keypair,
+}
+
+impl BinaryOptionClient {
+    pub fn new(url: &str, program_id: Pubkey, payer: Keypair) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+            program_id,
+            payer,
+        }
+    }
+
+    /// Fetches and decodes a pool. Accounts that are not owned by the program are rejected, since
+    /// anyone can create an account with the same layout.
+    pub fn get_pool(&self, address: &Pubkey) -> ClientResult<Pool> {
+        let account = self.rpc.get_account(address)?;
+        if account.owner != self.program_id {
+            return Err(ClientError::IncorrectPoolOwner(account.owner));
+        }
+        Ok(Pool::new(
+            self.program_id,
+            *address,
+            decode_binary_option(&account.data)?,
+        ))
+    }
+
+    /// Creates a pool that is settled by the payer, and returns its address
+    pub fn initialize(
+        &self,
+        escrow_mint: &Pubkey,
+        decimals: u8,
+        expiry_unix_timestamp: i64,
+    ) -> ClientResult<Pubkey> {
+        let pool = Keypair::new();
+        let escrow = Keypair::new();
+        let long_mint = Keypair::new();
+        let short_mint = Keypair::new();
+        self.send(
+            &[instructions::initialize(
+                self.program_id,
+                pool.pubkey(),
+                *escrow_mint,
+                escrow.pubkey(),
+                long_mint.pubkey(),
+                short_mint.pubkey(),
+                self.payer.pubkey(),
+                decimals,
+                expiry_unix_timestamp,
+            )],
+            &[&pool, &escrow, &long_mint, &short_mint],
+        )?;
+        Ok(pool.pubkey())
+    }
+
+    /// Trades `size` contracts, creating the token accounts of both traders if needed. Both
+    /// traders pay from the associated token account of the collateral mint.
+    pub fn trade(
+        &self,
+        pool: &Pubkey,
+        buyer: &Keypair,
+        seller: &Keypair,
+        size: u64,
+        buy_price: u64,
+        sell_price: u64,
+    ) -> ClientResult<Signature> {
+        let pool = self.get_pool(pool)?;
+        let payer = self.payer.pubkey();
+        let mut instructions = pool.create_position_accounts(&payer, &buyer.pubkey());
+        instructions.extend(pool.create_position_accounts(&payer, &seller.pubkey()));
+        instructions.push(pool.trade(
+            &buyer.pubkey(),
+            &seller.pubkey(),
+            size,
+            buy_price,
+            sell_price,
+        ));
+        self.send(&instructions, &[buyer, seller])
+    }
+
+    pub fn settle(
+        &self,
+        pool: &Pubkey,
+        owner: &Keypair,
+        winning_mint: &Pubkey,
+    ) -> ClientResult<Signature> {
+        let pool = self.get_pool(pool)?;
+        self.send(&[pool.settle(winning_mint, &owner.pubkey())], &[owner])
+    }
+
+    /// Collects the positions of `collector` into their associated token account
+    pub fn collect(&self, pool: &Pubkey, collector: &Pubkey) -> ClientResult<Signature> {
+        let pool = self.get_pool(pool)?;
+        self.send(&[pool.collect(collector)], &[])
+    }
+
+    /// Collects the positions of `collector`, who signs, into `destination`
+    pub fn collect_to(
+        &self,
+        pool: &Pubkey,
+        collector: &Keypair,
+        destination: &Pubkey,
+    ) -> ClientResult<Signature> {
+        let pool = self.get_pool(pool)?;
+        self.send(
+            &[pool.collect_to(&collector.pubkey(), destination)],
+            &[collector],
+        )
+    }
+
+    /// Signs with the payer and `signers`, and waits for confirmation
+    pub fn send(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> ClientResult<Signature> {
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            self.rpc.get_latest_blockhash()?,
+        );
+        Ok(self.rpc.send_and_confirm_transaction(&transaction)?)
+    }
+}
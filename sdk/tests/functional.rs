@@ -0,0 +1,213 @@
+use {
+    binary_option::{id, processor::Processor},
+    binary_option_client::{
+        accounts::{decode_binary_option, escrow_authority, Pool},
+        instructions,
+    },
+    solana_program_test::{processor, ProgramTest, ProgramTestContext},
+    solana_sdk::{
+        instruction::Instruction,
+        program_option::COption,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction,
+        transaction::Transaction,
+    },
+    spl_associated_token_account::instruction::create_associated_token_account,
+    spl_token::state::{Account, Mint},
+};
+
+async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+async fn token_balance(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    let account = context.banks_client.get_account(*address).await.unwrap();
+    Account::unpack(&account.unwrap().data).unwrap().amount
+}
+
+/// Creates a pool with the client's builders and decodes it like `BinaryOptionClient::get_pool`
+async fn initialize(context: &mut ProgramTestContext, escrow_mint: &Keypair) -> Pool {
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+    process(
+        context,
+        &[
+            system_instruction::create_account(
+                &payer,
+                &escrow_mint.pubkey(),
+                rent.minimum_balance(Mint::LEN),
+                Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &escrow_mint.pubkey(),
+                &payer,
+                None,
+                0,
+            )
+            .unwrap(),
+        ],
+        &[escrow_mint],
+    )
+    .await;
+
+    let pool = Keypair::new();
+    let escrow = Keypair::new();
+    let long_mint = Keypair::new();
+    let short_mint = Keypair::new();
+    process(
+        context,
+        &[instructions::initialize(
+            id(),
+            pool.pubkey(),
+            escrow_mint.pubkey(),
+            escrow.pubkey(),
+            long_mint.pubkey(),
+            short_mint.pubkey(),
+            payer,
+            2,
+            0,
+        )],
+        &[&pool, &escrow, &long_mint, &short_mint],
+    )
+    .await;
+    let account = context
+        .banks_client
+        .get_account(pool.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, id());
+    Pool::new(
+        id(),
+        pool.pubkey(),
+        decode_binary_option(&account.data).unwrap(),
+    )
+}
+
+/// Creates the associated token accounts of `wallet` and funds its collateral account
+async fn fund(context: &mut ProgramTestContext, pool: &Pool, wallet: &Pubkey, amount: u64) {
+    let payer = context.payer.pubkey();
+    let mut instructions = vec![create_associated_token_account(
+        &payer,
+        wallet,
+        &pool.state.escrow_mint_account_pubkey,
+        &spl_token::id(),
+    )];
+    instructions.extend(pool.create_position_accounts(&payer, wallet));
+    instructions.push(
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &pool.state.escrow_mint_account_pubkey,
+            &pool.position_accounts(wallet).collateral,
+            &payer,
+            &[],
+            amount,
+        )
+        .unwrap(),
+    );
+    process(context, &instructions, &[]).await;
+}
+
+#[tokio::test]
+async fn test_escrow_authority_matches_program() {
+    let mut context = ProgramTest::new("binary_option", id(), processor!(Processor::process))
+        .start_with_context()
+        .await;
+    let pool = initialize(&mut context, &Keypair::new()).await;
+
+    // The program hands the mint authority of both mints and the escrow to its PDA
+    let authority = escrow_authority(
+        &id(),
+        &pool.state.long_mint_account_pubkey,
+        &pool.state.short_mint_account_pubkey,
+    )
+    .0;
+    assert_eq!(pool.escrow_authority(), authority);
+    for mint in [
+        pool.state.long_mint_account_pubkey,
+        pool.state.short_mint_account_pubkey,
+    ] {
+        let account = context.banks_client.get_account(mint).await.unwrap();
+        let mint = Mint::unpack(&account.unwrap().data).unwrap();
+        assert_eq!(mint.mint_authority, COption::Some(authority));
+    }
+    let account = context
+        .banks_client
+        .get_account(pool.state.escrow_account_pubkey)
+        .await
+        .unwrap();
+    assert_eq!(
+        Account::unpack(&account.unwrap().data).unwrap().owner,
+        authority
+    );
+}
+
+#[tokio::test]
+async fn test_trade_and_collect() {
+    let mut context = ProgramTest::new("binary_option", id(), processor!(Processor::process))
+        .start_with_context()
+        .await;
+    let pool = initialize(&mut context, &Keypair::new()).await;
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    fund(&mut context, &pool, &alice.pubkey(), 10_000).await;
+    fund(&mut context, &pool, &bob.pubkey(), 10_000).await;
+
+    // Alice buys 10 long tokens at 30 from Bob
+    process(
+        &mut context,
+        &[pool.trade(&alice.pubkey(), &bob.pubkey(), 10, 30, 70)],
+        &[&alice, &bob],
+    )
+    .await;
+    let owner = context.payer.pubkey();
+    process(
+        &mut context,
+        &[pool.settle(&pool.state.long_mint_account_pubkey, &owner)],
+        &[],
+    )
+    .await;
+
+    // Alice's payout goes to her associated token account without her signature, Bob signs to
+    // send his (empty) payout elsewhere
+    let alice_collateral = pool.position_accounts(&alice.pubkey()).collateral;
+    process(&mut context, &[pool.collect(&alice.pubkey())], &[]).await;
+    assert_eq!(
+        token_balance(&mut context, &alice_collateral).await,
+        9_700 + 1_000
+    );
+    let bob_long = pool.position_accounts(&bob.pubkey()).long;
+    let bob_short = pool.position_accounts(&bob.pubkey()).short;
+    process(
+        &mut context,
+        &[pool.collect_to(&bob.pubkey(), &alice_collateral)],
+        &[&bob],
+    )
+    .await;
+    assert_eq!(token_balance(&mut context, &bob_long).await, 0);
+    assert_eq!(token_balance(&mut context, &bob_short).await, 0);
+    assert_eq!(
+        token_balance(&mut context, &pool.state.escrow_account_pubkey).await,
+        0
+    );
+}